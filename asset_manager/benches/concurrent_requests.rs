@@ -0,0 +1,122 @@
+//! Many threads requesting and reading the same meshes every frame, the way parallel systems do.
+//!
+//! Compares sharing the manager directly against putting it behind a single lock, which is what
+//! every caller had to do while requesting an asset needed `&mut AssetManager`. Then compares
+//! the sharded `AssetStorage` underneath against one whose slots are all behind a single lock.
+//!
+//! Run with `cargo bench -p asset_manager`
+
+use std::{
+    hint::black_box,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use asset_manager::{AssetManager, AssetStorage};
+
+const THREADS: usize = 8;
+const REQUESTS_PER_THREAD: usize = 200_000;
+const MESHES: usize = 64;
+
+/// Request a mesh and read from it, the same work every benchmark does per request
+fn request(asset_manager: &AssetManager, id: &str) -> u32 {
+    let handle = asset_manager.get_mesh(id).weak();
+
+    match asset_manager.mesh(handle) {
+        Some(mesh) => mesh.lock().unwrap().vertex_count,
+        None => 0,
+    }
+}
+
+/// Look up an asset by its id and read from it, which is what requesting one does in the storage
+fn lookup(storage: &AssetStorage<u32>, id: &str) -> u32 {
+    match storage.handle(id).and_then(|handle| storage.get(handle)) {
+        Some(asset) => *asset.lock().unwrap(),
+        None => 0,
+    }
+}
+
+/// Run `work` on every thread at once and time until the last one is done
+fn run(work: impl Fn(usize) + Sync) -> Duration {
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for thread in 0..THREADS {
+            let work = &work;
+            scope.spawn(move || work(thread));
+        }
+    });
+
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let requests = THREADS * REQUESTS_PER_THREAD;
+
+    println!(
+        "{:<12} {:>8.2} ms {:>8.1} ns/request",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        elapsed.as_nanos() as f64 / requests as f64
+    );
+}
+
+fn main() {
+    let ids: Vec<String> = (1..=MESHES)
+        .map(|size| format!("builtin://plane?size={}", size))
+        .collect();
+
+    let asset_manager = AssetManager::new();
+
+    for id in &ids {
+        asset_manager.get_mesh(id);
+    }
+
+    // Every thread goes through the ids in a different order, like systems with their own entities
+    let work = |asset_manager: &AssetManager, thread: usize| {
+        for i in 0..REQUESTS_PER_THREAD {
+            black_box(request(asset_manager, &ids[(i + thread * 7) % MESHES]));
+        }
+    };
+
+    let single = Instant::now();
+    for thread in 0..THREADS {
+        work(&asset_manager, thread);
+    }
+    report("1 thread", single.elapsed());
+
+    let locked = Mutex::new(asset_manager);
+    report(
+        "locked",
+        run(|thread| {
+            for i in 0..REQUESTS_PER_THREAD {
+                black_box(request(
+                    &locked.lock().unwrap(),
+                    &ids[(i + thread * 7) % MESHES],
+                ));
+            }
+        }),
+    );
+
+    let asset_manager = locked.into_inner().unwrap();
+    report("shared", run(|thread| work(&asset_manager, thread)));
+
+    for (name, storage) in [
+        ("single lock", AssetStorage::with_shards(1)),
+        ("sharded", AssetStorage::new()),
+    ] {
+        for (value, id) in ids.iter().enumerate() {
+            storage.insert(id, value as u32);
+        }
+
+        report(
+            name,
+            run(|thread| {
+                for i in 0..REQUESTS_PER_THREAD {
+                    black_box(lookup(&storage, &ids[(i + thread * 7) % MESHES]));
+                }
+            }),
+        );
+    }
+}
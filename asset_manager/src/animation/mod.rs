@@ -0,0 +1,488 @@
+use gltf::animation::util::ReadOutputs;
+use log::warn;
+
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    gltf_file::{self, GltfFile},
+    loader::{LoadContext, LoadableAsset},
+    skeleton::{JointTransform, Skeleton},
+    AssetError,
+};
+
+/// The array of a glTF file animation clips are read from, see `gltf_file::fragment_id`
+const KIND: &str = "animations";
+
+/// What an animation channel changes about its node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationProperty {
+    Translation,
+    Rotation,
+    Scale,
+    /// The weights of the morph targets of the node's mesh
+    Weights,
+}
+
+/// How values in between two keyframes are found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// The value of the earlier keyframe is held until the next one
+    Step,
+    /// Linear for everything except rotations, which take the shortest arc between the two
+    Linear,
+    /// A Hermite spline through the keyframes, shaped by their tangents
+    CubicSpline,
+}
+
+/// The keyframes of a single property of a single node
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationChannel {
+    /// Index of the node in the glTF file, see `Joint::node`
+    pub node: usize,
+    pub property: AnimationProperty,
+    pub interpolation: Interpolation,
+    /// Time of every keyframe in seconds, ascending
+    pub times: Vec<f32>,
+    /// `components` floats for every keyframe, rotations being xyzw quaternions.
+    /// Cubic spline keyframes are stored as in-tangent, value and out-tangent
+    pub values: Vec<f32>,
+    /// Floats in a single value, 3 or 4 for transforms and the morph target count for weights
+    pub components: usize,
+}
+
+impl AnimationChannel {
+    /// The value of the property at the time, holding the first and last keyframes outside of them
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        if self.times.is_empty() {
+            return vec![];
+        }
+
+        // The first keyframe after the time
+        let next = self.times.partition_point(|keyframe| *keyframe <= time);
+
+        let value = match next {
+            0 => self.keyframe(0).to_vec(),
+            next if next == self.times.len() => self.keyframe(next - 1).to_vec(),
+            next => {
+                let previous = next - 1;
+                let duration = self.times[next] - self.times[previous];
+                let t = (time - self.times[previous]) / duration;
+
+                match self.interpolation {
+                    Interpolation::Step => self.keyframe(previous).to_vec(),
+                    Interpolation::Linear if self.property == AnimationProperty::Rotation => slerp(
+                        &to_quat(self.keyframe(previous)),
+                        &to_quat(self.keyframe(next)),
+                        t,
+                    )
+                    .coords
+                    .as_slice()
+                    .to_vec(),
+                    Interpolation::Linear => self
+                        .keyframe(previous)
+                        .iter()
+                        .zip(self.keyframe(next))
+                        .map(|(from, to)| from + (to - from) * t)
+                        .collect(),
+                    Interpolation::CubicSpline => self.cubic_spline(previous, next, duration, t),
+                }
+            }
+        };
+
+        match self.property {
+            AnimationProperty::Rotation => to_quat(&value).normalize().coords.as_slice().to_vec(),
+            _ => value,
+        }
+    }
+
+    /// Floats stored for every keyframe, which has two extra tangents with a cubic spline
+    fn stride(&self) -> usize {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.components * 3,
+            Interpolation::Step | Interpolation::Linear => self.components,
+        }
+    }
+
+    /// The part of a keyframe at `offset` values into it, the value itself or one of its tangents
+    fn keyframe_part(&self, keyframe: usize, offset: usize) -> &[f32] {
+        let start = keyframe * self.stride() + offset * self.components;
+
+        &self.values[start..start + self.components]
+    }
+
+    fn keyframe(&self, keyframe: usize) -> &[f32] {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.keyframe_part(keyframe, 1),
+            Interpolation::Step | Interpolation::Linear => self.keyframe_part(keyframe, 0),
+        }
+    }
+
+    fn cubic_spline(&self, previous: usize, next: usize, duration: f32, t: f32) -> Vec<f32> {
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        let from = self.keyframe_part(previous, 1);
+        let out_tangent = self.keyframe_part(previous, 2);
+        let in_tangent = self.keyframe_part(next, 0);
+        let to = self.keyframe_part(next, 1);
+
+        (0..self.components)
+            .map(|i| {
+                (2.0 * t3 - 3.0 * t2 + 1.0) * from[i]
+                    + (t3 - 2.0 * t2 + t) * duration * out_tangent[i]
+                    + (-2.0 * t3 + 3.0 * t2) * to[i]
+                    + (t3 - t2) * duration * in_tangent[i]
+            })
+            .collect()
+    }
+}
+
+fn to_quat(value: &[f32]) -> glm::Quat {
+    glm::quat(value[0], value[1], value[2], value[3])
+}
+
+/// Spherical interpolation along the shortest arc between two unit quaternions
+fn slerp(from: &glm::Quat, to: &glm::Quat, t: f32) -> glm::Quat {
+    let dot = glm::quat_dot(from, to);
+
+    // A quaternion and its negation are the same rotation, the one closer to `from` has the shorter arc
+    let (to, dot) = match dot < 0.0 {
+        true => (-to, -dot),
+        false => (*to, dot),
+    };
+
+    // Nearly equal rotations would divide by almost zero, normalized lerp is just as good there
+    if dot > 0.9995 {
+        return glm::quat_lerp(from, &to, t).normalize();
+    }
+
+    let angle = dot.acos();
+
+    (from * ((1.0 - t) * angle).sin() + to * (t * angle).sin()) / angle.sin()
+}
+
+/// The channels of an animation clip, waiting to be published into it
+pub struct AnimationData {
+    pub name: Option<String>,
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+/// An animation of a glTF file, moving the joints of a skeleton or the weights of morph targets
+///
+/// Animations are referenced as `<path>#animations/<index or name>`, for example
+/// `robot.glb#animations/walk`. Anything that parses as a number is taken as an index
+pub struct AnimationClip {
+    pub asset_info: AssetInfo,
+    pub name: Option<String>,
+    /// Time of the last keyframe of any channel in seconds
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AnimationClip {
+    pub fn unload(&mut self) {
+        self.channels = vec![];
+        self.asset_info.status = AssetStatus::Unloaded;
+    }
+
+    /// Overwrite the transforms of the joints the clip animates with their values at the time,
+    /// leaving the rest of the pose alone so several clips can be layered
+    pub fn sample_pose(&self, time: f32, skeleton: &Skeleton, pose: &mut [JointTransform]) {
+        for channel in &self.channels {
+            let joint = match skeleton.joint_index(channel.node) {
+                Some(joint) => &mut pose[joint],
+                None => continue,
+            };
+
+            let value = channel.sample(time);
+
+            match channel.property {
+                AnimationProperty::Translation => joint.translation = glm::make_vec3(&value),
+                AnimationProperty::Rotation => joint.rotation = to_quat(&value),
+                AnimationProperty::Scale => joint.scale = glm::make_vec3(&value),
+                AnimationProperty::Weights => {}
+            }
+        }
+    }
+
+    /// The morph target weights of the node at the time, if the clip animates them
+    pub fn sample_weights(&self, time: f32, node: usize) -> Option<Vec<f32>> {
+        self.channels
+            .iter()
+            .find(|channel| channel.node == node && channel.property == AnimationProperty::Weights)
+            .map(|channel| channel.sample(time))
+    }
+
+    fn read_animation(
+        animation: &gltf::Animation,
+        gltf: &GltfFile,
+    ) -> Result<AnimationData, AssetError> {
+        let mut channels = vec![];
+
+        for channel in animation.channels() {
+            let reader = channel.reader(|buffer| Some(&gltf.buffers[buffer.index()]));
+
+            let times: Vec<f32> = match reader.read_inputs() {
+                Some(times) => times.collect(),
+                None => return Err(AssetError::MissingAttribute("input".to_owned())),
+            };
+
+            let (property, values): (AnimationProperty, Vec<f32>) = match reader.read_outputs() {
+                Some(ReadOutputs::Translations(values)) => {
+                    (AnimationProperty::Translation, values.flatten().collect())
+                }
+                Some(ReadOutputs::Rotations(values)) => (
+                    AnimationProperty::Rotation,
+                    values.into_f32().flatten().collect(),
+                ),
+                Some(ReadOutputs::Scales(values)) => {
+                    (AnimationProperty::Scale, values.flatten().collect())
+                }
+                Some(ReadOutputs::MorphTargetWeights(values)) => {
+                    (AnimationProperty::Weights, values.into_f32().collect())
+                }
+                None => return Err(AssetError::MissingAttribute("output".to_owned())),
+            };
+
+            let interpolation = match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+            };
+
+            let values_per_keyframe = match interpolation {
+                Interpolation::CubicSpline => times.len() * 3,
+                Interpolation::Step | Interpolation::Linear => times.len(),
+            };
+
+            // There is one weight for every morph target, which only the output count tells
+            let components = match property {
+                AnimationProperty::Translation | AnimationProperty::Scale => 3,
+                AnimationProperty::Rotation => 4,
+                AnimationProperty::Weights => values.len() / values_per_keyframe.max(1),
+            };
+
+            if values.len() != values_per_keyframe * components {
+                return Err(AssetError::Parse(format!(
+                    "Animation channel has {} values for {} keyframes",
+                    values.len(),
+                    times.len()
+                )));
+            }
+
+            channels.push(AnimationChannel {
+                node: channel.target().node().index(),
+                property,
+                interpolation,
+                times,
+                values,
+                components,
+            });
+        }
+
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+
+        Ok(AnimationData {
+            name: animation.name().map(str::to_owned),
+            duration,
+            channels,
+        })
+    }
+}
+
+impl LoadableAsset for AnimationClip {
+    type Data = AnimationData;
+
+    fn read(id: &str, context: &LoadContext) -> Result<AnimationData, AssetError> {
+        let (path, name) = match gltf_file::split_fragment_id(id, KIND) {
+            Some((path, name)) => (path, name),
+            None => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+        };
+
+        let gltf = GltfFile::open(path, &context.vfs)?;
+
+        let animation = match name.parse::<usize>() {
+            Ok(index) => gltf.document.animations().nth(index),
+            Err(_) => gltf
+                .document
+                .animations()
+                .find(|animation| animation.name() == Some(name)),
+        };
+
+        match animation {
+            Some(animation) => AnimationClip::read_animation(&animation, &gltf),
+            None => Err(AssetError::Parse(format!(
+                "{} has no animation {}",
+                path, name
+            ))),
+        }
+    }
+
+    fn publish(&mut self, data: Result<AnimationData, AssetError>) {
+        match data {
+            Ok(data) => {
+                self.name = data.name;
+                self.duration = data.duration;
+                self.channels = data.channels;
+                self.asset_info.set_loaded();
+            }
+            Err(e) => {
+                warn!("Failed to load animation {}: {}", self.asset_info.id, e);
+
+                self.asset_info.set_failed(e);
+            }
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_fixture() -> AnimationData {
+        AnimationClip::read(
+            "fixtures/skinned.gltf#animations/bend",
+            &LoadContext::default(),
+        )
+        .unwrap()
+    }
+
+    fn channel(data: &AnimationData, property: AnimationProperty) -> &AnimationChannel {
+        data.channels
+            .iter()
+            .find(|channel| channel.property == property)
+            .unwrap()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_channels() {
+        let data = read_fixture();
+
+        assert_eq!(data.name.as_deref(), Some("bend"));
+        assert_eq!(data.duration, 1.0);
+        assert_eq!(data.channels.len(), 3);
+
+        let scale = channel(&data, AnimationProperty::Scale);
+        assert_eq!(scale.interpolation, Interpolation::CubicSpline);
+        assert_eq!(scale.values.len(), 2 * 3 * 3);
+
+        // Reading by index finds the same animation as by name
+        let by_index = AnimationClip::read(
+            "fixtures/skinned.gltf#animations/0",
+            &LoadContext::default(),
+        )
+        .unwrap();
+        assert_eq!(by_index.channels, data.channels);
+    }
+
+    #[test]
+    fn test_step() {
+        let data = read_fixture();
+        let translation = channel(&data, AnimationProperty::Translation);
+
+        assert_close(&translation.sample(0.99), &[0.0, 0.0, 0.0]);
+        assert_close(&translation.sample(1.0), &[0.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_linear_rotation() {
+        let data = read_fixture();
+        let rotation = channel(&data, AnimationProperty::Rotation);
+
+        // Halfway through a quarter turn around z is an eighth of a turn
+        let angle = std::f32::consts::PI / 8.0;
+        assert_close(&rotation.sample(0.5), &[0.0, 0.0, angle.sin(), angle.cos()]);
+
+        // Outside the keyframes the closest one is held
+        assert_close(&rotation.sample(-1.0), &[0.0, 0.0, 0.0, 1.0]);
+        assert_close(&rotation.sample(2.0), &rotation.sample(1.0));
+    }
+
+    #[test]
+    fn test_cubic_spline() {
+        let data = read_fixture();
+        let scale = channel(&data, AnimationProperty::Scale);
+
+        // With flat tangents the spline eases in and out between 1 and 2
+        assert_close(&scale.sample(0.5), &[1.5, 1.5, 1.5]);
+        assert!(scale.sample(0.25)[0] < 1.25);
+        assert_close(&scale.sample(1.0), &[2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_sample_pose() {
+        let skeleton = Skeleton {
+            asset_info: AssetInfo::new("fixtures/skinned.gltf#skins/0"),
+            joints: Skeleton::read("fixtures/skinned.gltf#skins/0", &LoadContext::default())
+                .unwrap(),
+        };
+        let data = read_fixture();
+        let clip = AnimationClip {
+            asset_info: AssetInfo::new("fixtures/skinned.gltf#animations/bend"),
+            name: data.name,
+            duration: data.duration,
+            channels: data.channels,
+        };
+
+        let mut pose = skeleton.rest_pose();
+        clip.sample_pose(1.0, &skeleton, &mut pose);
+
+        assert_eq!(pose[0].translation, glm::vec3(0.0, 2.0, 0.0));
+        assert_eq!(pose[0].scale, glm::vec3(2.0, 2.0, 2.0));
+
+        // The knee keeps its rest translation, only its rotation is animated
+        assert_eq!(pose[1].translation, glm::vec3(0.0, 1.0, 0.0));
+        assert_eq!(clip.sample_weights(1.0, 2), None);
+    }
+
+    #[test]
+    fn test_sample_weights() {
+        let data = AnimationClip::read(
+            "fixtures/morph_targets.gltf#animations/smile",
+            &LoadContext::default(),
+        )
+        .unwrap();
+
+        // One weight for each of the mesh's two targets
+        assert_eq!(data.channels[0].components, 2);
+
+        let clip = AnimationClip {
+            asset_info: AssetInfo::new("fixtures/morph_targets.gltf#animations/smile"),
+            name: data.name,
+            duration: data.duration,
+            channels: data.channels,
+        };
+
+        assert_eq!(clip.sample_weights(0.5, 0), Some(vec![0.5, 0.25]));
+        assert_eq!(clip.sample_weights(0.5, 1), None);
+    }
+
+    #[test]
+    fn test_read_missing_animation() {
+        assert!(AnimationClip::read(
+            "fixtures/skinned.gltf#animations/run",
+            &LoadContext::default()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// Why an asset failed to load
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetError {
+    /// The file could not be opened or read
+    Io(String),
+    /// The file was read but its contents could not be decoded
+    Parse(String),
+    /// The file extension is not one any importer handles
+    UnsupportedFormat(String),
+    /// None of the primitives of a mesh are triangles, which is the only mode that is drawn
+    UnsupportedPrimitiveMode(String),
+    /// A primitive lacks an attribute it cannot be drawn without
+    MissingAttribute(String),
+    /// Reading the asset panicked, with the panic's message
+    Panicked(String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(e) => write!(f, "Failed to read file: {}", e),
+            AssetError::Parse(e) => write!(f, "Failed to parse file: {}", e),
+            AssetError::UnsupportedFormat(format) => write!(f, "Unsupported format: {}", format),
+            AssetError::UnsupportedPrimitiveMode(mode) => {
+                write!(f, "Unsupported primitive mode: {}", mode)
+            }
+            AssetError::MissingAttribute(attribute) => {
+                write!(f, "Missing attribute: {}", attribute)
+            }
+            AssetError::Panicked(message) => write!(f, "Load panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<std::io::Error> for AssetError {
+    fn from(e: std::io::Error) -> Self {
+        AssetError::Io(e.to_string())
+    }
+}
+
+impl From<gltf::Error> for AssetError {
+    fn from(e: gltf::Error) -> Self {
+        match e {
+            gltf::Error::Io(e) => AssetError::Io(e.to_string()),
+            e => AssetError::Parse(e.to_string()),
+        }
+    }
+}
+
+impl From<image::ImageError> for AssetError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => AssetError::Io(e.to_string()),
+            e => AssetError::Parse(e.to_string()),
+        }
+    }
+}
+
+impl From<tobj::LoadError> for AssetError {
+    fn from(e: tobj::LoadError) -> Self {
+        match e {
+            tobj::LoadError::OpenFileFailed | tobj::LoadError::ReadError => {
+                AssetError::Io(e.to_string())
+            }
+            e => AssetError::Parse(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use crate::{AssetError, UntypedHandle};
+
+/// Something that happened to an asset, see `AssetManager::drain_events`
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetEvent {
+    /// The asset finished loading for the first time
+    Loaded(UntypedHandle),
+    /// Loading or reloading the asset failed, it is left `AssetStatus::Invalid`
+    Failed(UntypedHandle, AssetError),
+    /// The asset finished loading again after a reload
+    Reloaded(UntypedHandle),
+    /// The asset was removed and its handles are no longer valid
+    Unloaded(UntypedHandle),
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq)]
+use crate::AssetError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AssetStatus {
     Invalid,
     Unloaded,
@@ -10,4 +12,26 @@ pub enum AssetStatus {
 pub struct AssetInfo {
     pub id: String,
     pub status: AssetStatus,
+    /// Why the last load failed, set whenever the status is `AssetStatus::Invalid`
+    pub error: Option<AssetError>,
+}
+
+impl AssetInfo {
+    pub fn new(id: &str) -> AssetInfo {
+        AssetInfo {
+            id: id.to_owned(),
+            status: AssetStatus::Unloaded,
+            error: None,
+        }
+    }
+
+    pub fn set_loaded(&mut self) {
+        self.status = AssetStatus::Loaded;
+        self.error = None;
+    }
+
+    pub fn set_failed(&mut self, error: AssetError) {
+        self.status = AssetStatus::Invalid;
+        self.error = Some(error);
+    }
 }
@@ -0,0 +1,338 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::Write,
+    path::PathBuf,
+};
+
+use crate::{gltf_file, is_builtin, vfs::Vfs, UntypedHandle};
+
+/// Something an asset is made from, other than its own file
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Dependency {
+    /// A file read along with the asset's own, like a glTF buffer or an MTL file
+    File(PathBuf),
+    /// Another asset, by id
+    Asset(String),
+}
+
+#[derive(Clone)]
+struct Node {
+    id: String,
+    /// The file the asset itself is read from, builtin assets have none
+    source: Option<PathBuf>,
+    dependencies: Vec<Dependency>,
+}
+
+/// What every requested asset depends on, as of its last successful load
+///
+/// Reloading or unloading an asset reloads every asset depending on it, see `AssetManager::reload`
+#[derive(Clone, Default)]
+pub struct DependencyGraph {
+    nodes: HashMap<UntypedHandle, Node>,
+    /// Dependency files found by loads since `take_new_files` was last called
+    new_files: Vec<(UntypedHandle, PathBuf)>,
+}
+
+impl DependencyGraph {
+    pub(crate) fn insert(&mut self, handle: UntypedHandle, id: &str) {
+        let source = match is_builtin(id) {
+            true => None,
+            false => Some(PathBuf::from(gltf_file::source_file(id))),
+        };
+
+        self.nodes.insert(
+            handle,
+            Node {
+                id: id.to_owned(),
+                source,
+                dependencies: vec![],
+            },
+        );
+    }
+
+    pub(crate) fn remove(&mut self, handle: UntypedHandle) {
+        self.nodes.remove(&handle);
+    }
+
+    /// Replace the dependencies of an asset after it loaded, ignored if it was removed meanwhile
+    pub(crate) fn set_dependencies(
+        &mut self,
+        handle: UntypedHandle,
+        dependencies: Vec<Dependency>,
+    ) {
+        let node = match self.nodes.get_mut(&handle) {
+            Some(node) => node,
+            None => return,
+        };
+
+        for dependency in &dependencies {
+            if let Dependency::File(file) = dependency {
+                if !node.dependencies.contains(dependency) {
+                    self.new_files.push((handle, file.clone()));
+                }
+            }
+        }
+
+        node.dependencies = dependencies;
+    }
+
+    pub(crate) fn take_new_files(&mut self) -> Vec<(UntypedHandle, PathBuf)> {
+        std::mem::take(&mut self.new_files)
+    }
+
+    pub fn id(&self, handle: UntypedHandle) -> Option<&str> {
+        self.nodes.get(&handle).map(|node| node.id.as_str())
+    }
+
+    pub fn dependencies(&self, handle: UntypedHandle) -> &[Dependency] {
+        match self.nodes.get(&handle) {
+            Some(node) => &node.dependencies,
+            None => &[],
+        }
+    }
+
+    /// The assets depending directly on the asset with the id
+    pub fn dependents(&self, id: &str) -> Vec<UntypedHandle> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| {
+                node.dependencies
+                    .iter()
+                    .any(|dependency| matches!(dependency, Dependency::Asset(asset) if asset == id))
+            })
+            .map(|(handle, _)| *handle)
+            .collect()
+    }
+
+    /// Every asset depending on the assets, directly or through others, nearest first.
+    /// The assets themselves are not included
+    pub fn transitive_dependents(&self, handles: &[UntypedHandle]) -> Vec<UntypedHandle> {
+        let mut visited: HashSet<UntypedHandle> = handles.iter().copied().collect();
+        let mut queue: VecDeque<UntypedHandle> = handles.iter().copied().collect();
+        let mut dependents = vec![];
+
+        while let Some(handle) = queue.pop_front() {
+            let id = match self.id(handle) {
+                Some(id) => id,
+                None => continue,
+            };
+
+            for dependent in self.dependents(id) {
+                if visited.insert(dependent) {
+                    dependents.push(dependent);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        dependents
+    }
+
+    /// Every asset with its id and dependencies
+    pub fn iter(&self) -> impl Iterator<Item = (UntypedHandle, &str, &[Dependency])> {
+        self.nodes
+            .iter()
+            .map(|(handle, node)| (*handle, node.id.as_str(), node.dependencies.as_slice()))
+    }
+
+    /// Every file an asset is read from, their own files included,
+    /// files of the mounts that are not in here are not used by any requested asset
+    pub fn files(&self) -> BTreeSet<PathBuf> {
+        self.nodes
+            .values()
+            .flat_map(|node| {
+                node.source
+                    .iter()
+                    .cloned()
+                    .chain(
+                        node.dependencies
+                            .iter()
+                            .filter_map(|dependency| match dependency {
+                                Dependency::File(file) => Some(file.clone()),
+                                Dependency::Asset(_) => None,
+                            }),
+                    )
+            })
+            .collect()
+    }
+
+    /// Files that are not in any mount and assets that were never requested,
+    /// along with the id of the asset needing them
+    pub fn missing(&self, vfs: &Vfs) -> Vec<(&str, Dependency)> {
+        let ids: HashSet<&str> = self.nodes.values().map(|node| node.id.as_str()).collect();
+
+        let mut missing = vec![];
+
+        for node in self.nodes.values() {
+            if let Some(source) = &node.source {
+                if !vfs.exists(source) {
+                    missing.push((node.id.as_str(), Dependency::File(source.clone())));
+                }
+            }
+
+            for dependency in &node.dependencies {
+                let found = match dependency {
+                    Dependency::File(file) => vfs.exists(file),
+                    Dependency::Asset(id) => ids.contains(id.as_str()),
+                };
+
+                if !found {
+                    missing.push((node.id.as_str(), dependency.clone()));
+                }
+            }
+        }
+
+        missing.sort_by(|a, b| a.0.cmp(b.0));
+
+        missing
+    }
+
+    /// The graph in Graphviz dot format, with an edge from every asset to what it depends on
+    pub fn to_dot(&self) -> String {
+        let mut nodes: Vec<&Node> = self.nodes.values().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut dot = String::from("digraph assets {\n");
+
+        for node in nodes {
+            let _ = writeln!(dot, "    {:?};", node.id);
+
+            for dependency in &node.dependencies {
+                let _ = match dependency {
+                    Dependency::File(file) => writeln!(
+                        dot,
+                        "    {:?} -> {:?} [style=dashed];",
+                        node.id,
+                        file.to_string_lossy()
+                    ),
+                    Dependency::Asset(id) => writeln!(dot, "    {:?} -> {:?};", node.id, id),
+                };
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Handle;
+
+    fn handle(index: u32) -> UntypedHandle {
+        UntypedHandle::Texture(Handle::new(index, 0))
+    }
+
+    /// A texture, a material using it and a scene using the material
+    fn chain() -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+
+        graph.insert(handle(0), "textures/a.png");
+        graph.insert(handle(1), "materials/a.toml");
+        graph.insert(handle(2), "scenes/a.toml");
+        graph.set_dependencies(
+            handle(1),
+            vec![Dependency::Asset("textures/a.png".to_owned())],
+        );
+        graph.set_dependencies(
+            handle(2),
+            vec![Dependency::Asset("materials/a.toml".to_owned())],
+        );
+
+        graph
+    }
+
+    #[test]
+    fn test_transitive_dependents() {
+        let graph = chain();
+
+        assert_eq!(graph.dependents("textures/a.png"), vec![handle(1)]);
+        assert_eq!(
+            graph.transitive_dependents(&[handle(0)]),
+            vec![handle(1), handle(2)]
+        );
+        assert!(graph.transitive_dependents(&[handle(2)]).is_empty());
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut graph = chain();
+        graph.set_dependencies(
+            handle(0),
+            vec![Dependency::Asset("scenes/a.toml".to_owned())],
+        );
+
+        assert_eq!(
+            graph.transitive_dependents(&[handle(0)]),
+            vec![handle(1), handle(2)]
+        );
+    }
+
+    #[test]
+    fn test_new_files() {
+        let mut graph = DependencyGraph::default();
+        graph.insert(handle(0), "models/a.gltf");
+
+        let buffer = Dependency::File(PathBuf::from("models/a.bin"));
+        graph.set_dependencies(handle(0), vec![buffer.clone()]);
+        graph.set_dependencies(handle(0), vec![buffer]);
+
+        // Only files the asset did not already depend on are new
+        assert_eq!(
+            graph.take_new_files(),
+            vec![(handle(0), PathBuf::from("models/a.bin"))]
+        );
+        assert!(graph.take_new_files().is_empty());
+
+        // Removed assets get no dependencies
+        graph.set_dependencies(handle(1), vec![Dependency::Asset("a".to_owned())]);
+        assert!(graph.dependencies(handle(1)).is_empty());
+    }
+
+    #[test]
+    fn test_files_and_missing() {
+        let mut graph = DependencyGraph::default();
+        graph.insert(handle(0), "fixtures/two_materials.obj");
+        graph.insert(handle(1), "builtin://cube");
+        graph.set_dependencies(
+            handle(0),
+            vec![
+                Dependency::File(PathBuf::from("fixtures/two_materials.mtl")),
+                Dependency::File(PathBuf::from("fixtures/missing.mtl")),
+                Dependency::Asset("textures/missing.png".to_owned()),
+            ],
+        );
+
+        assert_eq!(
+            graph.files().into_iter().collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("fixtures/missing.mtl"),
+                PathBuf::from("fixtures/two_materials.mtl"),
+                PathBuf::from("fixtures/two_materials.obj"),
+            ]
+        );
+        assert_eq!(
+            graph.missing(&Vfs::working_directory()),
+            vec![
+                (
+                    "fixtures/two_materials.obj",
+                    Dependency::File(PathBuf::from("fixtures/missing.mtl"))
+                ),
+                (
+                    "fixtures/two_materials.obj",
+                    Dependency::Asset("textures/missing.png".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = chain().to_dot();
+
+        assert!(dot.starts_with("digraph assets {\n"));
+        assert!(dot.contains("    \"materials/a.toml\" -> \"textures/a.png\";\n"));
+    }
+}
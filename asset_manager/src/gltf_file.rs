@@ -0,0 +1,141 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use gltf::buffer::Source;
+
+use crate::{vfs::Vfs, AssetError};
+
+/// A glTF document along with its buffers, read through the `Vfs` instead of straight from disk
+pub struct GltfFile {
+    pub document: gltf::Document,
+    pub buffers: Vec<gltf::buffer::Data>,
+}
+
+impl GltfFile {
+    pub fn open(path: &str, vfs: &Vfs) -> Result<GltfFile, AssetError> {
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(&vfs.read(path)?)?;
+
+        let mut buffers = vec![];
+
+        for buffer in document.buffers() {
+            let data = match buffer_file(path, buffer.source()) {
+                Some(file) => {
+                    let mut data = vfs.read(file)?;
+
+                    // Buffers are padded like `gltf::import_buffers` does
+                    data.resize(data.len().next_multiple_of(4), 0);
+
+                    gltf::buffer::Data(data)
+                }
+                // Data URIs and the binary chunk of a glb file never touch the filesystem
+                None => gltf::buffer::Data::from_source_and_blob(buffer.source(), None, &mut blob)?,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(AssetError::Parse(format!(
+                    "Buffer {} is {} bytes long, expected {}",
+                    buffer.index(),
+                    data.len(),
+                    buffer.length()
+                )));
+            }
+
+            buffers.push(data);
+        }
+
+        Ok(GltfFile { document, buffers })
+    }
+
+    /// Buffers stored in files of their own, embedded and data URI buffers are part of the glTF file
+    pub fn buffer_files(&self, path: &str) -> Vec<PathBuf> {
+        self.document
+            .buffers()
+            .filter_map(|buffer| buffer_file(path, buffer.source()))
+            .collect()
+    }
+}
+
+/// The file a URI in the glTF file at `path` refers to, if it is not a data URI
+pub fn uri_file(path: &str, uri: &str) -> Option<PathBuf> {
+    if uri.starts_with("data:") {
+        return None;
+    }
+
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+    match urlencoding::decode(uri) {
+        Ok(uri) => Some(directory.join(&*uri)),
+        Err(_) => Some(directory.join(uri)),
+    }
+}
+
+/// Id of something read from the glTF file at `path` other than its meshes and images,
+/// `<path>#<kind>/<name>` where kind is the array of the file it is in, like `robot.glb#skins/0`
+pub fn fragment_id(path: &str, kind: &str, name: impl Display) -> String {
+    format!("{}#{}/{}", path, kind, name)
+}
+
+/// Split an id made by `fragment_id` into the path of the glTF file and the name
+pub fn split_fragment_id<'a>(id: &'a str, kind: &str) -> Option<(&'a str, &'a str)> {
+    let (path, fragment) = id.split_once('#')?;
+
+    if !is_gltf(path) {
+        return None;
+    }
+
+    let name = fragment.strip_prefix(kind)?.strip_prefix('/')?;
+
+    Some((path, name))
+}
+
+/// The file an asset is read from, which is the glTF file for anything read from one
+pub fn source_file(id: &str) -> &str {
+    match id.split_once('#') {
+        Some((path, _)) if is_gltf(path) => path,
+        _ => id,
+    }
+}
+
+fn is_gltf(path: &str) -> bool {
+    match Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some(extension) => {
+            extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb")
+        }
+        None => false,
+    }
+}
+
+fn buffer_file(path: &str, source: Source) -> Option<PathBuf> {
+    match source {
+        Source::Uri(uri) => uri_file(path, uri),
+        Source::Bin => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_fragment_id() {
+        assert_eq!(fragment_id("robot.glb", "skins", 0), "robot.glb#skins/0");
+        assert_eq!(
+            split_fragment_id("models/robot.glb#animations/walk", "animations"),
+            Some(("models/robot.glb", "walk"))
+        );
+        assert_eq!(split_fragment_id("robot.glb#skins/0", "animations"), None);
+        assert_eq!(split_fragment_id("robot.glb#0", "skins"), None);
+        assert_eq!(split_fragment_id("robot.obj#skins/0", "skins"), None);
+    }
+
+    #[test]
+    fn test_source_file() {
+        assert_eq!(source_file("models/robot.glb#skins/0"), "models/robot.glb");
+        assert_eq!(source_file("sounds/#1.wav"), "sounds/#1.wav");
+    }
+}
@@ -0,0 +1,220 @@
+use serde_derive::Deserialize;
+
+use crate::{
+    gltf_file, is_builtin, vfs::Vfs, AssetError, AssetStatus, Handle, Mesh, Texture, UntypedHandle,
+};
+
+/// The ids of every asset a level or screen needs, listed by type in a TOML file like
+///
+/// ```toml
+/// meshes = ["models/level1.glb", "builtin://cube"]
+/// sounds = ["sounds/music.ogg"]
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadManifest {
+    pub meshes: Vec<String>,
+    pub textures: Vec<String>,
+    pub sounds: Vec<String>,
+    pub skeletons: Vec<String>,
+    pub animations: Vec<String>,
+    pub materials: Vec<String>,
+    pub scenes: Vec<String>,
+}
+
+impl LoadManifest {
+    pub fn read(path: &str, vfs: &Vfs) -> Result<LoadManifest, AssetError> {
+        match toml::from_str(&vfs.read_to_string(path)?) {
+            Ok(manifest) => Ok(manifest),
+            Err(e) => Err(AssetError::Parse(format!(
+                "Invalid load manifest {}: {}",
+                path, e
+            ))),
+        }
+    }
+}
+
+struct GroupAsset {
+    handle: UntypedHandle,
+    /// Size of the file the asset is read from, 0 when it has none
+    size: u64,
+}
+
+/// Assets requested together whose loading is followed as one, see `AssetManager::load_group`
+#[derive(Default)]
+pub struct AssetGroup {
+    assets: Vec<GroupAsset>,
+}
+
+impl AssetGroup {
+    pub(crate) fn add(&mut self, id: &str, handle: UntypedHandle, vfs: &Vfs) {
+        let size = match is_builtin(id) {
+            true => 0,
+            false => vfs.size(gltf_file::source_file(id)).unwrap_or(0),
+        };
+
+        self.assets.push(GroupAsset { handle, size });
+    }
+
+    pub fn handles(&self) -> impl Iterator<Item = UntypedHandle> + '_ {
+        self.assets.iter().map(|asset| asset.handle)
+    }
+
+    /// The meshes of the group, which are only ready once they are uploaded
+    pub fn meshes(&self) -> impl Iterator<Item = Handle<Mesh>> + '_ {
+        self.handles().filter_map(|handle| match handle {
+            UntypedHandle::Mesh(handle) => Some(handle),
+            _ => None,
+        })
+    }
+
+    /// The textures of the group, which are only ready once they are uploaded
+    pub fn textures(&self) -> impl Iterator<Item = Handle<Texture>> + '_ {
+        self.handles().filter_map(|handle| match handle {
+            UntypedHandle::Texture(handle) => Some(handle),
+            _ => None,
+        })
+    }
+
+    /// Count every asset of the group by the status `asset_status` gives for it,
+    /// which is None for assets that have been unloaded since
+    pub(crate) fn progress(
+        &self,
+        asset_status: impl Fn(UntypedHandle) -> Option<AssetStatus>,
+    ) -> LoadProgress {
+        let mut progress = LoadProgress {
+            total: self.assets.len(),
+            bytes_total: self.assets.iter().map(|asset| asset.size).sum(),
+            ..LoadProgress::default()
+        };
+
+        for asset in &self.assets {
+            let status = asset_status(asset.handle);
+
+            match status {
+                Some(AssetStatus::Unloaded) => continue,
+                Some(AssetStatus::Invalid) | None => progress.failed += 1,
+                Some(AssetStatus::Loaded | AssetStatus::Optimized | AssetStatus::Uploaded) => {
+                    progress.loaded += 1;
+                }
+            }
+
+            let ready = match (asset.handle, status) {
+                (_, Some(AssetStatus::Uploaded)) => true,
+                (UntypedHandle::Mesh(_) | UntypedHandle::Texture(_), _) => false,
+                (_, status) => status.is_some_and(|status| status != AssetStatus::Invalid),
+            };
+
+            if ready {
+                progress.ready += 1;
+            }
+
+            progress.bytes_loaded += asset.size;
+        }
+
+        progress
+    }
+}
+
+/// How far the assets of a group are, for example to draw a loading screen's progress bar
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub total: usize,
+    /// Assets read from their files, including meshes and textures still waiting to be uploaded
+    pub loaded: usize,
+    /// Loaded assets that can be used, meshes and textures once they are uploaded to the GPU
+    pub ready: usize,
+    /// Assets that failed to load, or were unloaded since
+    pub failed: usize,
+    /// Size of the files of every asset that finished loading, failed or not
+    pub bytes_loaded: u64,
+    pub bytes_total: u64,
+}
+
+impl LoadProgress {
+    /// Every asset has been read from its file or failed to
+    pub fn is_finished(&self) -> bool {
+        self.loaded + self.failed == self.total
+    }
+
+    /// Every asset can be used or has failed, failed assets never become ready so they do not
+    /// hold up the rest, check `failed` to handle them
+    pub fn is_ready(&self) -> bool {
+        self.ready + self.failed == self.total
+    }
+
+    /// How much of the group has finished loading between 0 and 1, by file size when known
+    pub fn fraction(&self) -> f32 {
+        match (self.bytes_total, self.total) {
+            (0, 0) => 1.0,
+            (0, total) => (self.loaded + self.failed) as f32 / total as f32,
+            (bytes_total, _) => self.bytes_loaded as f32 / bytes_total as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(handles: &[UntypedHandle]) -> AssetGroup {
+        AssetGroup {
+            assets: handles
+                .iter()
+                .map(|handle| GroupAsset {
+                    handle: *handle,
+                    size: 10,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_read_manifest() {
+        let vfs = Vfs::working_directory();
+
+        let manifest = LoadManifest::read("fixtures/level.toml", &vfs).unwrap();
+
+        assert_eq!(
+            manifest.meshes,
+            vec!["fixtures/nested_nodes.gltf", "builtin://cube"]
+        );
+        assert_eq!(manifest.sounds, vec!["fixtures/beep.wav"]);
+        assert!(manifest.textures.is_empty());
+    }
+
+    #[test]
+    fn test_progress() {
+        let mesh = UntypedHandle::Mesh(Handle::new(0, 0));
+        let sound = UntypedHandle::Sound(Handle::new(0, 0));
+        let texture = UntypedHandle::Texture(Handle::new(0, 0));
+        let group = group(&[mesh, sound, texture]);
+
+        let progress = group.progress(|_| Some(AssetStatus::Unloaded));
+        assert_eq!(progress.bytes_total, 30);
+        assert_eq!(progress.fraction(), 0.0);
+        assert!(!progress.is_finished());
+
+        // The sound can be used once loaded, the mesh and texture have to be uploaded first
+        let progress = group.progress(|_| Some(AssetStatus::Loaded));
+        assert_eq!((progress.loaded, progress.ready), (3, 1));
+        assert!(progress.is_finished());
+        assert!(!progress.is_ready());
+        assert_eq!(progress.fraction(), 1.0);
+
+        let progress = group.progress(|handle| match handle {
+            UntypedHandle::Mesh(_) | UntypedHandle::Texture(_) => Some(AssetStatus::Uploaded),
+            _ => None,
+        });
+        assert_eq!((progress.ready, progress.failed), (2, 1));
+        assert!(progress.is_ready());
+    }
+
+    #[test]
+    fn test_empty_progress() {
+        let progress = AssetGroup::default().progress(|_| None);
+
+        assert!(progress.is_ready());
+        assert_eq!(progress.fraction(), 1.0);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Deref,
+    sync::Arc,
+};
+
+use crate::{AnimationClip, Mesh, PbrMaterial, Scene, Skeleton, Sound, Texture};
+
+/// A cheap, copyable reference to an asset stored in an `AssetStorage`, which does not keep the
+/// asset alive by itself, see `StrongHandle`
+///
+/// The generation is bumped every time a slot is freed, so a handle to an asset that has
+/// since been removed will no longer resolve, even if its slot has been reused
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn new(index: u32, generation: u32) -> Handle<T> {
+        Handle {
+            index,
+            generation,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// These are implemented by hand as deriving them would require T to implement them as well
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> PartialOrd for Handle<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Handle<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.index, self.generation).cmp(&(other.index, other.generation))
+    }
+}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// A handle that keeps its asset from being evicted for as long as it, or a clone of it, is alive,
+/// which is what `AssetManager::get_mesh` and the other requests hand back
+///
+/// It dereferences to the plain `Handle` every lookup takes
+pub struct StrongHandle<T> {
+    handle: Handle<T>,
+    /// Shared with the asset's slot, which counts them to know whether it is still referenced
+    references: Arc<()>,
+}
+
+impl<T> StrongHandle<T> {
+    pub(crate) fn new(handle: Handle<T>, references: Arc<()>) -> StrongHandle<T> {
+        StrongHandle { handle, references }
+    }
+
+    /// The plain handle, which does not keep the asset alive
+    pub fn weak(&self) -> Handle<T> {
+        self.handle
+    }
+}
+
+impl<T> Deref for StrongHandle<T> {
+    type Target = Handle<T>;
+
+    fn deref(&self) -> &Handle<T> {
+        &self.handle
+    }
+}
+
+impl<T> Clone for StrongHandle<T> {
+    fn clone(&self) -> Self {
+        StrongHandle {
+            handle: self.handle,
+            references: Arc::clone(&self.references),
+        }
+    }
+}
+
+impl<T> PartialEq for StrongHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl<T> Eq for StrongHandle<T> {}
+
+impl<T> Hash for StrongHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for StrongHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StrongHandle").field(&self.handle).finish()
+    }
+}
+
+impl<T> From<&StrongHandle<T>> for Handle<T> {
+    fn from(handle: &StrongHandle<T>) -> Self {
+        handle.handle
+    }
+}
+
+/// A handle to an asset of any type the `AssetManager` stores
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UntypedHandle {
+    Mesh(Handle<Mesh>),
+    Sound(Handle<Sound>),
+    Texture(Handle<Texture>),
+    Skeleton(Handle<Skeleton>),
+    AnimationClip(Handle<AnimationClip>),
+    PbrMaterial(Handle<PbrMaterial>),
+    Scene(Handle<Scene>),
+}
+
+impl From<Handle<Mesh>> for UntypedHandle {
+    fn from(handle: Handle<Mesh>) -> Self {
+        UntypedHandle::Mesh(handle)
+    }
+}
+
+impl From<Handle<Sound>> for UntypedHandle {
+    fn from(handle: Handle<Sound>) -> Self {
+        UntypedHandle::Sound(handle)
+    }
+}
+
+impl From<Handle<Texture>> for UntypedHandle {
+    fn from(handle: Handle<Texture>) -> Self {
+        UntypedHandle::Texture(handle)
+    }
+}
+
+impl From<Handle<Skeleton>> for UntypedHandle {
+    fn from(handle: Handle<Skeleton>) -> Self {
+        UntypedHandle::Skeleton(handle)
+    }
+}
+
+impl From<Handle<AnimationClip>> for UntypedHandle {
+    fn from(handle: Handle<AnimationClip>) -> Self {
+        UntypedHandle::AnimationClip(handle)
+    }
+}
+
+impl From<Handle<PbrMaterial>> for UntypedHandle {
+    fn from(handle: Handle<PbrMaterial>) -> Self {
+        UntypedHandle::PbrMaterial(handle)
+    }
+}
+
+impl From<Handle<Scene>> for UntypedHandle {
+    fn from(handle: Handle<Scene>) -> Self {
+        UntypedHandle::Scene(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dummy;
+
+    #[test]
+    fn test_handle_eq() {
+        let a = Handle::<Dummy>::new(1, 0);
+        let b = a;
+
+        assert_eq!(a, b);
+        assert_ne!(a, Handle::<Dummy>::new(1, 1));
+        assert_ne!(a, Handle::<Dummy>::new(2, 0));
+    }
+
+    #[test]
+    fn test_handle_ord() {
+        let mut handles = [
+            Handle::<Dummy>::new(2, 0),
+            Handle::<Dummy>::new(0, 1),
+            Handle::<Dummy>::new(0, 0),
+        ];
+
+        handles.sort();
+
+        assert_eq!(
+            handles,
+            [Handle::new(0, 0), Handle::new(0, 1), Handle::new(2, 0)]
+        );
+    }
+}
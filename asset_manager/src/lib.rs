@@ -1,119 +1,1138 @@
 extern crate nalgebra_glm as glm;
 
+mod animation;
+mod asset_error;
+mod asset_event;
 mod asset_info;
+mod dependencies;
+mod gltf_file;
+mod group;
+mod handle;
+mod loader;
+mod material;
 mod mesh;
+mod meta;
+mod scene;
+mod skeleton;
 mod sound;
+mod storage;
+mod texture;
+mod vfs;
+mod watcher;
 
 use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-    thread::spawn,
+    collections::{HashMap, HashSet},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use asset_info::AssetInfo;
+use gpu_info::{Buffer, Image};
+use loader::{panic_message, AssetLoader, LoadContext, LoadableAsset};
+use log::info;
+use watcher::AssetWatcher;
 
-pub use mesh::{Mesh, Vertex};
-use sound::Sound;
+pub use animation::{AnimationChannel, AnimationClip, AnimationProperty, Interpolation};
+pub use asset_error::AssetError;
+pub use asset_event::AssetEvent;
+pub use asset_info::{AssetInfo, AssetStatus};
+pub use dependencies::{Dependency, DependencyGraph};
+pub use group::{AssetGroup, LoadManifest, LoadProgress};
+pub use handle::{Handle, StrongHandle, UntypedHandle};
+pub use loader::LoadPriority;
+pub use material::{AlphaMode, MaterialProperties, MaterialTexture, PbrMaterial};
+pub use mesh::{
+    is_builtin, Aabb, BoundingSphere, Bounds, Indices, LodSettings, Mesh, MeshLod, MeshMaterial,
+    MeshOptimizations, MorphTarget, Submesh, Vertex, VertexAttribute, VertexLayout, BUILTIN_SCHEME,
+};
+pub use meta::{AssetMeta, MeshMeta, SoundMeta, TextureMeta, UpAxis};
+pub use scene::{Scene, SceneNode};
+pub use skeleton::{Joint, JointTransform, Skeleton};
+pub use sound::{Sound, SoundData, SoundInstance};
+pub use storage::AssetStorage;
+pub use texture::Texture;
+pub use vfs::{DirectorySource, MountSource, TarPack, Vfs, VfsReader, ZipPack};
+
+/// Number of loader threads used when none is configured
+const DEFAULT_LOADER_THREADS: usize = 4;
 
+/// Every method that requests or reads assets takes `&self`, so any number of threads can use the
+/// manager at once. Only setting it up and evicting assets need it to themselves
 pub struct AssetManager {
-    meshes: Arc<Mutex<HashMap<String, Arc<Mutex<Mesh>>>>>,
-    sounds: Arc<Mutex<HashMap<String, Arc<Mutex<Sound>>>>>,
+    meshes: AssetStorage<Mesh>,
+    sounds: AssetStorage<Sound>,
+    textures: AssetStorage<Texture>,
+    skeletons: AssetStorage<Skeleton>,
+    animations: AssetStorage<AnimationClip>,
+    materials: AssetStorage<PbrMaterial>,
+    scenes: AssetStorage<Scene>,
+    groups: AssetStorage<AssetGroup>,
+    // Several assets can come from the same file, like the meshes and images of a glTF file
+    asset_paths: Mutex<HashMap<PathBuf, Vec<UntypedHandle>>>,
+    // Written by the loader threads as assets finish loading
+    dependencies: Arc<Mutex<DependencyGraph>>,
+    // GPU buffers of unloaded meshes, waiting for the renderer to free them
+    released_buffers: Mutex<Vec<Buffer>>,
+    // GPU images of unloaded textures, waiting for the renderer to free them
+    released_images: Mutex<Vec<Image>>,
+    watcher: Mutex<Option<AssetWatcher>>,
+    loader: AssetLoader,
+    load_context: Arc<LoadContext>,
+    event_sender: Sender<AssetEvent>,
+    // The receiver is not Sync, the mutex lets the manager stay usable as a bevy Resource
+    events: Mutex<Receiver<AssetEvent>>,
+    // Notified by the loader threads whenever a load finishes, see `wait_for_group`
+    load_finished: Arc<(Mutex<()>, Condvar)>,
+    // Bumped every time a load of the asset is queued, only the latest one gets to publish
+    load_generations: Arc<Mutex<HashMap<UntypedHandle, u64>>>,
 }
 
 impl AssetManager {
     pub fn new() -> AssetManager {
+        AssetManager::with_loader_threads(DEFAULT_LOADER_THREADS)
+    }
+
+    /// Create an asset manager whose loads are spread over a fixed number of worker threads
+    pub fn with_loader_threads(thread_count: usize) -> AssetManager {
+        let (event_sender, events) = channel();
+
         AssetManager {
-            meshes: Arc::new(Mutex::new(HashMap::new())),
-            sounds: Arc::new(Mutex::new(HashMap::new())),
+            meshes: AssetStorage::new(),
+            sounds: AssetStorage::new(),
+            textures: AssetStorage::new(),
+            skeletons: AssetStorage::new(),
+            animations: AssetStorage::new(),
+            materials: AssetStorage::new(),
+            scenes: AssetStorage::new(),
+            groups: AssetStorage::new(),
+            asset_paths: Mutex::new(HashMap::new()),
+            dependencies: Arc::new(Mutex::new(DependencyGraph::default())),
+            released_buffers: Mutex::new(vec![]),
+            released_images: Mutex::new(vec![]),
+            watcher: Mutex::new(None),
+            loader: AssetLoader::new(thread_count),
+            load_context: Arc::new(LoadContext::default()),
+            event_sender,
+            events: Mutex::new(events),
+            load_finished: Arc::new((Mutex::new(()), Condvar::new())),
+            load_generations: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Start watching the files of every requested asset, reloading them when they change on disk
+    /// Changes are picked up when `update` is called
+    pub fn enable_hot_reload(&mut self) -> Result<(), String> {
+        if self.watcher.get_mut().unwrap().is_some() {
+            return Ok(());
+        }
+
+        let mut watcher = AssetWatcher::new()?;
+
+        for path in self.asset_paths.get_mut().unwrap().keys() {
+            watcher.watch(path);
         }
+
+        *self.watcher.get_mut().unwrap() = Some(watcher);
+
+        Ok(())
+    }
+
+    /// Keep preprocessed meshes in the directory so later runs can skip parsing their source files
+    /// Only loads started after this call use the cache
+    pub fn enable_cache(&mut self, directory: impl Into<PathBuf>) {
+        Arc::make_mut(&mut self.load_context).cache_directory = Some(directory.into());
+    }
+
+    /// Set the passes run on every mesh that has no optimizations of its own
+    /// Only loads started after this call use them, see `reload` to optimize loaded meshes
+    pub fn set_default_mesh_optimizations(&mut self, optimizations: MeshOptimizations) {
+        Arc::make_mut(&mut self.load_context).default_mesh_optimizations = optimizations;
+    }
+
+    /// Set the passes run on a single mesh, whatever the default ones are
+    pub fn set_mesh_optimizations(&mut self, id: &str, optimizations: MeshOptimizations) {
+        Arc::make_mut(&mut self.load_context)
+            .mesh_optimizations
+            .insert(id.to_owned(), optimizations);
+    }
+
+    /// Set how many simplified levels are generated for every mesh
+    /// Only loads started after this call use them, see `reload` to regenerate loaded meshes
+    pub fn set_lod_settings(&mut self, settings: LodSettings) {
+        Arc::make_mut(&mut self.load_context).lod_settings = settings;
+    }
+
+    /// Set how long a sound can be before it is streamed instead of decoded up front
+    /// Only loads started after this call use it
+    pub fn set_sound_streaming_threshold(&mut self, threshold: Duration) {
+        Arc::make_mut(&mut self.load_context).sound_streaming_threshold = threshold;
+    }
+
+    /// The virtual filesystem every asset is read from
+    pub fn vfs(&self) -> &Vfs {
+        &self.load_context.vfs
     }
 
-    pub fn iter_meshes_mut(&mut self) -> Arc<Mutex<HashMap<String, Arc<Mutex<Mesh>>>>> {
-        self.meshes.clone()
+    /// Change the mounts of the virtual filesystem, only loads started after this use the changes
+    /// so mounts are best set up before any asset is requested
+    pub fn vfs_mut(&mut self) -> &mut Vfs {
+        &mut Arc::make_mut(&mut self.load_context).vfs
     }
 
-    pub fn get_mesh(&mut self, name: &String) -> Arc<Mutex<Mesh>> {
-        let existing = {
-            let meshes_binding = self.meshes.lock().unwrap();
+    /// Reload every asset whose file, or a file it depends on, has changed since the last update,
+    /// along with the assets depending on it
+    pub fn update(&self) {
+        self.track_dependency_files();
 
-            meshes_binding.get(name).cloned()
+        let changed_paths = match &mut *self.watcher.lock().unwrap() {
+            Some(watcher) => watcher.changed_paths(),
+            None => return,
         };
 
-        match existing {
-            Some(mesh) => mesh,
-            None => self.insert_mesh(name),
+        let mut changed: Vec<UntypedHandle> = vec![];
+        let asset_paths = self.asset_paths.lock().unwrap();
+
+        for path in changed_paths {
+            let handles = match asset_paths.get(&path) {
+                Some(handles) => handles,
+                None => continue,
+            };
+
+            info!("Reloading changed asset: {}", path.display());
+
+            for handle in handles {
+                if !changed.contains(handle) {
+                    changed.push(*handle);
+                }
+            }
         }
+
+        drop(asset_paths);
+
+        self.reload_with_dependents(&changed);
     }
 
-    fn insert_mesh(&mut self, name: &str) -> Arc<Mutex<Mesh>> {
-        let asset_info = AssetInfo {
-            id: name.to_owned(),
-            status: asset_info::AssetStatus::Unloaded,
-        };
+    /// Take every event that happened to an asset since the last call
+    pub fn drain_events(&self) -> Vec<AssetEvent> {
+        self.events.lock().unwrap().try_iter().collect()
+    }
 
-        let mesh_info = Mesh {
-            asset_info,
-            gpu_info: None,
-            vertices: vec![],
-            vertex_count: 0,
-        };
+    /// Load the asset with the given id again from its file, along with every asset depending on it
+    /// Returns false if no asset with that id has been requested
+    pub fn reload(&self, id: &str) -> bool {
+        match self.untyped_handle(id) {
+            Some(handle) => {
+                self.reload_with_dependents(&[handle]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove the asset with the given id, invalidating every handle to it,
+    /// the assets depending on it are reloaded without it
+    /// Returns false if no asset with that id has been requested
+    pub fn unload(&self, id: &str) -> bool {
+        match self.untyped_handle(id) {
+            Some(handle) => {
+                let dependents = self
+                    .dependencies
+                    .lock()
+                    .unwrap()
+                    .transitive_dependents(&[handle]);
+
+                self.unload_handle(handle);
+
+                for dependent in dependents {
+                    self.reload_handle(dependent);
+                }
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keep an asset alive even when nothing else references it, until a matching `release`
+    pub fn retain(&self, handle: impl Into<UntypedHandle>) {
+        match handle.into() {
+            UntypedHandle::Mesh(handle) => self.meshes.retain(handle),
+            UntypedHandle::Sound(handle) => self.sounds.retain(handle),
+            UntypedHandle::Texture(handle) => self.textures.retain(handle),
+            UntypedHandle::Skeleton(handle) => self.skeletons.retain(handle),
+            UntypedHandle::AnimationClip(handle) => self.animations.retain(handle),
+            UntypedHandle::PbrMaterial(handle) => self.materials.retain(handle),
+            UntypedHandle::Scene(handle) => self.scenes.retain(handle),
+        }
+    }
+
+    pub fn release(&self, handle: impl Into<UntypedHandle>) {
+        match handle.into() {
+            UntypedHandle::Mesh(handle) => self.meshes.release(handle),
+            UntypedHandle::Sound(handle) => self.sounds.release(handle),
+            UntypedHandle::Texture(handle) => self.textures.release(handle),
+            UntypedHandle::Skeleton(handle) => self.skeletons.release(handle),
+            UntypedHandle::AnimationClip(handle) => self.animations.release(handle),
+            UntypedHandle::PbrMaterial(handle) => self.materials.release(handle),
+            UntypedHandle::Scene(handle) => self.scenes.release(handle),
+        }
+    }
+
+    /// Unload every asset no `StrongHandle` refers to anymore, that is not retained and was not
+    /// requested since the last eviction pass.
+    /// Assets that are kept also keep the assets they depend on.
+    /// It takes the manager to itself so no asset can be requested while it is being evicted
+    pub fn evict_unreferenced(&mut self) {
+        let unrequested = self
+            .meshes
+            .take_unrequested()
+            .into_iter()
+            .map(UntypedHandle::from)
+            .chain(
+                self.sounds
+                    .take_unrequested()
+                    .into_iter()
+                    .map(UntypedHandle::from),
+            )
+            .chain(
+                self.textures
+                    .take_unrequested()
+                    .into_iter()
+                    .map(UntypedHandle::from),
+            )
+            .chain(
+                self.skeletons
+                    .take_unrequested()
+                    .into_iter()
+                    .map(UntypedHandle::from),
+            )
+            .chain(
+                self.animations
+                    .take_unrequested()
+                    .into_iter()
+                    .map(UntypedHandle::from),
+            )
+            .chain(
+                self.materials
+                    .take_unrequested()
+                    .into_iter()
+                    .map(UntypedHandle::from),
+            )
+            .chain(
+                self.scenes
+                    .take_unrequested()
+                    .into_iter()
+                    .map(UntypedHandle::from),
+            );
 
-        let mesh = Arc::new(Mutex::new(mesh_info));
+        let mut unreferenced: HashSet<UntypedHandle> = unrequested.collect();
 
-        self.meshes
+        let graph = self.dependencies.lock().unwrap();
+
+        let mut kept: Vec<UntypedHandle> = graph
+            .iter()
+            .map(|(handle, _, _)| handle)
+            .filter(|handle| !unreferenced.contains(handle))
+            .collect();
+
+        while let Some(handle) = kept.pop() {
+            for dependency in graph.dependencies(handle) {
+                let dependency = match dependency {
+                    Dependency::Asset(id) => self.untyped_handle(id),
+                    Dependency::File(_) => None,
+                };
+
+                if let Some(dependency) = dependency {
+                    if unreferenced.remove(&dependency) {
+                        kept.push(dependency);
+                    }
+                }
+            }
+        }
+
+        drop(graph);
+
+        for handle in unreferenced {
+            self.unload_handle(handle);
+        }
+    }
+
+    /// Take the GPU buffers of unloaded meshes, the caller is responsible for freeing them
+    /// once the GPU is no longer using them
+    pub fn take_released_buffers(&self) -> Vec<Buffer> {
+        std::mem::take(&mut self.released_buffers.lock().unwrap())
+    }
+
+    /// Take the GPU images of unloaded textures, the caller is responsible for freeing them
+    /// once the GPU is no longer using them
+    pub fn take_released_images(&self) -> Vec<Image> {
+        std::mem::take(&mut self.released_images.lock().unwrap())
+    }
+
+    fn untyped_handle(&self, id: &str) -> Option<UntypedHandle> {
+        if let Some(handle) = self.meshes.handle(id) {
+            return Some(handle.into());
+        }
+
+        if let Some(handle) = self.sounds.handle(id) {
+            return Some(handle.into());
+        }
+
+        if let Some(handle) = self.textures.handle(id) {
+            return Some(handle.into());
+        }
+
+        if let Some(handle) = self.skeletons.handle(id) {
+            return Some(handle.into());
+        }
+
+        if let Some(handle) = self.animations.handle(id) {
+            return Some(handle.into());
+        }
+
+        if let Some(handle) = self.materials.handle(id) {
+            return Some(handle.into());
+        }
+
+        self.scenes.handle(id).map(UntypedHandle::from)
+    }
+
+    fn asset_status(&self, handle: UntypedHandle) -> Option<AssetStatus> {
+        match handle {
+            UntypedHandle::Mesh(handle) => self
+                .meshes
+                .get(handle)
+                .map(|mesh| mesh.lock().unwrap().asset_info.status),
+            UntypedHandle::Sound(handle) => self
+                .sounds
+                .get(handle)
+                .map(|sound| sound.lock().unwrap().asset_info.status),
+            UntypedHandle::Texture(handle) => self
+                .textures
+                .get(handle)
+                .map(|texture| texture.lock().unwrap().asset_info.status),
+            UntypedHandle::Skeleton(handle) => self
+                .skeletons
+                .get(handle)
+                .map(|skeleton| skeleton.lock().unwrap().asset_info.status),
+            UntypedHandle::AnimationClip(handle) => self
+                .animations
+                .get(handle)
+                .map(|animation| animation.lock().unwrap().asset_info.status),
+            UntypedHandle::PbrMaterial(handle) => self
+                .materials
+                .get(handle)
+                .map(|material| material.lock().unwrap().asset_info.status),
+            UntypedHandle::Scene(handle) => self
+                .scenes
+                .get(handle)
+                .map(|scene| scene.lock().unwrap().asset_info.status),
+        }
+    }
+
+    /// Reload the assets and every asset depending on them, each only once
+    fn reload_with_dependents(&self, handles: &[UntypedHandle]) {
+        let dependents = self
+            .dependencies
             .lock()
             .unwrap()
-            .insert(name.to_owned(), mesh.clone());
+            .transitive_dependents(handles);
 
-        let closure_mesh = mesh.clone();
-        spawn(move || {
-            let mut mesh_binding = closure_mesh.lock().unwrap();
+        for handle in handles.iter().copied().chain(dependents) {
+            self.reload_handle(handle);
+        }
+    }
 
-            mesh_binding.load();
-        });
+    fn reload_handle(&self, handle: UntypedHandle) -> bool {
+        match handle {
+            UntypedHandle::Mesh(handle) => match self.meshes.get(handle) {
+                Some(mesh) => {
+                    let id = mesh.lock().unwrap().asset_info.id.clone();
+
+                    self.queue_load(mesh, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+            UntypedHandle::Sound(handle) => match self.sounds.get(handle) {
+                Some(sound) => {
+                    let id = sound.lock().unwrap().asset_info.id.clone();
+
+                    self.queue_load(sound, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+            UntypedHandle::Texture(handle) => match self.textures.get(handle) {
+                Some(texture) => {
+                    let id = texture.lock().unwrap().asset_info.id.clone();
+
+                    self.queue_load(texture, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+            UntypedHandle::Skeleton(handle) => match self.skeletons.get(handle) {
+                Some(skeleton) => {
+                    let id = skeleton.lock().unwrap().asset_info.id.clone();
 
-        mesh.clone()
+                    self.queue_load(skeleton, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+            UntypedHandle::AnimationClip(handle) => match self.animations.get(handle) {
+                Some(animation) => {
+                    let id = animation.lock().unwrap().asset_info.id.clone();
+
+                    self.queue_load(animation, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+            UntypedHandle::PbrMaterial(handle) => match self.materials.get(handle) {
+                Some(material) => {
+                    let id = material.lock().unwrap().asset_info.id.clone();
+
+                    self.queue_load(material, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+            UntypedHandle::Scene(handle) => match self.scenes.get(handle) {
+                Some(scene) => {
+                    let id = scene.lock().unwrap().asset_info.id.clone();
+
+                    self.queue_load(scene, handle.into(), &id, LoadPriority::Normal);
+                    true
+                }
+                None => false,
+            },
+        }
     }
 
-    pub fn get_audio(&mut self, name: &String) -> Arc<Mutex<Sound>> {
-        let existing = {
-            let sounds_binding = self.sounds.lock().unwrap();
+    fn unload_handle(&self, handle: UntypedHandle) -> bool {
+        match handle {
+            UntypedHandle::Mesh(mesh_handle) => match self.meshes.remove(mesh_handle) {
+                Some(mesh) => {
+                    let buffers = mesh.lock().unwrap().remove_gpu_info();
+
+                    self.released_buffers.lock().unwrap().extend(buffers);
+                }
+                None => return false,
+            },
+            UntypedHandle::Sound(sound_handle) => match self.sounds.remove(sound_handle) {
+                Some(sound) => sound.lock().unwrap().unload(),
+                None => return false,
+            },
+            UntypedHandle::Texture(texture_handle) => match self.textures.remove(texture_handle) {
+                Some(texture) => {
+                    if let Some(image) = texture.lock().unwrap().remove_gpu_info() {
+                        self.released_images.lock().unwrap().push(image);
+                    }
+                }
+                None => return false,
+            },
+            UntypedHandle::Skeleton(skeleton_handle) => {
+                match self.skeletons.remove(skeleton_handle) {
+                    Some(skeleton) => skeleton.lock().unwrap().unload(),
+                    None => return false,
+                }
+            }
+            UntypedHandle::AnimationClip(animation_handle) => {
+                match self.animations.remove(animation_handle) {
+                    Some(animation) => animation.lock().unwrap().unload(),
+                    None => return false,
+                }
+            }
+            UntypedHandle::PbrMaterial(material_handle) => {
+                match self.materials.remove(material_handle) {
+                    Some(material) => material.lock().unwrap().unload(),
+                    None => return false,
+                }
+            }
+            UntypedHandle::Scene(scene_handle) => match self.scenes.remove(scene_handle) {
+                Some(scene) => scene.lock().unwrap().unload(),
+                None => return false,
+            },
+        }
 
-            sounds_binding.get(name).cloned()
+        let _ = self.event_sender.send(AssetEvent::Unloaded(handle));
+
+        self.dependencies.lock().unwrap().remove(handle);
+        self.load_generations.lock().unwrap().remove(&handle);
+        self.asset_paths.lock().unwrap().retain(|_, handles| {
+            handles.retain(|existing| *existing != handle);
+
+            !handles.is_empty()
+        });
+
+        true
+    }
+
+    /// Start tracking the references to an asset and watching the file it is loaded from
+    fn track_asset(&self, id: &str, file: &str, handle: UntypedHandle) {
+        // Files inside pack files never change and builtin meshes have no file, so there is nothing to watch
+        let path = match is_builtin(file) {
+            true => None,
+            false => self.load_context.vfs.real_path(file),
         };
 
-        match existing {
-            Some(sound) => sound,
-            None => self.insert_audio(name),
+        // A meta file changing, or being created, changes how the asset is imported
+        let meta_path =
+            meta::meta_path(file).and_then(|meta| self.load_context.vfs.real_path(meta));
+
+        for path in path.into_iter().chain(meta_path) {
+            let path = watcher::absolute_path(&path);
+
+            if let Some(watcher) = &mut *self.watcher.lock().unwrap() {
+                watcher.watch(&path);
+            }
+
+            self.asset_paths
+                .lock()
+                .unwrap()
+                .entry(path)
+                .or_default()
+                .push(handle);
         }
+
+        self.dependencies.lock().unwrap().insert(handle, id);
     }
 
-    fn insert_audio(&mut self, name: &str) -> Arc<Mutex<Sound>> {
-        let asset_info = AssetInfo {
-            id: name.to_owned(),
-            status: asset_info::AssetStatus::Unloaded,
-        };
+    /// Watch the files assets were found to depend on by their last load,
+    /// so they are reloaded when those change too
+    fn track_dependency_files(&self) {
+        let new_files = self.dependencies.lock().unwrap().take_new_files();
+
+        for (handle, file) in new_files {
+            // The asset may have been unloaded since
+            if self.dependencies.lock().unwrap().id(handle).is_none() {
+                continue;
+            }
+
+            let path = match self.load_context.vfs.real_path(&file) {
+                Some(path) => watcher::absolute_path(&path),
+                None => continue,
+            };
+
+            if let Some(watcher) = &mut *self.watcher.lock().unwrap() {
+                watcher.watch(&path);
+            }
+
+            let mut asset_paths = self.asset_paths.lock().unwrap();
+            let handles = asset_paths.entry(path).or_default();
+
+            if !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+    }
+
+    /// Insert the asset made by `asset` under the id if there is none yet, then watch its file and
+    /// queue its load. Another thread may have requested it first, then it is already loading
+    fn insert_asset<T: LoadableAsset + Send + 'static>(
+        &self,
+        storage: &AssetStorage<T>,
+        name: &str,
+        file: &str,
+        priority: LoadPriority,
+        asset: impl FnOnce() -> T,
+    ) -> Handle<T>
+    where
+        Handle<T>: Into<UntypedHandle>,
+    {
+        let (handle, inserted) = storage.get_or_insert_with(name, asset);
+
+        if inserted {
+            self.track_asset(name, file, handle.into());
+
+            self.queue_load(storage.get(handle).unwrap(), handle.into(), name, priority);
+        }
+
+        handle
+    }
+
+    /// Read the asset on a loader thread, it is only locked to publish the result
+    fn queue_load<T: LoadableAsset + Send + 'static>(
+        &self,
+        asset: Arc<Mutex<T>>,
+        handle: UntypedHandle,
+        id: &str,
+        priority: LoadPriority,
+    ) {
+        let id = id.to_owned();
+        let context = self.load_context.clone();
+        let event_sender = self.event_sender.clone();
+        let dependencies = self.dependencies.clone();
+        let load_finished = self.load_finished.clone();
+        let load_generations = self.load_generations.clone();
 
-        let sound_info = Sound {
-            asset_info,
-            source: None,
+        // A reload can be queued while an earlier load is still waiting or reading, which may then
+        // finish last. Only the load queued last publishes, the others are dropped
+        let generation = {
+            let mut load_generations = self.load_generations.lock().unwrap();
+            let generation = load_generations.entry(handle).or_insert(0);
+            *generation += 1;
+            *generation
         };
 
-        let sound = Arc::new(Mutex::new(sound_info));
+        self.loader.submit(priority, move || {
+            // Importers are handed any file, a bug decoding one must still fail the asset
+            let data = match catch_unwind(AssertUnwindSafe(|| T::read(&id, &context))) {
+                Ok(data) => data,
+                Err(panic) => Err(AssetError::Panicked(panic_message(panic.as_ref()))),
+            };
 
-        self.sounds
-            .lock()
-            .unwrap()
-            .insert(name.to_owned(), sound.clone());
+            // A failed load keeps the dependencies of the last one, so fixing a missing file reloads it
+            let asset_dependencies = data.as_ref().ok().map(T::dependencies);
+
+            let mut asset = asset.lock().unwrap();
+
+            if load_generations.lock().unwrap().get(&handle) != Some(&generation) {
+                return;
+            }
 
-        let closure_sound = sound.clone();
-        spawn(move || {
-            let mut sound_binding = closure_sound.lock().unwrap();
+            let first_load = asset.asset_info().status == AssetStatus::Unloaded;
+            asset.publish(data);
 
-            sound_binding.load();
+            let event = match (&asset.asset_info().error, first_load) {
+                (Some(error), _) => AssetEvent::Failed(handle, error.clone()),
+                (None, true) => AssetEvent::Loaded(handle),
+                (None, false) => AssetEvent::Reloaded(handle),
+            };
+
+            // Anyone waiting checks the asset's status while holding the lock, so it must be released first
+            drop(asset);
+
+            if let Some(asset_dependencies) = asset_dependencies {
+                dependencies
+                    .lock()
+                    .unwrap()
+                    .set_dependencies(handle, asset_dependencies);
+            }
+
+            // Nobody is left to tell if the manager was dropped while the asset was loading
+            let _ = event_sender.send(event);
+
+            let (lock, condvar) = &*load_finished;
+            let _guard = lock.lock().unwrap();
+            condvar.notify_all();
         });
+    }
+
+    /// Request every asset of the manifest and follow their loading as one group, see `group_progress`
+    ///
+    /// The assets are retained until the group is removed, a group with the same name
+    /// is handed back as is
+    pub fn load_group(&self, name: &str, manifest: &LoadManifest) -> Handle<AssetGroup> {
+        if let Some(handle) = self.groups.handle(name) {
+            return handle;
+        }
+
+        let mut group = AssetGroup::default();
+
+        for id in &manifest.meshes {
+            let handle = self.get_mesh(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        for id in &manifest.textures {
+            let handle = self.get_texture(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        for id in &manifest.sounds {
+            let handle = self.get_audio(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        for id in &manifest.skeletons {
+            let handle = self.get_skeleton(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        for id in &manifest.animations {
+            let handle = self.get_animation(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        for id in &manifest.materials {
+            let handle = self.get_material(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        for id in &manifest.scenes {
+            let handle = self.get_scene(id).weak().into();
+            group.add(id, handle, &self.load_context.vfs);
+        }
+
+        let handles: Vec<UntypedHandle> = group.handles().collect();
+
+        // Only the thread that inserted the group retains its assets, for a single release later
+        let (handle, inserted) = self.groups.get_or_insert_with(name, || group);
+
+        if inserted {
+            for asset in handles {
+                self.retain(asset);
+            }
+        }
+
+        handle
+    }
+
+    /// Read a `LoadManifest` through the virtual filesystem and load it as a group named after its path
+    pub fn load_manifest(&self, path: &str) -> Result<Handle<AssetGroup>, AssetError> {
+        if let Some(handle) = self.groups.handle(path) {
+            return Ok(handle);
+        }
+
+        let manifest = LoadManifest::read(path, &self.load_context.vfs)?;
+
+        Ok(self.load_group(path, &manifest))
+    }
+
+    /// A snapshot of what every requested asset depends on, to find missing or unused files
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        self.dependencies.lock().unwrap().clone()
+    }
+
+    pub fn group(&self, handle: Handle<AssetGroup>) -> Option<Arc<Mutex<AssetGroup>>> {
+        self.groups.get(handle)
+    }
+
+    pub fn group_progress(&self, handle: Handle<AssetGroup>) -> Option<LoadProgress> {
+        let group = self.groups.get(handle)?;
+        let group = group.lock().unwrap();
+
+        Some(group.progress(|handle| self.asset_status(handle)))
+    }
+
+    /// Block until every asset of the group has been read from its file or failed to,
+    /// or the timeout passes, returning the progress at that point
+    ///
+    /// Meshes and textures still have to be uploaded by the renderer afterwards,
+    /// see `LoadProgress::is_ready`
+    pub fn wait_for_group(
+        &self,
+        handle: Handle<AssetGroup>,
+        timeout: Duration,
+    ) -> Option<LoadProgress> {
+        let deadline = Instant::now() + timeout;
+
+        let (lock, condvar) = &*self.load_finished;
+        let mut guard = lock.lock().unwrap();
+
+        loop {
+            let progress = self.group_progress(handle)?;
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if progress.is_finished() || remaining.is_zero() {
+                return Some(progress);
+            }
+
+            guard = condvar.wait_timeout(guard, remaining).unwrap().0;
+        }
+    }
+
+    /// Stop following the group and release its assets, which can then be evicted
+    /// Returns false if the group was already removed
+    pub fn remove_group(&self, handle: Handle<AssetGroup>) -> bool {
+        let group = match self.groups.remove(handle) {
+            Some(group) => group,
+            None => return false,
+        };
+
+        for handle in group.lock().unwrap().handles() {
+            self.release(handle);
+        }
+
+        true
+    }
+
+    pub fn iter_meshes(&self) -> impl Iterator<Item = Arc<Mutex<Mesh>>> {
+        self.meshes.iter()
+    }
+
+    /// Get a handle keeping the mesh loaded, loading it if it has not been requested before
+    pub fn get_mesh(&self, name: &str) -> StrongHandle<Mesh> {
+        self.get_mesh_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_mesh`, the priority decides how soon the load is picked up from the queue
+    pub fn get_mesh_with_priority(&self, name: &str, priority: LoadPriority) -> StrongHandle<Mesh> {
+        let handle = self.insert_mesh(name, priority);
+
+        self.meshes.mark_requested(handle);
+
+        self.meshes.strong(handle)
+    }
+
+    pub fn mesh(&self, handle: Handle<Mesh>) -> Option<Arc<Mutex<Mesh>>> {
+        self.meshes.get(handle)
+    }
+
+    /// The bounds of a loaded mesh, still there after its vertices are uploaded and freed
+    pub fn mesh_bounds(&self, handle: Handle<Mesh>) -> Option<Bounds> {
+        let mesh = self.meshes.get(handle)?;
+        let mesh = mesh.lock().unwrap();
+
+        match mesh.asset_info.status {
+            AssetStatus::Loaded | AssetStatus::Uploaded | AssetStatus::Optimized => {
+                Some(mesh.bounds)
+            }
+            AssetStatus::Invalid | AssetStatus::Unloaded => None,
+        }
+    }
+
+    fn insert_mesh(&self, name: &str, priority: LoadPriority) -> Handle<Mesh> {
+        self.insert_asset(
+            &self.meshes,
+            name,
+            gltf_file::source_file(name),
+            priority,
+            || Mesh {
+                asset_info: AssetInfo::new(name),
+                gpu_info: None,
+                index_gpu_info: None,
+                vertices: vec![],
+                vertex_count: 0,
+                vertex_layout: VertexLayout::default(),
+                indices: Indices::default(),
+                bounds: Bounds::default(),
+                submeshes: vec![],
+                materials: vec![],
+                lods: vec![],
+                skeleton: None,
+                morph_targets: vec![],
+                morph_weights: vec![],
+            },
+        )
+    }
 
-        sound.clone()
+    /// Get a handle keeping the sound loaded, loading it if it has not been requested before
+    pub fn get_audio(&self, name: &str) -> StrongHandle<Sound> {
+        self.get_audio_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_audio`, the priority decides how soon the load is picked up from the queue
+    pub fn get_audio_with_priority(
+        &self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> StrongHandle<Sound> {
+        let handle = self.insert_audio(name, priority);
+
+        self.sounds.mark_requested(handle);
+
+        self.sounds.strong(handle)
+    }
+
+    pub fn sound(&self, handle: Handle<Sound>) -> Option<Arc<Mutex<Sound>>> {
+        self.sounds.get(handle)
+    }
+
+    fn insert_audio(&self, name: &str, priority: LoadPriority) -> Handle<Sound> {
+        self.insert_asset(&self.sounds, name, name, priority, || Sound {
+            asset_info: AssetInfo::new(name),
+            data: None,
+        })
+    }
+
+    pub fn iter_textures(&self) -> impl Iterator<Item = Arc<Mutex<Texture>>> {
+        self.textures.iter()
+    }
+
+    /// Get a handle keeping the texture loaded, loading it if it has not been requested before
+    /// Images embedded in a glTF file are requested as `<path>#<image index>`
+    pub fn get_texture(&self, name: &str) -> StrongHandle<Texture> {
+        self.get_texture_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_texture`, the priority decides how soon the load is picked up from the queue
+    pub fn get_texture_with_priority(
+        &self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> StrongHandle<Texture> {
+        let handle = self.insert_texture(name, priority);
+
+        self.textures.mark_requested(handle);
+
+        self.textures.strong(handle)
+    }
+
+    pub fn texture(&self, handle: Handle<Texture>) -> Option<Arc<Mutex<Texture>>> {
+        self.textures.get(handle)
+    }
+
+    fn insert_texture(&self, name: &str, priority: LoadPriority) -> Handle<Texture> {
+        self.insert_asset(
+            &self.textures,
+            name,
+            texture::source_file(name),
+            priority,
+            || Texture {
+                asset_info: AssetInfo::new(name),
+                gpu_info: None,
+                pixels: vec![],
+                width: 0,
+                height: 0,
+                srgb: true,
+                mips: vec![],
+            },
+        )
+    }
+
+    /// Get a handle keeping the skeleton loaded, loading it if it has not been requested before
+    /// Skins of a glTF file are requested as `<path>#skins/<skin index>`, see `Mesh::skeleton`
+    pub fn get_skeleton(&self, name: &str) -> StrongHandle<Skeleton> {
+        self.get_skeleton_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_skeleton`, the priority decides how soon the load is picked up from the queue
+    pub fn get_skeleton_with_priority(
+        &self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> StrongHandle<Skeleton> {
+        let handle = self.insert_skeleton(name, priority);
+
+        self.skeletons.mark_requested(handle);
+
+        self.skeletons.strong(handle)
+    }
+
+    pub fn skeleton(&self, handle: Handle<Skeleton>) -> Option<Arc<Mutex<Skeleton>>> {
+        self.skeletons.get(handle)
+    }
+
+    fn insert_skeleton(&self, name: &str, priority: LoadPriority) -> Handle<Skeleton> {
+        self.insert_asset(
+            &self.skeletons,
+            name,
+            gltf_file::source_file(name),
+            priority,
+            || Skeleton {
+                asset_info: AssetInfo::new(name),
+                joints: vec![],
+            },
+        )
+    }
+
+    /// Get a handle keeping the animation clip loaded, loading it if it has not been requested before
+    /// Animations of a glTF file are requested as `<path>#animations/<index or name>`
+    pub fn get_animation(&self, name: &str) -> StrongHandle<AnimationClip> {
+        self.get_animation_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_animation`, the priority decides how soon the load is picked up from the queue
+    pub fn get_animation_with_priority(
+        &self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> StrongHandle<AnimationClip> {
+        let handle = self.insert_animation(name, priority);
+
+        self.animations.mark_requested(handle);
+
+        self.animations.strong(handle)
+    }
+
+    pub fn animation(&self, handle: Handle<AnimationClip>) -> Option<Arc<Mutex<AnimationClip>>> {
+        self.animations.get(handle)
+    }
+
+    fn insert_animation(&self, name: &str, priority: LoadPriority) -> Handle<AnimationClip> {
+        self.insert_asset(
+            &self.animations,
+            name,
+            gltf_file::source_file(name),
+            priority,
+            || AnimationClip {
+                asset_info: AssetInfo::new(name),
+                name: None,
+                duration: 0.0,
+                channels: vec![],
+            },
+        )
+    }
+
+    /// Get a handle keeping the material loaded, loading it if it has not been requested before.
+    /// Materials of a glTF file are requested as `<path>#materials/<index or name>`,
+    /// which is the id `MeshMaterial::id` holds
+    pub fn get_material(&self, name: &str) -> StrongHandle<PbrMaterial> {
+        self.get_material_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_material`, the priority decides how soon the load is picked up from the queue
+    pub fn get_material_with_priority(
+        &self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> StrongHandle<PbrMaterial> {
+        let handle = self.insert_material(name, priority);
+
+        self.materials.mark_requested(handle);
+
+        self.materials.strong(handle)
+    }
+
+    pub fn material(&self, handle: Handle<PbrMaterial>) -> Option<Arc<Mutex<PbrMaterial>>> {
+        self.materials.get(handle)
+    }
+
+    fn insert_material(&self, name: &str, priority: LoadPriority) -> Handle<PbrMaterial> {
+        self.insert_asset(
+            &self.materials,
+            name,
+            gltf_file::source_file(name),
+            priority,
+            || PbrMaterial {
+                asset_info: AssetInfo::new(name),
+                properties: MaterialProperties::default(),
+            },
+        )
+    }
+
+    /// Request the node hierarchy of a glTF scene, see `Scene` for its ids, the handle keeps it loaded
+    pub fn get_scene(&self, name: &str) -> StrongHandle<Scene> {
+        self.get_scene_with_priority(name, LoadPriority::Normal)
+    }
+
+    /// Like `get_scene`, the priority decides how soon the load is picked up from the queue
+    pub fn get_scene_with_priority(
+        &self,
+        name: &str,
+        priority: LoadPriority,
+    ) -> StrongHandle<Scene> {
+        let handle = self.insert_scene(name, priority);
+
+        self.scenes.mark_requested(handle);
+
+        self.scenes.strong(handle)
+    }
+
+    pub fn scene(&self, handle: Handle<Scene>) -> Option<Arc<Mutex<Scene>>> {
+        self.scenes.get(handle)
+    }
+
+    fn insert_scene(&self, name: &str, priority: LoadPriority) -> Handle<Scene> {
+        self.insert_asset(
+            &self.scenes,
+            name,
+            gltf_file::source_file(name),
+            priority,
+            || Scene {
+                asset_info: AssetInfo::new(name),
+                nodes: vec![],
+            },
+        )
     }
 }
 
@@ -122,3 +1141,526 @@ impl Default for AssetManager {
         AssetManager::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    /// An asset whose reads are scripted by its id, to test the loader itself
+    struct Scripted {
+        asset_info: AssetInfo,
+        value: usize,
+    }
+
+    static SCRIPTED_READS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Holds the next scripted read until the test lets it go, telling it once the read started
+    static HELD_READ: Mutex<Option<(Sender<()>, Receiver<()>)>> = Mutex::new(None);
+
+    impl LoadableAsset for Scripted {
+        type Data = usize;
+
+        fn read(id: &str, _context: &LoadContext) -> Result<usize, AssetError> {
+            let read = SCRIPTED_READS.fetch_add(1, Ordering::SeqCst);
+
+            match id {
+                "panics" => panic!("bad file"),
+                _ => {
+                    let held = HELD_READ.lock().unwrap().take();
+
+                    if let Some((started, release)) = held {
+                        started.send(()).unwrap();
+                        release.recv().unwrap();
+                    }
+
+                    Ok(read)
+                }
+            }
+        }
+
+        fn publish(&mut self, data: Result<usize, AssetError>) {
+            match data {
+                Ok(value) => {
+                    self.value = value;
+                    self.asset_info.set_loaded();
+                }
+                Err(e) => self.asset_info.set_failed(e),
+            }
+        }
+
+        fn asset_info(&self) -> &AssetInfo {
+            &self.asset_info
+        }
+    }
+
+    fn scripted(id: &str) -> Arc<Mutex<Scripted>> {
+        Arc::new(Mutex::new(Scripted {
+            asset_info: AssetInfo::new(id),
+            value: usize::MAX,
+        }))
+    }
+
+    fn next_event(asset_manager: &AssetManager) -> AssetEvent {
+        asset_manager
+            .events
+            .lock()
+            .unwrap()
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scripted_loads() {
+        let mut asset_manager = AssetManager::with_loader_threads(2);
+        let handle = UntypedHandle::Mesh(Handle::new(0, 0));
+
+        let (started, read_started) = channel();
+        let (release_read, release) = channel();
+        *HELD_READ.lock().unwrap() = Some((started, release));
+
+        // The reload is read first and publishes, the load queued before it is held until then
+        // and dropped
+        let asset = scripted("slow");
+        asset_manager.queue_load(asset.clone(), handle, "slow", LoadPriority::Normal);
+        read_started.recv_timeout(Duration::from_secs(5)).unwrap();
+        asset_manager.queue_load(asset.clone(), handle, "slow", LoadPriority::Normal);
+
+        assert_eq!(next_event(&asset_manager), AssetEvent::Loaded(handle));
+        release_read.send(()).unwrap();
+
+        // Swapping the loader out joins its threads, so the held load has finished
+        drop(std::mem::replace(
+            &mut asset_manager.loader,
+            AssetLoader::new(1),
+        ));
+        assert_eq!(asset.lock().unwrap().value, 1);
+        assert!(asset_manager.drain_events().is_empty());
+
+        // A panicking read fails the asset instead of leaving it loading forever
+        let handle = UntypedHandle::Mesh(Handle::new(1, 0));
+        let asset = scripted("panics");
+        asset_manager.queue_load(asset.clone(), handle, "panics", LoadPriority::Normal);
+
+        match next_event(&asset_manager) {
+            AssetEvent::Failed(failed, AssetError::Panicked(message)) => {
+                assert_eq!(failed, handle);
+                assert_eq!(message, "bad file");
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert_eq!(
+            asset.lock().unwrap().asset_info.status,
+            AssetStatus::Invalid
+        );
+    }
+
+    #[test]
+    fn test_failed_and_unloaded_events() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("missing.glb").weak();
+
+        match next_event(&asset_manager) {
+            AssetEvent::Failed(failed, AssetError::Io(_)) => assert_eq!(failed, handle.into()),
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert_eq!(
+            asset_manager
+                .mesh(handle)
+                .unwrap()
+                .lock()
+                .unwrap()
+                .asset_info
+                .status,
+            AssetStatus::Invalid
+        );
+
+        asset_manager.unload("missing.glb");
+        assert_eq!(
+            asset_manager.drain_events(),
+            vec![AssetEvent::Unloaded(handle.into())]
+        );
+    }
+
+    #[test]
+    fn test_loaded_and_reloaded_events() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("fixtures/nested_nodes.gltf").weak();
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(handle.into())
+        );
+
+        asset_manager.reload("fixtures/nested_nodes.gltf");
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Reloaded(handle.into())
+        );
+    }
+
+    #[test]
+    fn test_concurrent_requests() {
+        // A single loader thread works through the loads in the order they were queued
+        let asset_manager = AssetManager::with_loader_threads(1);
+
+        let handles: Vec<StrongHandle<Mesh>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| asset_manager.get_mesh("fixtures/nested_nodes.gltf")))
+                .collect();
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect()
+        });
+
+        // Every thread gets the same mesh, and it is only loaded once
+        assert!(handles.iter().all(|handle| *handle == handles[0]));
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(handles[0].weak().into())
+        );
+
+        // Queued after any second load of the mesh, so that one has finished once this runs
+        let (done, finished) = channel();
+        asset_manager
+            .loader
+            .submit(LoadPriority::Low, move || done.send(()).unwrap());
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert!(asset_manager.drain_events().is_empty());
+    }
+
+    #[test]
+    fn test_mesh_bounds() {
+        let asset_manager = AssetManager::new();
+
+        let missing = asset_manager.get_mesh("missing.glb").weak();
+        next_event(&asset_manager);
+        assert_eq!(asset_manager.mesh_bounds(missing), None);
+
+        let handle = asset_manager.get_mesh("fixtures/nested_nodes.gltf").weak();
+        next_event(&asset_manager);
+
+        let bounds = asset_manager.mesh_bounds(handle).unwrap();
+        assert_eq!(bounds.aabb.max, glm::vec3(11.0, 6.0, 0.0));
+        assert!(bounds.sphere.radius > 0.0);
+    }
+
+    #[test]
+    fn test_builtin_mesh() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("builtin://cube?size=2").weak();
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(handle.into())
+        );
+
+        let bounds = asset_manager.mesh_bounds(handle).unwrap();
+        assert_eq!(bounds.aabb.max, glm::vec3(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_skinned_mesh() {
+        let asset_manager = AssetManager::new();
+
+        let mesh = asset_manager.get_mesh("fixtures/skinned.gltf").weak();
+        next_event(&asset_manager);
+
+        let skeleton_id = asset_manager
+            .mesh(mesh)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .skeleton
+            .clone()
+            .unwrap();
+        assert_eq!(skeleton_id, "fixtures/skinned.gltf#skins/0");
+
+        let skeleton = asset_manager.get_skeleton(&skeleton_id).weak();
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(skeleton.into())
+        );
+
+        let animation = asset_manager
+            .get_animation("fixtures/skinned.gltf#animations/bend")
+            .weak();
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(animation.into())
+        );
+
+        // Every asset read from the file is reloaded with it
+        let path = watcher::absolute_path(
+            &asset_manager
+                .vfs()
+                .real_path("fixtures/skinned.gltf")
+                .unwrap(),
+        );
+        assert_eq!(asset_manager.asset_paths.lock().unwrap()[&path].len(), 3);
+
+        assert!(asset_manager.unload("fixtures/skinned.gltf#animations/bend"));
+        assert!(asset_manager.animation(animation).is_none());
+    }
+
+    #[test]
+    fn test_meta_file_tracked() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("fixtures/z_up.obj").weak();
+
+        let meta_path = watcher::absolute_path(
+            &asset_manager
+                .vfs()
+                .real_path("fixtures/z_up.obj.meta")
+                .unwrap(),
+        );
+        assert_eq!(
+            asset_manager.asset_paths.lock().unwrap()[&meta_path],
+            vec![UntypedHandle::from(handle)]
+        );
+    }
+
+    #[test]
+    fn test_load_group() {
+        let mut asset_manager = AssetManager::new();
+
+        let group = asset_manager.load_manifest("fixtures/level.toml").unwrap();
+        assert_eq!(
+            asset_manager.load_manifest("fixtures/level.toml"),
+            Ok(group)
+        );
+
+        let progress = asset_manager
+            .wait_for_group(group, Duration::from_secs(5))
+            .unwrap();
+        assert!(progress.is_finished());
+        assert_eq!(
+            (progress.total, progress.loaded, progress.failed),
+            (3, 3, 0)
+        );
+        assert_eq!(progress.bytes_loaded, progress.bytes_total);
+        assert!(progress.bytes_total > 0);
+
+        // Only the sound is ready, the meshes are waiting for the renderer
+        assert_eq!(progress.ready, 1);
+        assert!(!progress.is_ready());
+
+        // The group keeps its assets from being evicted until it is removed
+        asset_manager.evict_unreferenced();
+        asset_manager.evict_unreferenced();
+        assert_eq!(asset_manager.group_progress(group), Some(progress));
+
+        assert!(asset_manager.remove_group(group));
+        assert_eq!(asset_manager.group_progress(group), None);
+        asset_manager.evict_unreferenced();
+        assert_eq!(asset_manager.iter_meshes().count(), 0);
+    }
+
+    #[test]
+    fn test_file_dependencies() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("fixtures/two_materials.obj").weak();
+        next_event(&asset_manager);
+
+        let graph = asset_manager.dependency_graph();
+        assert_eq!(
+            graph.dependencies(handle.into()),
+            [Dependency::File(PathBuf::from(
+                "fixtures/two_materials.mtl"
+            ))]
+        );
+        assert!(graph
+            .files()
+            .contains(&PathBuf::from("fixtures/two_materials.obj")));
+        assert!(graph.missing(asset_manager.vfs()).is_empty());
+    }
+
+    #[test]
+    fn test_material() {
+        let asset_manager = AssetManager::new();
+
+        let path = "fixtures/textured.gltf";
+        let handle = asset_manager
+            .get_material(&format!("{}#materials/painted", path))
+            .weak();
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(handle.into())
+        );
+
+        let material = asset_manager.material(handle).unwrap();
+        let material = material.lock().unwrap();
+        assert_eq!(material.properties.name.as_deref(), Some("painted"));
+
+        // The textures it samples were never requested
+        let graph = asset_manager.dependency_graph();
+        assert_eq!(graph.dependencies(handle.into()).len(), 2);
+        assert_eq!(graph.missing(asset_manager.vfs()).len(), 2);
+        assert!(graph
+            .dependencies(handle.into())
+            .contains(&Dependency::Asset(format!("{}#0", path))));
+    }
+
+    #[test]
+    fn test_scene() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager
+            .get_scene("fixtures/nested_nodes.gltf#scenes/0")
+            .weak();
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Loaded(handle.into())
+        );
+
+        let scene = asset_manager.scene(handle).unwrap();
+        let mesh_id = scene.lock().unwrap().nodes[1].mesh.clone().unwrap();
+
+        // The node's mesh is read on its own, without the transforms of the node or its parent
+        let mesh = asset_manager.get_mesh(&mesh_id).weak();
+        next_event(&asset_manager);
+        let mesh = asset_manager.mesh(mesh).unwrap();
+        assert_eq!(
+            mesh.lock().unwrap().vertices[1].position,
+            glm::vec3(1.0, 0.0, 0.0)
+        );
+
+        // Editing the file reloads the scene along with its meshes
+        let path = watcher::absolute_path(
+            &asset_manager
+                .vfs()
+                .real_path("fixtures/nested_nodes.gltf")
+                .unwrap(),
+        );
+        assert_eq!(asset_manager.asset_paths.lock().unwrap()[&path].len(), 2);
+    }
+
+    #[test]
+    fn test_reload_and_unload_cascade() {
+        let mut asset_manager = AssetManager::new();
+
+        let sound = asset_manager.get_audio("fixtures/beep.wav").weak();
+        let mesh = asset_manager.get_mesh("fixtures/nested_nodes.gltf").weak();
+        next_event(&asset_manager);
+        next_event(&asset_manager);
+
+        // Nothing depends on another asset yet, so pretend the mesh was made from the sound
+        let depend_on_sound = |asset_manager: &AssetManager| {
+            asset_manager.dependencies.lock().unwrap().set_dependencies(
+                mesh.into(),
+                vec![Dependency::Asset("fixtures/beep.wav".to_owned())],
+            )
+        };
+        depend_on_sound(&asset_manager);
+        assert!(asset_manager
+            .dependency_graph()
+            .missing(asset_manager.vfs())
+            .is_empty());
+
+        // The sound is only kept by the mesh depending on it
+        asset_manager.retain(mesh);
+        asset_manager.evict_unreferenced();
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.sound(sound).is_some());
+
+        asset_manager.reload("fixtures/beep.wav");
+        let mut events = vec![next_event(&asset_manager), next_event(&asset_manager)];
+        events.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(
+            events,
+            vec![
+                AssetEvent::Reloaded(mesh.into()),
+                AssetEvent::Reloaded(sound.into())
+            ]
+        );
+
+        // Reading the mesh again replaced its dependencies with those it really has
+        depend_on_sound(&asset_manager);
+        asset_manager.unload("fixtures/beep.wav");
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Unloaded(sound.into())
+        );
+        assert_eq!(
+            next_event(&asset_manager),
+            AssetEvent::Reloaded(mesh.into())
+        );
+    }
+
+    #[test]
+    fn test_unload_invalidates_handle() {
+        let asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("missing.glb").weak();
+
+        assert!(asset_manager.unload("missing.glb"));
+        assert!(asset_manager.mesh(handle).is_none());
+        assert!(!asset_manager.unload("missing.glb"));
+        assert!(!asset_manager.reload("missing.glb"));
+    }
+
+    #[test]
+    fn test_evict_unreferenced() {
+        let mut asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_mesh("missing.glb").weak();
+
+        // Freshly requested assets survive one pass so their users have a chance to reference them
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.mesh(handle).is_some());
+
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.mesh(handle).is_none());
+    }
+
+    #[test]
+    fn test_evict_keeps_referenced() {
+        let mut asset_manager = AssetManager::new();
+
+        let strong = asset_manager.get_mesh("missing.glb");
+        let handle = strong.weak();
+
+        asset_manager.evict_unreferenced();
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.mesh(handle).is_some());
+
+        // Any clone keeps it alive just as well
+        let clone = strong.clone();
+        drop(strong);
+
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.mesh(handle).is_some());
+
+        drop(clone);
+
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.mesh(handle).is_none());
+    }
+
+    #[test]
+    fn test_evict_keeps_retained() {
+        let mut asset_manager = AssetManager::new();
+
+        let handle = asset_manager.get_audio("missing.wav").weak();
+        asset_manager.retain(handle);
+
+        asset_manager.evict_unreferenced();
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.sound(handle).is_some());
+
+        asset_manager.release(handle);
+
+        asset_manager.evict_unreferenced();
+        assert!(asset_manager.sound(handle).is_none());
+    }
+}
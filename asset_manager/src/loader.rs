@@ -0,0 +1,261 @@
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+    thread::{spawn, JoinHandle},
+    time::Duration,
+};
+
+use log::error;
+
+use crate::{
+    asset_info::AssetInfo, vfs::Vfs, AssetError, Dependency, LodSettings, MeshOptimizations,
+};
+
+/// An asset that can be loaded on the `AssetLoader`
+///
+/// Loading is split in two so the slow part, `read`, can run without holding the asset's lock
+/// and only the quick `publish` has to block anyone else looking at the asset
+pub trait LoadableAsset {
+    type Data: Send + 'static;
+
+    fn read(id: &str, context: &LoadContext) -> Result<Self::Data, AssetError>;
+
+    fn publish(&mut self, data: Result<Self::Data, AssetError>);
+
+    fn asset_info(&self) -> &AssetInfo;
+
+    /// Files other than its own and other assets the loaded data was made from,
+    /// see `DependencyGraph`
+    fn dependencies(_data: &Self::Data) -> Vec<Dependency> {
+        vec![]
+    }
+}
+
+/// Settings shared by every load, handed to `LoadableAsset::read`
+#[derive(Clone)]
+pub struct LoadContext {
+    /// Every file is read through it
+    pub vfs: Vfs,
+    /// Where preprocessed assets are kept between runs, nothing is cached when unset
+    pub cache_directory: Option<PathBuf>,
+    /// Passes run on every mesh without an override of its own
+    pub default_mesh_optimizations: MeshOptimizations,
+    /// Passes run on single meshes, by id
+    pub mesh_optimizations: HashMap<String, MeshOptimizations>,
+    /// Simplified levels generated for every mesh
+    pub lod_settings: LodSettings,
+    /// Sounds longer than this are decoded while they play instead of when they are loaded
+    pub sound_streaming_threshold: Duration,
+}
+
+impl LoadContext {
+    pub fn mesh_optimizations(&self, id: &str) -> MeshOptimizations {
+        match self.mesh_optimizations.get(id) {
+            Some(optimizations) => *optimizations,
+            None => self.default_mesh_optimizations,
+        }
+    }
+}
+
+impl Default for LoadContext {
+    fn default() -> Self {
+        LoadContext {
+            vfs: Vfs::working_directory(),
+            cache_directory: None,
+            default_mesh_optimizations: MeshOptimizations::default(),
+            mesh_optimizations: HashMap::new(),
+            lod_settings: LodSettings::default(),
+            sound_streaming_threshold: Duration::from_secs(10),
+        }
+    }
+}
+
+/// The order in which queued loads are picked up, higher priorities first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LoadPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+type LoadJob = Box<dyn FnOnce() + Send>;
+
+struct LoadRequest {
+    priority: LoadPriority,
+    sequence: u64,
+    job: LoadJob,
+}
+
+// Requests of the same priority are handled first come first served
+impl Ord for LoadRequest {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for LoadRequest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for LoadRequest {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for LoadRequest {}
+
+#[derive(Default)]
+struct LoadQueue {
+    requests: BinaryHeap<LoadRequest>,
+    next_sequence: u64,
+    shutdown: bool,
+}
+
+/// A fixed size pool of threads working through a priority queue of asset loads
+pub struct AssetLoader {
+    queue: Arc<(Mutex<LoadQueue>, Condvar)>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl AssetLoader {
+    pub fn new(thread_count: usize) -> AssetLoader {
+        let queue = Arc::new((Mutex::new(LoadQueue::default()), Condvar::new()));
+
+        let workers = (0..thread_count.max(1))
+            .map(|_| {
+                let queue = queue.clone();
+
+                spawn(move || AssetLoader::work(&queue))
+            })
+            .collect();
+
+        AssetLoader { queue, workers }
+    }
+
+    pub fn submit(&self, priority: LoadPriority, job: impl FnOnce() + Send + 'static) {
+        let (queue, condvar) = &*self.queue;
+
+        let mut queue = queue.lock().unwrap();
+
+        let sequence = queue.next_sequence;
+        queue.next_sequence += 1;
+
+        queue.requests.push(LoadRequest {
+            priority,
+            sequence,
+            job: Box::new(job),
+        });
+
+        condvar.notify_one();
+    }
+
+    fn work(queue: &(Mutex<LoadQueue>, Condvar)) {
+        let (queue, condvar) = queue;
+
+        loop {
+            let request = {
+                let mut queue = condvar
+                    .wait_while(queue.lock().unwrap(), |queue| {
+                        queue.requests.is_empty() && !queue.shutdown
+                    })
+                    .unwrap();
+
+                if queue.shutdown {
+                    return;
+                }
+
+                queue.requests.pop().unwrap()
+            };
+
+            // A panicking load should not take the worker down with it
+            if let Err(panic) = catch_unwind(AssertUnwindSafe(request.job)) {
+                error!("Asset load panicked: {}", panic_message(panic.as_ref()));
+            }
+        }
+    }
+}
+
+/// The message a panic was started with, if it was given one
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        (None, None) => "unknown cause".to_owned(),
+    }
+}
+
+impl Drop for AssetLoader {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.queue;
+
+        queue.lock().unwrap().shutdown = true;
+        condvar.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    #[test]
+    fn test_priority_order() {
+        let loader = AssetLoader::new(1);
+        let order = Arc::new(Mutex::new(vec![]));
+
+        // Keep the only worker busy until everything else is queued
+        let (unblock, blocked) = channel::<()>();
+        loader.submit(LoadPriority::Normal, move || {
+            blocked.recv().unwrap();
+        });
+
+        for (priority, name) in [
+            (LoadPriority::Low, "low"),
+            (LoadPriority::Normal, "normal 1"),
+            (LoadPriority::High, "high"),
+            (LoadPriority::Normal, "normal 2"),
+        ] {
+            let order = order.clone();
+
+            loader.submit(priority, move || order.lock().unwrap().push(name));
+        }
+
+        let (done, finished) = channel();
+        loader.submit(LoadPriority::Low, move || done.send(()).unwrap());
+
+        unblock.send(()).unwrap();
+        finished.recv().unwrap();
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["high", "normal 1", "normal 2", "low"]
+        );
+    }
+
+    #[test]
+    fn test_survives_panicking_load() {
+        let loader = AssetLoader::new(1);
+
+        loader.submit(LoadPriority::Normal, || panic!("failed load"));
+
+        let (done, finished) = channel();
+        loader.submit(LoadPriority::Normal, move || done.send(()).unwrap());
+
+        finished.recv().unwrap();
+    }
+}
@@ -0,0 +1,293 @@
+use log::warn;
+
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    gltf_file,
+    loader::{LoadContext, LoadableAsset},
+    texture, AssetError, Dependency,
+};
+
+/// The array of a glTF file materials are read from, see `gltf_file::fragment_id`
+const KIND: &str = "materials";
+
+/// How the alpha of the base color is used
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha is ignored
+    #[default]
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded, the rest are opaque
+    Mask(f32),
+    /// Blended with what is behind it
+    Blend,
+}
+
+/// A texture sampled by a material
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MaterialTexture {
+    /// Id of the texture, to be requested through `AssetManager::get_texture`
+    pub id: String,
+    /// The set of texture coordinates it is sampled with
+    pub tex_coord: u32,
+}
+
+/// The metallic-roughness parameters of a material, as the glTF specification defines them.
+/// Factors multiply the values sampled from their texture
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialProperties {
+    pub name: Option<String>,
+    pub base_color: glm::Vec4,
+    pub base_color_texture: Option<MaterialTexture>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Roughness in the green channel and metalness in the blue one
+    pub metallic_roughness_texture: Option<MaterialTexture>,
+    /// Tangent space normals
+    pub normal_texture: Option<MaterialTexture>,
+    /// Scales the x and y of the sampled normals
+    pub normal_scale: f32,
+    /// Ambient occlusion in the red channel
+    pub occlusion_texture: Option<MaterialTexture>,
+    /// How much of the occlusion is applied, between 0 and 1
+    pub occlusion_strength: f32,
+    pub emissive: glm::Vec3,
+    pub emissive_texture: Option<MaterialTexture>,
+    pub alpha_mode: AlphaMode,
+    /// Back faces are drawn as well, lit as if they faced the other way
+    pub double_sided: bool,
+}
+
+impl MaterialProperties {
+    /// Every texture the material samples
+    pub fn textures(&self) -> impl Iterator<Item = &MaterialTexture> {
+        [
+            &self.base_color_texture,
+            &self.metallic_roughness_texture,
+            &self.normal_texture,
+            &self.occlusion_texture,
+            &self.emissive_texture,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl Default for MaterialProperties {
+    fn default() -> Self {
+        MaterialProperties {
+            name: None,
+            base_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: glm::Vec3::zeros(),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        }
+    }
+}
+
+/// A material of a glTF file, which submeshes refer to through `MeshMaterial::id`
+///
+/// Materials are referenced as `<path>#materials/<index or name>`, for example
+/// `robot.glb#materials/0`. Anything that parses as a number is taken as an index
+pub struct PbrMaterial {
+    pub asset_info: AssetInfo,
+    pub properties: MaterialProperties,
+}
+
+impl PbrMaterial {
+    pub fn unload(&mut self) {
+        self.properties = MaterialProperties::default();
+        self.asset_info.status = AssetStatus::Unloaded;
+    }
+
+    fn read_material(path: &str, material: &gltf::Material) -> MaterialProperties {
+        let pbr = material.pbr_metallic_roughness();
+
+        let material_texture = |texture: gltf::Texture, tex_coord: u32| MaterialTexture {
+            id: texture::gltf_texture_id(path, &texture),
+            tex_coord,
+        };
+
+        let alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => {
+                AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5))
+            }
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        };
+
+        MaterialProperties {
+            name: material.name().map(str::to_owned),
+            base_color: glm::Vec4::from(pbr.base_color_factor()),
+            base_color_texture: pbr
+                .base_color_texture()
+                .map(|info| material_texture(info.texture(), info.tex_coord())),
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            metallic_roughness_texture: pbr
+                .metallic_roughness_texture()
+                .map(|info| material_texture(info.texture(), info.tex_coord())),
+            normal_texture: material
+                .normal_texture()
+                .map(|info| material_texture(info.texture(), info.tex_coord())),
+            normal_scale: material.normal_texture().map_or(1.0, |info| info.scale()),
+            occlusion_texture: material
+                .occlusion_texture()
+                .map(|info| material_texture(info.texture(), info.tex_coord())),
+            occlusion_strength: material
+                .occlusion_texture()
+                .map_or(1.0, |info| info.strength()),
+            emissive: glm::Vec3::from(material.emissive_factor()),
+            emissive_texture: material
+                .emissive_texture()
+                .map(|info| material_texture(info.texture(), info.tex_coord())),
+            alpha_mode,
+            double_sided: material.double_sided(),
+        }
+    }
+}
+
+/// Id of the material of the glTF file at `path`
+pub(crate) fn material_id(path: &str, index: usize) -> String {
+    gltf_file::fragment_id(path, KIND, index)
+}
+
+impl LoadableAsset for PbrMaterial {
+    type Data = MaterialProperties;
+
+    fn read(id: &str, context: &LoadContext) -> Result<MaterialProperties, AssetError> {
+        let (path, name) = match gltf_file::split_fragment_id(id, KIND) {
+            Some((path, name)) => (path, name),
+            None => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+        };
+
+        // Materials are only made of JSON, the buffers are not needed
+        let document = gltf::Gltf::from_slice(&context.vfs.read(path)?)?.document;
+
+        let material = match name.parse::<usize>() {
+            Ok(index) => document.materials().nth(index),
+            Err(_) => document
+                .materials()
+                .find(|material| material.name() == Some(name)),
+        };
+
+        match material {
+            Some(material) => Ok(PbrMaterial::read_material(path, &material)),
+            None => Err(AssetError::Parse(format!(
+                "{} has no material {}",
+                path, name
+            ))),
+        }
+    }
+
+    fn publish(&mut self, properties: Result<MaterialProperties, AssetError>) {
+        match properties {
+            Ok(properties) => {
+                self.properties = properties;
+                self.asset_info.set_loaded();
+            }
+            Err(e) => {
+                warn!("Failed to load material {}: {}", self.asset_info.id, e);
+
+                self.asset_info.set_failed(e);
+            }
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    /// Reloading a texture reloads the materials sampling it
+    fn dependencies(properties: &MaterialProperties) -> Vec<Dependency> {
+        let mut dependencies: Vec<Dependency> = vec![];
+
+        // Packed textures like occlusion-roughness-metallic are sampled more than once
+        for texture in properties.textures() {
+            let dependency = Dependency::Asset(texture.id.clone());
+
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+
+        dependencies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_factors() {
+        let glass = PbrMaterial::read(
+            "fixtures/materials.gltf#materials/glass",
+            &LoadContext::default(),
+        )
+        .unwrap();
+
+        assert_eq!(glass.base_color, glm::vec4(0.5, 0.5, 1.0, 0.25));
+        assert_eq!((glass.metallic, glass.roughness), (0.0, 0.1));
+        assert_eq!(glass.alpha_mode, AlphaMode::Blend);
+        assert!(glass.double_sided);
+        assert_eq!(glass.textures().count(), 0);
+
+        let leaves = PbrMaterial::read(
+            "fixtures/materials.gltf#materials/1",
+            &LoadContext::default(),
+        )
+        .unwrap();
+
+        assert_eq!(leaves.name.as_deref(), Some("leaves"));
+        assert_eq!(leaves.alpha_mode, AlphaMode::Mask(0.3));
+        assert_eq!(leaves.emissive, glm::vec3(1.0, 0.5, 0.0));
+        // Unset factors take the defaults of the specification
+        assert_eq!(leaves.base_color, glm::vec4(1.0, 1.0, 1.0, 1.0));
+        assert_eq!((leaves.metallic, leaves.roughness), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_read_textures() {
+        let path = "fixtures/textured.gltf";
+
+        let painted = PbrMaterial::read(&material_id(path, 0), &LoadContext::default()).unwrap();
+
+        assert_eq!(
+            painted.base_color_texture,
+            Some(MaterialTexture {
+                id: format!("{}#0", path),
+                tex_coord: 0
+            })
+        );
+        assert_eq!(painted.emissive, glm::vec3(1.0, 1.0, 1.0));
+        assert_eq!(painted.emissive_texture.as_ref().unwrap().tex_coord, 1);
+        assert_eq!(painted.occlusion_strength, 0.5);
+        assert_eq!(painted.textures().count(), 4);
+
+        // The occlusion is packed with the metalness and roughness, it is only depended on once
+        assert_eq!(
+            painted.occlusion_texture,
+            painted.metallic_roughness_texture
+        );
+        assert_eq!(PbrMaterial::dependencies(&painted).len(), 2);
+    }
+
+    #[test]
+    fn test_read_missing_material() {
+        assert!(PbrMaterial::read(
+            "fixtures/materials.gltf#materials/2",
+            &LoadContext::default()
+        )
+        .is_err());
+        assert!(PbrMaterial::read("fixtures/materials.gltf", &LoadContext::default()).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use super::{Submesh, Vertex};
+
+/// A box and a sphere around the geometry of a mesh or submesh, both kept after its vertices are freed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    /// Bounds around the vertices, with the box given instead of computed when it is already known
+    pub fn new(aabb: Aabb, vertices: &[Vertex]) -> Bounds {
+        Bounds {
+            aabb,
+            sphere: BoundingSphere::around(aabb.center(), vertices),
+        }
+    }
+
+    pub fn from_vertices(vertices: &[Vertex]) -> Bounds {
+        Bounds::new(Aabb::from_vertices(vertices), vertices)
+    }
+
+    /// Bounds of a whole mesh, around the boxes of its submeshes
+    pub fn from_submeshes(submeshes: &[Submesh], vertices: &[Vertex]) -> Bounds {
+        let aabb = submeshes
+            .iter()
+            .map(|submesh| submesh.bounds.aabb)
+            .reduce(|aabb, submesh| aabb.union(&submesh))
+            .unwrap_or_default();
+
+        Bounds::new(aabb, vertices)
+    }
+}
+
+/// A sphere around every vertex of a mesh, centered on its box rather than fitted as tightly as possible
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: glm::Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn around(center: glm::Vec3, vertices: &[Vertex]) -> BoundingSphere {
+        let radius = vertices
+            .iter()
+            .map(|vertex| glm::distance(&center, &vertex.position))
+            .fold(0.0, f32::max);
+
+        BoundingSphere { center, radius }
+    }
+}
+
+impl Default for BoundingSphere {
+    fn default() -> Self {
+        BoundingSphere {
+            center: glm::Vec3::zeros(),
+            radius: 0.0,
+        }
+    }
+}
+
+/// An axis aligned box around every vertex of a mesh
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    /// The smallest box around the vertices, a mesh without vertices gets an empty box at the origin
+    pub fn from_vertices(vertices: &[Vertex]) -> Aabb {
+        let mut vertices = vertices.iter();
+
+        let first = match vertices.next() {
+            Some(vertex) => vertex.position,
+            None => return Aabb::default(),
+        };
+
+        vertices.fold(
+            Aabb {
+                min: first,
+                max: first,
+            },
+            |bounds, vertex| Aabb {
+                min: glm::min2(&bounds.min, &vertex.position),
+                max: glm::max2(&bounds.max, &vertex.position),
+            },
+        )
+    }
+
+    /// The smallest box around both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    /// The smallest box around the transformed corners of the box
+    pub fn transformed(&self, transform: &glm::Mat4) -> Aabb {
+        let corners = (0..8).map(|corner| {
+            let select = |bit: usize, axis: usize| match corner & bit {
+                0 => self.min[axis],
+                _ => self.max[axis],
+            };
+
+            (transform * glm::vec4(select(1, 0), select(2, 1), select(4, 2), 1.0)).xyz()
+        });
+
+        corners
+            .map(|corner| Aabb {
+                min: corner,
+                max: corner,
+            })
+            .reduce(|bounds, corner| bounds.union(&corner))
+            .unwrap()
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb {
+            min: glm::Vec3::zeros(),
+            max: glm::Vec3::zeros(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_vertices() {
+        let vertices: Vec<Vertex> = [
+            glm::vec3(1.0, -2.0, 0.5),
+            glm::vec3(-1.0, 4.0, 0.0),
+            glm::vec3(0.0, 0.0, 3.0),
+        ]
+        .into_iter()
+        .map(|position| Vertex {
+            position,
+            ..Default::default()
+        })
+        .collect();
+
+        assert_eq!(
+            Aabb::from_vertices(&vertices),
+            Aabb {
+                min: glm::vec3(-1.0, -2.0, 0.0),
+                max: glm::vec3(1.0, 4.0, 3.0),
+            }
+        );
+        assert_eq!(Aabb::from_vertices(&[]), Aabb::default());
+
+        let bounds = Bounds::from_vertices(&vertices);
+        assert_eq!(bounds.sphere.center, glm::vec3(0.0, 1.0, 1.5));
+        assert_eq!(bounds.sphere.radius, 3.5);
+    }
+
+    #[test]
+    fn test_transformed() {
+        let aabb = Aabb {
+            min: glm::vec3(-1.0, -2.0, -3.0),
+            max: glm::vec3(1.0, 2.0, 3.0),
+        };
+
+        let translated = aabb.transformed(&glm::translation(&glm::vec3(10.0, 0.0, 0.0)));
+        assert_eq!(translated.min, glm::vec3(9.0, -2.0, -3.0));
+        assert_eq!(translated.max, glm::vec3(11.0, 2.0, 3.0));
+
+        // A quarter turn around z swaps the x and y extents
+        let rotated = aabb.transformed(&glm::rotation(
+            std::f32::consts::FRAC_PI_2,
+            &glm::vec3(0.0, 0.0, 1.0),
+        ));
+        assert!(glm::distance(&rotated.min, &glm::vec3(-2.0, -1.0, -3.0)) < 1e-5);
+        assert!(glm::distance(&rotated.max, &glm::vec3(2.0, 1.0, 3.0)) < 1e-5);
+    }
+}
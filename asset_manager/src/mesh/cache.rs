@@ -0,0 +1,901 @@
+use std::{
+    fs::{self, File},
+    hash::Hash,
+    iter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use memmap2::Mmap;
+use xxhash_rust::xxh3::{xxh3_128, Xxh3};
+
+use super::{
+    Aabb, BoundingSphere, Bounds, Indices, MeshData, MeshLod, MeshMaterial, MorphTarget, Submesh,
+    Vertex, VertexLayout,
+};
+use crate::{gltf_file, loader::LoadContext, meta, AssetError};
+
+/// Bumped whenever the blob layout or the output of an importer changes,
+/// so blobs written by an older build are never read back
+const FORMAT_VERSION: u32 = 9;
+
+const MAGIC: &[u8; 4] = b"RDMC";
+
+/// A file a cached mesh was imported from, as it was when the blob was written
+struct SourceFile {
+    path: PathBuf,
+    size: u64,
+    /// See `timestamp`
+    modified: u128,
+    hash: u128,
+}
+
+/// Read the cached mesh of a source file
+///
+/// Returns `None` when nothing is cached yet or the files the source depends on have changed,
+/// an error means the blob is corrupt and should be rewritten
+///
+/// Files whose size and modification time are unchanged are trusted as is, only the others are
+/// read and hashed. Files that were touched without changing get their new time written back
+pub fn read(
+    directory: &Path,
+    context: &LoadContext,
+    source: &str,
+) -> Result<Option<MeshData>, AssetError> {
+    let path = blob_path(directory, context, source)?;
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return Ok(None),
+    };
+
+    // Safety: blobs are only ever replaced by renaming a new file over them, never written in place,
+    // and anything else touching the file is caught by the validation below
+    let blob = unsafe { Mmap::map(&file)? };
+
+    let mut reader = BlobReader { bytes: &blob };
+
+    if reader.take(MAGIC.len())? != MAGIC || reader.u32()? != FORMAT_VERSION {
+        return Err(AssetError::Parse("Not a mesh cache blob".to_owned()));
+    }
+
+    let mut files = reader.files()?;
+    let mut restamped = false;
+
+    for file in &mut files {
+        let modified = timestamp(context.vfs.modified(&file.path));
+
+        if modified != 0
+            && modified == file.modified
+            && context.vfs.size(&file.path) == Some(file.size)
+        {
+            continue;
+        }
+
+        match context.vfs.read(&file.path) {
+            Ok(contents) if xxh3_128(&contents) == file.hash => {
+                restamped |= modified != file.modified;
+                file.modified = modified;
+            }
+            _ => return Ok(None),
+        }
+    }
+
+    let bounds = reader.bounds()?;
+    let vertex_layout = VertexLayout::from_bits(reader.u8()?);
+
+    let data = MeshData {
+        bounds,
+        vertex_layout,
+        vertices: reader.vertices(vertex_layout)?,
+        indices: reader.indices()?,
+        submeshes: reader.submeshes()?,
+        materials: reader.materials()?,
+        lods: reader.lods()?,
+        skeleton: reader.optional_string()?,
+        morph_targets: reader.morph_targets()?,
+        morph_weights: reader.f32_array()?,
+        // The first file is the source itself
+        dependencies: files[1..].iter().map(|file| file.path.clone()).collect(),
+        optimized: reader.u8()? != 0,
+    };
+
+    if !reader.bytes.is_empty() {
+        return Err(AssetError::Parse(
+            "Trailing data in mesh cache blob".to_owned(),
+        ));
+    }
+
+    validate(&data)?;
+
+    // Unmapped before the blob is replaced, which some platforms refuse for mapped files
+    drop(blob);
+
+    if restamped {
+        if let Err(e) = write_blob(&path, &files, &data) {
+            warn!("Failed to update the cached mesh of {}: {}", source, e);
+        }
+    }
+
+    Ok(Some(data))
+}
+
+/// Check that every index and range of the mesh lies within its vertices and indices,
+/// so a blob that was tampered with can not send the renderer out of bounds
+fn validate(data: &MeshData) -> Result<(), AssetError> {
+    let indices: Vec<u32> = data.indices.iter().collect();
+
+    let submeshes = data
+        .submeshes
+        .iter()
+        .chain(data.lods.iter().flat_map(|lod| &lod.submeshes));
+
+    for submesh in submeshes {
+        let index_range =
+            submesh.first_index as u64..submesh.first_index as u64 + submesh.index_count as u64;
+        let vertex_end = submesh.first_vertex as u64 + submesh.vertex_count as u64;
+
+        if index_range.end > indices.len() as u64 || vertex_end > data.vertices.len() as u64 {
+            return Err(AssetError::Parse(
+                "Mesh cache blob has a submesh out of range".to_owned(),
+            ));
+        }
+
+        let range = index_range.start as usize..index_range.end as usize;
+
+        if indices[range]
+            .iter()
+            .any(|index| *index >= submesh.vertex_count)
+        {
+            return Err(AssetError::Parse(
+                "Mesh cache blob has an index out of range".to_owned(),
+            ));
+        }
+
+        if submesh
+            .material_index
+            .is_some_and(|material| material >= data.materials.len())
+        {
+            return Err(AssetError::Parse(
+                "Mesh cache blob has a material out of range".to_owned(),
+            ));
+        }
+    }
+
+    if data
+        .morph_targets
+        .iter()
+        .any(|target| target.submesh >= data.submeshes.len())
+    {
+        return Err(AssetError::Parse(
+            "Mesh cache blob has a morph target out of range".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Cache the mesh imported from a source file, replacing any blob already cached for it
+pub fn write(
+    directory: &Path,
+    context: &LoadContext,
+    source: &str,
+    data: &MeshData,
+) -> Result<(), AssetError> {
+    let path = blob_path(directory, context, source)?;
+    let files = source_files(context, source, &data.dependencies)?;
+
+    fs::create_dir_all(directory)?;
+
+    write_blob(&path, &files, data)
+}
+
+fn write_blob(path: &Path, files: &[SourceFile], data: &MeshData) -> Result<(), AssetError> {
+    let mut writer = BlobWriter::default();
+
+    writer.bytes.extend_from_slice(MAGIC);
+    writer.u32(FORMAT_VERSION);
+    writer.files(files)?;
+    writer.bounds(&data.bounds);
+    writer.bytes.push(data.vertex_layout.bits());
+    writer.vertices(&data.vertices, data.vertex_layout);
+    writer.indices(&data.indices);
+    writer.submeshes(&data.submeshes);
+    writer.materials(&data.materials);
+    writer.lods(&data.lods);
+    writer.optional_string(data.skeleton.as_deref());
+    writer.morph_targets(&data.morph_targets);
+    writer.f32_array(&data.morph_weights);
+    writer.bytes.push(data.optimized as u8);
+
+    // Written next to the blob first so a reader never reads a half written file
+    let partial_path = path.with_extension(format!("{}.partial", std::process::id()));
+
+    fs::write(&partial_path, &writer.bytes)?;
+    fs::rename(&partial_path, path)?;
+
+    Ok(())
+}
+
+/// The source file followed by the files it depends on, stamped before they are read
+/// so a change made while hashing them is still noticed by the next `read`
+fn source_files(
+    context: &LoadContext,
+    source: &str,
+    dependencies: &[PathBuf],
+) -> Result<Vec<SourceFile>, AssetError> {
+    iter::once(PathBuf::from(gltf_file::source_file(source)))
+        .chain(dependencies.iter().cloned())
+        .map(|path| {
+            let modified = timestamp(context.vfs.modified(&path));
+            let contents = context.vfs.read(&path)?;
+
+            Ok(SourceFile {
+                path,
+                size: contents.len() as u64,
+                modified,
+                hash: xxh3_128(&contents),
+            })
+        })
+        .collect()
+}
+
+/// Nanoseconds since the Unix epoch, 0 for sources that do not know when their files changed
+fn timestamp(modified: Option<SystemTime>) -> u128 {
+    modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_nanos())
+}
+
+/// The blob of a source file, named after a hash of its id and the import settings,
+/// those of its meta file included, so any change to them misses the cache.
+/// Changes to the source file itself are caught by `read`
+fn blob_path(directory: &Path, context: &LoadContext, source: &str) -> Result<PathBuf, AssetError> {
+    let mut hasher = Xxh3::new();
+
+    hasher.update(source.as_bytes());
+    hasher.update(&meta::contents(source, &context.vfs)?);
+    hasher.update(&FORMAT_VERSION.to_le_bytes());
+    context.mesh_optimizations(source).hash(&mut hasher);
+
+    let lod_settings = &context.lod_settings;
+    hasher.update(&lod_settings.levels.to_le_bytes());
+    hasher.update(&lod_settings.reduction.to_le_bytes());
+    hasher.update(&lod_settings.max_error.to_le_bytes());
+
+    Ok(directory.join(format!("{:032x}.mesh", hasher.digest128())))
+}
+
+#[derive(Default)]
+struct BlobWriter {
+    bytes: Vec<u8>,
+}
+
+impl BlobWriter {
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u128(&mut self, value: u128) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32s(&mut self, values: &[f32]) {
+        for value in values {
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn f32_array(&mut self, values: &[f32]) {
+        self.u32(values.len() as u32);
+        self.f32s(values);
+    }
+
+    fn vec3_array(&mut self, values: &[glm::Vec3]) {
+        self.u32(values.len() as u32);
+
+        for value in values {
+            self.f32s(value.as_slice());
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn optional_string(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.bytes.push(1);
+                self.string(value);
+            }
+            None => self.bytes.push(0),
+        }
+    }
+
+    fn files(&mut self, files: &[SourceFile]) -> Result<(), AssetError> {
+        self.u32(files.len() as u32);
+
+        for file in files {
+            let path = match file.path.to_str() {
+                Some(path) => path,
+                None => return Err(AssetError::Io("Path is not valid UTF-8".to_owned())),
+            };
+
+            self.string(path);
+            self.u64(file.size);
+            self.u128(file.modified);
+            self.u128(file.hash);
+        }
+
+        Ok(())
+    }
+
+    fn bounds(&mut self, bounds: &Bounds) {
+        self.f32s(bounds.aabb.min.as_slice());
+        self.f32s(bounds.aabb.max.as_slice());
+        self.f32s(bounds.sphere.center.as_slice());
+        self.f32s(&[bounds.sphere.radius]);
+    }
+
+    /// Only the attributes of the layout are stored, the others are left to their default value
+    fn vertices(&mut self, vertices: &[Vertex], layout: VertexLayout) {
+        self.u32(vertices.len() as u32);
+
+        for vertex in vertices {
+            for attribute in layout.attributes() {
+                self.f32s(attribute.read(vertex));
+            }
+        }
+    }
+
+    fn indices(&mut self, indices: &Indices) {
+        match indices {
+            Indices::U16(indices) => {
+                self.u32(16);
+                self.u32(indices.len() as u32);
+
+                for index in indices {
+                    self.bytes.extend_from_slice(&index.to_le_bytes());
+                }
+            }
+            Indices::U32(indices) => {
+                self.u32(32);
+                self.u32(indices.len() as u32);
+
+                for index in indices {
+                    self.bytes.extend_from_slice(&index.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    fn submeshes(&mut self, submeshes: &[Submesh]) {
+        self.u32(submeshes.len() as u32);
+
+        for submesh in submeshes {
+            self.u32(submesh.first_index);
+            self.u32(submesh.index_count);
+            self.u32(submesh.first_vertex);
+            self.u32(submesh.vertex_count);
+            self.u32(
+                submesh
+                    .material_index
+                    .map_or(u32::MAX, |index| index as u32),
+            );
+            self.bounds(&submesh.bounds);
+        }
+    }
+
+    fn materials(&mut self, materials: &[MeshMaterial]) {
+        self.u32(materials.len() as u32);
+
+        for material in materials {
+            self.optional_string(material.name.as_deref());
+            self.optional_string(material.id.as_deref());
+            self.f32s(material.base_color.as_slice());
+            self.optional_string(material.base_color_texture.as_deref());
+        }
+    }
+
+    fn lods(&mut self, lods: &[MeshLod]) {
+        self.u32(lods.len() as u32);
+
+        for lod in lods {
+            self.f32s(&[lod.error]);
+            self.submeshes(&lod.submeshes);
+        }
+    }
+
+    fn morph_targets(&mut self, targets: &[MorphTarget]) {
+        self.u32(targets.len() as u32);
+
+        for target in targets {
+            self.u32(target.submesh as u32);
+            self.u32(target.node as u32);
+            self.u32(target.weight as u32);
+            self.vec3_array(&target.position_deltas);
+            self.vec3_array(&target.normal_deltas);
+        }
+    }
+}
+
+/// Reads a blob front to back, failing instead of panicking when it is cut short
+struct BlobReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BlobReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], AssetError> {
+        if count > self.bytes.len() {
+            return Err(AssetError::Parse("Mesh cache blob is truncated".to_owned()));
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    /// Take `count` items of `size` bytes, checking the length before anything is allocated for them
+    fn take_array(&mut self, count: u32, size: usize) -> Result<&'a [u8], AssetError> {
+        match (count as usize).checked_mul(size) {
+            Some(length) => self.take(length),
+            None => Err(AssetError::Parse("Mesh cache blob is truncated".to_owned())),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, AssetError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, AssetError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, AssetError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u128(&mut self) -> Result<u128, AssetError> {
+        Ok(u128::from_le_bytes(self.take(16)?.try_into().unwrap()))
+    }
+
+    fn f32s(&mut self, values: &mut [f32]) -> Result<(), AssetError> {
+        let bytes = self.take_array(values.len() as u32, 4)?;
+
+        for (value, bytes) in values.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        Ok(())
+    }
+
+    fn vec3(&mut self) -> Result<glm::Vec3, AssetError> {
+        let mut value = glm::Vec3::zeros();
+        self.f32s(value.as_mut_slice())?;
+
+        Ok(value)
+    }
+
+    fn f32_array(&mut self) -> Result<Vec<f32>, AssetError> {
+        let count = self.u32()?;
+
+        Ok(self
+            .take_array(count, 4)?
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect())
+    }
+
+    fn vec3_array(&mut self) -> Result<Vec<glm::Vec3>, AssetError> {
+        let count = self.u32()?;
+
+        // Checked up front so a corrupt count can not allocate more than the blob holds
+        let mut array_reader = BlobReader {
+            bytes: self.take_array(count, 12)?,
+        };
+
+        (0..count).map(|_| array_reader.vec3()).collect()
+    }
+
+    fn string(&mut self) -> Result<String, AssetError> {
+        let length = self.u32()?;
+
+        match String::from_utf8(self.take_array(length, 1)?.to_vec()) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(AssetError::Parse(e.to_string())),
+        }
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, AssetError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.string()?)),
+        }
+    }
+
+    fn files(&mut self) -> Result<Vec<SourceFile>, AssetError> {
+        let count = self.u32()?;
+
+        let files: Vec<SourceFile> = (0..count)
+            .map(|_| {
+                Ok(SourceFile {
+                    path: PathBuf::from(self.string()?),
+                    size: self.u64()?,
+                    modified: self.u128()?,
+                    hash: self.u128()?,
+                })
+            })
+            .collect::<Result<_, AssetError>>()?;
+
+        match files.is_empty() {
+            true => Err(AssetError::Parse(
+                "Mesh cache blob has no source file".to_owned(),
+            )),
+            false => Ok(files),
+        }
+    }
+
+    fn bounds(&mut self) -> Result<Bounds, AssetError> {
+        let aabb = Aabb {
+            min: self.vec3()?,
+            max: self.vec3()?,
+        };
+        let center = self.vec3()?;
+
+        let mut radius = [0.0];
+        self.f32s(&mut radius)?;
+
+        Ok(Bounds {
+            aabb,
+            sphere: BoundingSphere {
+                center,
+                radius: radius[0],
+            },
+        })
+    }
+
+    fn vertices(&mut self, layout: VertexLayout) -> Result<Vec<Vertex>, AssetError> {
+        let count = self.u32()?;
+
+        // Without any attribute no bytes are read, and nothing would bound the count
+        if layout.vertex_size() == 0 && count > 0 {
+            return Err(AssetError::Parse(
+                "Mesh cache blob has vertices without attributes".to_owned(),
+            ));
+        }
+
+        let bytes = self.take_array(count, layout.vertex_size())?;
+
+        let mut vertex_reader = BlobReader { bytes };
+
+        let mut vertices = vec![Vertex::default(); count as usize];
+
+        for vertex in &mut vertices {
+            for attribute in layout.attributes() {
+                vertex_reader.f32s(attribute.read_mut(vertex))?;
+            }
+        }
+
+        Ok(vertices)
+    }
+
+    fn indices(&mut self) -> Result<Indices, AssetError> {
+        let width = self.u32()?;
+        let count = self.u32()?;
+
+        match width {
+            16 => Ok(Indices::U16(
+                self.take_array(count, 2)?
+                    .chunks_exact(2)
+                    .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect(),
+            )),
+            32 => Ok(Indices::U32(
+                self.take_array(count, 4)?
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect(),
+            )),
+            _ => Err(AssetError::Parse(format!("Unknown index width {}", width))),
+        }
+    }
+
+    fn submeshes(&mut self) -> Result<Vec<Submesh>, AssetError> {
+        let count = self.u32()?;
+
+        (0..count)
+            .map(|_| {
+                Ok(Submesh {
+                    first_index: self.u32()?,
+                    index_count: self.u32()?,
+                    first_vertex: self.u32()?,
+                    vertex_count: self.u32()?,
+                    material_index: match self.u32()? {
+                        u32::MAX => None,
+                        index => Some(index as usize),
+                    },
+                    bounds: self.bounds()?,
+                })
+            })
+            .collect()
+    }
+
+    fn materials(&mut self) -> Result<Vec<MeshMaterial>, AssetError> {
+        let count = self.u32()?;
+
+        (0..count)
+            .map(|_| {
+                let name = self.optional_string()?;
+                let id = self.optional_string()?;
+
+                let mut base_color = glm::Vec4::zeros();
+                self.f32s(base_color.as_mut_slice())?;
+
+                Ok(MeshMaterial {
+                    name,
+                    id,
+                    base_color,
+                    base_color_texture: self.optional_string()?,
+                })
+            })
+            .collect()
+    }
+
+    fn lods(&mut self) -> Result<Vec<MeshLod>, AssetError> {
+        let count = self.u32()?;
+
+        (0..count)
+            .map(|_| {
+                let mut error = [0.0];
+                self.f32s(&mut error)?;
+
+                Ok(MeshLod {
+                    error: error[0],
+                    submeshes: self.submeshes()?,
+                })
+            })
+            .collect()
+    }
+
+    fn morph_targets(&mut self) -> Result<Vec<MorphTarget>, AssetError> {
+        let count = self.u32()?;
+
+        (0..count)
+            .map(|_| {
+                Ok(MorphTarget {
+                    submesh: self.u32()? as usize,
+                    node: self.u32()? as usize,
+                    weight: self.u32()? as usize,
+                    position_deltas: self.vec3_array()?,
+                    normal_deltas: self.vec3_array()?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::mesh::{gltf_importer, obj_importer};
+    use crate::{vfs::Vfs, MeshOptimizations};
+
+    /// A cache directory of its own for every test, as tests run in parallel
+    fn cache_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("mesh_cache_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        directory
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let directory = cache_directory("round_trip");
+        let context = LoadContext::default();
+        let source = "fixtures/nested_nodes.gltf";
+
+        assert!(read(&directory, &context, source).unwrap().is_none());
+
+        let mut data = gltf_importer::read(source, &context.vfs).unwrap();
+        data.lods = vec![MeshLod {
+            error: 0.25,
+            submeshes: data.submeshes[..1].to_vec(),
+        }];
+        write(&directory, &context, source, &data).unwrap();
+
+        let cached = read(&directory, &context, source).unwrap().unwrap();
+
+        assert_eq!(cached.vertices, data.vertices);
+        assert_eq!(cached.indices, data.indices);
+        assert_eq!(cached.submeshes, data.submeshes);
+        assert_eq!(cached.materials, data.materials);
+        assert_eq!(cached.bounds, data.bounds);
+        assert_eq!(cached.dependencies, data.dependencies);
+        assert_eq!(cached.lods, data.lods);
+        assert_eq!(cached.optimized, data.optimized);
+
+        // Optimizing the mesh changes what would be cached for it
+        let mut optimized_context = context.clone();
+        optimized_context.default_mesh_optimizations = MeshOptimizations::all();
+        assert!(read(&directory, &optimized_context, source)
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip_morph_targets() {
+        let directory = cache_directory("round_trip_morph_targets");
+        let context = LoadContext::default();
+        let source = "fixtures/morph_targets.gltf";
+
+        let data = gltf_importer::read(source, &context.vfs).unwrap();
+        write(&directory, &context, source, &data).unwrap();
+
+        let cached = read(&directory, &context, source).unwrap().unwrap();
+
+        assert_eq!(cached.morph_targets, data.morph_targets);
+        assert_eq!(cached.morph_weights, data.morph_weights);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_changed_dependency() {
+        let directory = cache_directory("changed_dependency");
+        let source_directory = directory.join("source");
+        fs::create_dir_all(&source_directory).unwrap();
+
+        for file in ["two_materials.obj", "two_materials.mtl"] {
+            fs::copy(
+                Path::new("fixtures").join(file),
+                source_directory.join(file),
+            )
+            .unwrap();
+        }
+
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("", &source_directory);
+        let context = LoadContext {
+            vfs,
+            ..Default::default()
+        };
+        let source = "two_materials.obj";
+
+        write(
+            &directory,
+            &context,
+            source,
+            &obj_importer::read(source, &context.vfs).unwrap(),
+        )
+        .unwrap();
+        assert!(read(&directory, &context, source).unwrap().is_some());
+
+        // Only the MTL file changes, the cache is keyed by the OBJ file so it has to notice on its own
+        let material_library = source_directory.join("two_materials.mtl");
+        let mut materials = fs::read_to_string(&material_library).unwrap();
+        materials.push_str("\nnewmtl third\nKd 0 0 1\n");
+        fs::write(&material_library, materials).unwrap();
+
+        assert!(read(&directory, &context, source).unwrap().is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_touched_source() {
+        let directory = cache_directory("touched_source");
+        let source_directory = directory.join("source");
+        fs::create_dir_all(&source_directory).unwrap();
+
+        let obj = source_directory.join("no_normals.obj");
+        fs::copy("fixtures/no_normals.obj", &obj).unwrap();
+
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("", &source_directory);
+        let context = LoadContext {
+            vfs,
+            ..Default::default()
+        };
+        let source = "no_normals.obj";
+
+        write(
+            &directory,
+            &context,
+            source,
+            &obj_importer::read(source, &context.vfs).unwrap(),
+        )
+        .unwrap();
+
+        let blob_path = blob_path(&directory, &context, source).unwrap();
+        let blob = fs::read(&blob_path).unwrap();
+
+        // Touching the file hashes it again, and stores its new time once it turns out unchanged
+        let touched = SystemTime::now() + std::time::Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&obj)
+            .unwrap()
+            .set_modified(touched)
+            .unwrap();
+
+        assert!(read(&directory, &context, source).unwrap().is_some());
+        assert_ne!(fs::read(&blob_path).unwrap(), blob);
+
+        // Contents of the same size still miss the cache
+        let contents = fs::read_to_string(&obj).unwrap().replacen("v 1", "v 2", 1);
+        fs::write(&obj, contents).unwrap();
+
+        assert!(read(&directory, &context, source).unwrap().is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_out_of_range_blob() {
+        let directory = cache_directory("out_of_range_blob");
+        let context = LoadContext::default();
+        let source = "fixtures/nested_nodes.gltf";
+
+        let mut data = gltf_importer::read(source, &context.vfs).unwrap();
+        data.submeshes[0].vertex_count = data.vertices.len() as u32 + 1;
+        write(&directory, &context, source, &data).unwrap();
+
+        assert!(read(&directory, &context, source).is_err());
+
+        // A submesh in range whose indices point past its vertices
+        let mut data = gltf_importer::read(source, &context.vfs).unwrap();
+        data.submeshes[0].vertex_count = 1;
+        write(&directory, &context, source, &data).unwrap();
+
+        assert!(read(&directory, &context, source).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_vertices_without_attributes() {
+        let mut writer = BlobWriter::default();
+        writer.u32(u32::MAX);
+
+        let mut reader = BlobReader {
+            bytes: &writer.bytes,
+        };
+
+        assert!(reader.vertices(VertexLayout::from_bits(0)).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_blob() {
+        let directory = cache_directory("corrupt_blob");
+        let context = LoadContext::default();
+        let source = "fixtures/nested_nodes.gltf";
+
+        write(
+            &directory,
+            &context,
+            source,
+            &gltf_importer::read(source, &context.vfs).unwrap(),
+        )
+        .unwrap();
+
+        let path = blob_path(&directory, &context, source).unwrap();
+        let blob = fs::read(&path).unwrap();
+        fs::write(&path, &blob[..blob.len() / 2]).unwrap();
+
+        assert!(read(&directory, &context, source).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,455 @@
+use super::{
+    normals, tangents, Aabb, Bounds, Indices, MeshData, MeshMaterial, MorphTarget, Submesh, Vertex,
+    VertexAttribute, VertexLayout,
+};
+use log::warn;
+
+use crate::{
+    gltf_file::{self, GltfFile},
+    material, skeleton, texture,
+    vfs::Vfs,
+    AssetError,
+};
+
+/// The primitives read so far, gathered while walking the node tree
+#[derive(Default)]
+struct Primitives {
+    vertices: Vec<Vertex>,
+    /// Every attribute any of the primitives has
+    vertex_layout: VertexLayout,
+    indices: Vec<u32>,
+    submeshes: Vec<Submesh>,
+    /// A primitive mode that was skipped, reported if nothing could be read at all
+    skipped_mode: Option<gltf::mesh::Mode>,
+    /// The skin of the first skinned node, a mesh is drawn with a single skeleton
+    skin: Option<usize>,
+    /// Other skins of skinned nodes, whose vertices end up bound to the wrong joints
+    ignored_skins: Vec<usize>,
+    morph_targets: Vec<MorphTarget>,
+    /// The default weights of every morphed node, one after the other
+    morph_weights: Vec<f32>,
+}
+
+/// The array of a glTF file single meshes are read from, see `gltf_file::fragment_id`
+const KIND: &str = "meshes";
+
+/// Id of a single mesh of the glTF file at `path`, read without the transforms of its nodes
+pub(crate) fn mesh_id(path: &str, index: usize) -> String {
+    gltf_file::fragment_id(path, KIND, index)
+}
+
+pub(crate) fn split_mesh_id(id: &str) -> Option<(&str, &str)> {
+    gltf_file::split_fragment_id(id, KIND)
+}
+
+/// Read every triangle primitive of the default scene of a glTF file
+pub fn read(path: &str, vfs: &Vfs) -> Result<MeshData, AssetError> {
+    // Images are left for `Texture` to decode, only the buffers are needed here
+    read_document(path, &GltfFile::open(path, vfs)?)
+}
+
+fn read_document(path: &str, gltf: &GltfFile) -> Result<MeshData, AssetError> {
+    let document = &gltf.document;
+
+    let mut primitives = Primitives::default();
+
+    let scene = match document.default_scene() {
+        Some(scene) => Some(scene),
+        None => document.scenes().next(),
+    };
+
+    if let Some(scene) = scene {
+        for node in scene.nodes() {
+            read_node(
+                &node,
+                &glm::Mat4::identity(),
+                &gltf.buffers,
+                &mut primitives,
+            )?;
+        }
+    }
+
+    mesh_data(path, gltf, primitives)
+}
+
+/// Read a single mesh of a glTF file by index or name, without the transform of any node using it
+pub fn read_mesh(path: &str, name: &str, vfs: &Vfs) -> Result<MeshData, AssetError> {
+    let gltf = GltfFile::open(path, vfs)?;
+    let document = &gltf.document;
+
+    let mesh = match name.parse::<usize>() {
+        Ok(index) => document.meshes().nth(index),
+        Err(_) => document.meshes().find(|mesh| mesh.name() == Some(name)),
+    };
+
+    let mesh = match mesh {
+        Some(mesh) => mesh,
+        None => return Err(AssetError::Parse(format!("{} has no mesh {}", path, name))),
+    };
+
+    // Morph target weights are animated through a node, the first one using the mesh stands for all
+    let node = document.nodes().find(|node| {
+        node.mesh()
+            .is_some_and(|node_mesh| node_mesh.index() == mesh.index())
+    });
+
+    let mut primitives = Primitives {
+        skin: node
+            .as_ref()
+            .and_then(|node| node.skin())
+            .map(|skin| skin.index()),
+        ..Default::default()
+    };
+
+    read_primitives(
+        &mesh,
+        node.as_ref(),
+        &glm::Mat4::identity(),
+        &gltf.buffers,
+        &mut primitives,
+    )?;
+
+    mesh_data(path, &gltf, primitives)
+}
+
+fn mesh_data(path: &str, gltf: &GltfFile, primitives: Primitives) -> Result<MeshData, AssetError> {
+    let document = &gltf.document;
+
+    if let (true, Some(mode)) = (primitives.submeshes.is_empty(), primitives.skipped_mode) {
+        return Err(AssetError::UnsupportedPrimitiveMode(format!("{:?}", mode)));
+    }
+
+    if !primitives.ignored_skins.is_empty() {
+        warn!(
+            "{} has meshes skinned by skins {:?} as well as skin {}, only the first one is used. \
+             Spawn its scene to draw each mesh with its own skeleton",
+            path,
+            primitives.ignored_skins,
+            primitives.skin.unwrap_or_default()
+        );
+    }
+
+    let materials = document
+        .materials()
+        .map(|material| read_material(path, &material))
+        .collect();
+
+    Ok(MeshData {
+        bounds: Bounds::from_submeshes(&primitives.submeshes, &primitives.vertices),
+        vertices: primitives.vertices,
+        vertex_layout: primitives.vertex_layout,
+        indices: Indices::from(primitives.indices),
+        submeshes: primitives.submeshes,
+        materials,
+        dependencies: gltf.buffer_files(path),
+        lods: vec![],
+        skeleton: primitives
+            .skin
+            .map(|skin| skeleton::skeleton_id(path, skin)),
+        morph_targets: primitives.morph_targets,
+        morph_weights: primitives.morph_weights,
+        optimized: false,
+    })
+}
+
+fn read_node(
+    node: &gltf::Node,
+    parent_transform: &glm::Mat4,
+    buffers: &[gltf::buffer::Data],
+    primitives: &mut Primitives,
+) -> Result<(), AssetError> {
+    let transform = parent_transform * glm::Mat4::from(node.transform().matrix());
+
+    // The joints place skinned vertices, so the transform of their own node is ignored
+    let mesh_transform = match node.skin() {
+        Some(skin) => {
+            match primitives.skin {
+                None => primitives.skin = Some(skin.index()),
+                Some(first) if first != skin.index() => primitives.ignored_skins.push(skin.index()),
+                Some(_) => (),
+            }
+
+            glm::Mat4::identity()
+        }
+        None => transform,
+    };
+
+    // Cameras, lights and empty parents have no mesh but may still have children
+    if let Some(mesh) = node.mesh() {
+        read_primitives(&mesh, Some(node), &mesh_transform, buffers, primitives)?;
+    }
+
+    for child in node.children() {
+        read_node(&child, &transform, buffers, primitives)?;
+    }
+
+    Ok(())
+}
+
+/// Read the triangle primitives of a mesh as submeshes, `node` being the node the mesh is drawn by
+fn read_primitives(
+    mesh: &gltf::Mesh,
+    node: Option<&gltf::Node>,
+    transform: &glm::Mat4,
+    buffers: &[gltf::buffer::Data],
+    primitives: &mut Primitives,
+) -> Result<(), AssetError> {
+    // Nodes can override the weights of their mesh, which default to 0 for every target
+    let target_count = mesh
+        .primitives()
+        .map(|primitive| primitive.morph_targets().len())
+        .max()
+        .unwrap_or(0);
+    let first_weight = primitives.morph_weights.len();
+
+    let mut weights = node
+        .and_then(|node| node.weights())
+        .or(mesh.weights())
+        .unwrap_or(&[])
+        .to_vec();
+    weights.resize(target_count, 0.0);
+    primitives.morph_weights.extend(weights);
+
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            primitives.skipped_mode = Some(primitive.mode());
+            continue;
+        }
+
+        let (vertices, indices) =
+            get_triangular_primitive_vertices(&primitive, buffers, transform)?;
+
+        // Prefer the extents the file gives for its positions, computing them only when missing
+        let aabb = match position_extents(&primitive) {
+            Some(aabb) => aabb.transformed(transform),
+            None => Aabb::from_vertices(&vertices),
+        };
+
+        for (target, (position_deltas, normal_deltas)) in
+            read_morph_targets(&primitive, buffers, transform)
+                .into_iter()
+                .enumerate()
+        {
+            primitives.morph_targets.push(MorphTarget {
+                submesh: primitives.submeshes.len(),
+                node: node.map_or(0, |node| node.index()),
+                weight: first_weight + target,
+                position_deltas,
+                normal_deltas,
+            });
+        }
+
+        primitives.submeshes.push(Submesh {
+            first_index: primitives.indices.len() as u32,
+            index_count: indices.len() as u32,
+            first_vertex: primitives.vertices.len() as u32,
+            vertex_count: vertices.len() as u32,
+            material_index: primitive.material().index(),
+            bounds: Bounds::new(aabb, &vertices),
+        });
+        primitives.vertices.extend(vertices);
+        primitives.vertex_layout = primitives.vertex_layout.union(vertex_layout(&primitive));
+        primitives.indices.extend(indices);
+    }
+
+    Ok(())
+}
+
+/// The attributes the primitive has, along with those that are generated for every primitive
+fn vertex_layout(primitive: &gltf::Primitive) -> VertexLayout {
+    let semantics = [
+        (gltf::Semantic::TexCoords(0), VertexAttribute::TexCoord0),
+        (gltf::Semantic::TexCoords(1), VertexAttribute::TexCoord1),
+        (gltf::Semantic::Colors(0), VertexAttribute::Color),
+        (gltf::Semantic::Joints(0), VertexAttribute::Joints),
+        (gltf::Semantic::Weights(0), VertexAttribute::Weights),
+    ];
+
+    semantics
+        .into_iter()
+        .filter(|(semantic, _)| primitive.get(semantic).is_some())
+        .fold(VertexLayout::default(), |layout, (_, attribute)| {
+            layout.with(attribute)
+        })
+}
+
+/// The min and max of the primitive's position accessor, which glTF requires but not every exporter writes
+fn position_extents(primitive: &gltf::Primitive) -> Option<Aabb> {
+    let accessor = primitive.get(&gltf::Semantic::Positions)?;
+
+    let read = |value: Option<gltf::json::Value>| -> Option<glm::Vec3> {
+        let values = value?;
+        let values = values.as_array()?;
+
+        match values[..] {
+            [ref x, ref y, ref z] => Some(glm::vec3(
+                x.as_f64()? as f32,
+                y.as_f64()? as f32,
+                z.as_f64()? as f32,
+            )),
+            _ => None,
+        }
+    };
+
+    Some(Aabb {
+        min: read(accessor.min())?,
+        max: read(accessor.max())?,
+    })
+}
+
+fn read_material(path: &str, material: &gltf::Material) -> MeshMaterial {
+    let pbr = material.pbr_metallic_roughness();
+
+    let base_color_texture = pbr
+        .base_color_texture()
+        .map(|info| texture::gltf_texture_id(path, &info.texture()));
+
+    MeshMaterial {
+        name: material.name().map(str::to_owned),
+        // The default material has no index and no asset of its own
+        id: material
+            .index()
+            .map(|index| material::material_id(path, index)),
+        base_color: glm::Vec4::from(pbr.base_color_factor()),
+        base_color_texture,
+    }
+}
+
+/// The position and normal deltas of every morph target of the primitive, transformed like its vertices
+fn read_morph_targets(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    transform: &glm::Mat4,
+) -> Vec<(Vec<glm::Vec3>, Vec<glm::Vec3>)> {
+    let position_transform = glm::mat4_to_mat3(transform);
+    let normal_transform = glm::inverse_transpose(position_transform);
+
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    // Vertex normals are normalized after the transform, their deltas are scaled to match
+    let normal_scales: Vec<f32> = match reader.read_normals() {
+        Some(normals) => normals
+            .map(|normal| (normal_transform * glm::Vec3::from(normal)).norm())
+            .collect(),
+        None => vec![],
+    };
+
+    reader
+        .read_morph_targets()
+        .map(|(positions, normals, _)| {
+            let position_deltas = positions
+                .into_iter()
+                .flatten()
+                .map(|delta| position_transform * glm::Vec3::from(delta))
+                .collect();
+
+            let normal_deltas = normals
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(|(vertex, delta)| {
+                    let scale = normal_scales
+                        .get(vertex)
+                        .copied()
+                        .filter(|scale| *scale > 0.0)
+                        .unwrap_or(1.0);
+
+                    normal_transform * glm::Vec3::from(delta) / scale
+                })
+                .collect();
+
+            (position_deltas, normal_deltas)
+        })
+        .collect()
+}
+
+fn get_triangular_primitive_vertices(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    transform: &glm::Mat4,
+) -> Result<(Vec<Vertex>, Vec<u32>), AssetError> {
+    let normal_transform = glm::inverse_transpose(glm::mat4_to_mat3(transform));
+    let tangent_transform = glm::mat4_to_mat3(transform);
+
+    // A mirroring transform turns triangles inside out, reversing their winding turns them back
+    let mirrored = glm::determinant(&tangent_transform) < 0.0;
+
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let mut vertices: Vec<Vertex> = match reader.read_positions() {
+        Some(positions) => positions
+            .map(|position| Vertex {
+                position: (transform * glm::vec4(position[0], position[1], position[2], 1.0)).xyz(),
+                ..Default::default()
+            })
+            .collect(),
+        None => return Err(AssetError::MissingAttribute("POSITION".to_owned())),
+    };
+
+    if let Some(tex_coords) = reader.read_tex_coords(0) {
+        for (vertex, uv) in vertices.iter_mut().zip(tex_coords.into_f32()) {
+            vertex.uv0 = glm::Vec2::from(uv);
+        }
+    }
+    if let Some(tex_coords) = reader.read_tex_coords(1) {
+        for (vertex, uv) in vertices.iter_mut().zip(tex_coords.into_f32()) {
+            vertex.uv1 = glm::Vec2::from(uv);
+        }
+    }
+    if let Some(colors) = reader.read_colors(0) {
+        for (vertex, color) in vertices.iter_mut().zip(colors.into_rgba_f32()) {
+            vertex.color = glm::Vec4::from(color);
+        }
+    }
+
+    if let Some(joints) = reader.read_joints(0) {
+        for (vertex, joints) in vertices.iter_mut().zip(joints.into_u16()) {
+            vertex.joints = glm::Vec4::from(joints.map(f32::from));
+        }
+    }
+    if let Some(weights) = reader.read_weights(0) {
+        for (vertex, weights) in vertices.iter_mut().zip(weights.into_f32()) {
+            vertex.weights = glm::Vec4::from(weights);
+        }
+    }
+
+    // Primitives without indices draw their vertices in order
+    let mut indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+
+    if mirrored {
+        for triangle in indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    match reader.read_normals() {
+        Some(normals) => {
+            for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                vertex.normal = (normal_transform * glm::Vec3::from(normal)).normalize();
+            }
+        }
+        None => normals::generate_normals(&mut vertices, &indices),
+    }
+
+    match reader.read_tangents() {
+        Some(tangents) => {
+            for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                let direction = tangent_transform * glm::vec3(tangent[0], tangent[1], tangent[2]);
+
+                // The bitangent is mirrored as well, which flips the handedness
+                let handedness = match mirrored {
+                    true => -tangent[3],
+                    false => tangent[3],
+                };
+
+                vertex.tangent = direction.normalize().push(handedness);
+            }
+        }
+        None => tangents::generate_tangents(&mut vertices, &indices),
+    }
+
+    Ok((vertices, indices))
+}
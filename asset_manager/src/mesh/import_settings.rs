@@ -0,0 +1,164 @@
+use log::warn;
+
+use super::{normals, tangents, Bounds, MeshData};
+use crate::meta::{MeshMeta, UpAxis};
+
+/// Apply the settings of the mesh's meta file to it, before it is optimized
+///
+/// Skinned meshes are placed by their skeleton's joints, which the scale and axis are not applied to
+pub fn apply(data: &mut MeshData, settings: &MeshMeta) {
+    if settings.regenerate_normals {
+        regenerate_normals(data);
+    }
+
+    let rotation = match settings.up_axis {
+        UpAxis::Y => glm::Mat4::identity(),
+        UpAxis::Z => glm::rotation(-std::f32::consts::FRAC_PI_2, &glm::Vec3::x()),
+    };
+
+    if settings.scale == 1.0 && settings.up_axis == UpAxis::Y {
+        return;
+    }
+
+    if let Some(skeleton) = &data.skeleton {
+        warn!(
+            "Not scaling or rotating a mesh skinned by {}, scale the entity instead",
+            skeleton
+        );
+
+        return;
+    }
+
+    let transform =
+        glm::scaling(&glm::vec3(settings.scale, settings.scale, settings.scale)) * rotation;
+    let position_transform = glm::mat4_to_mat3(&transform);
+    let direction_transform = glm::mat4_to_mat3(&rotation);
+
+    // A mirroring scale turns the triangles inside out, the normals and tangents follow the
+    // surface and the winding is reversed so the triangles still face them
+    let normal_sign = settings.scale.signum();
+
+    if settings.scale < 0.0 {
+        data.indices.reverse_winding();
+    }
+
+    for vertex in &mut data.vertices {
+        vertex.position = position_transform * vertex.position;
+        vertex.normal = direction_transform * vertex.normal * normal_sign;
+
+        let tangent = direction_transform * vertex.tangent.xyz() * normal_sign;
+        vertex.tangent = tangent.push(vertex.tangent.w * normal_sign);
+    }
+
+    for target in &mut data.morph_targets {
+        for delta in &mut target.position_deltas {
+            *delta = position_transform * *delta;
+        }
+        for delta in &mut target.normal_deltas {
+            *delta = direction_transform * *delta * normal_sign;
+        }
+    }
+
+    let transform_bounds = |bounds: &mut Bounds| {
+        bounds.aabb = bounds.aabb.transformed(&transform);
+        bounds.sphere.center = position_transform * bounds.sphere.center;
+        bounds.sphere.radius *= settings.scale.abs();
+    };
+
+    for submesh in &mut data.submeshes {
+        transform_bounds(&mut submesh.bounds);
+    }
+    transform_bounds(&mut data.bounds);
+}
+
+/// Replace the normals and tangents of every submesh with ones generated from its triangles
+fn regenerate_normals(data: &mut MeshData) {
+    let indices: Vec<u32> = data.indices.iter().collect();
+
+    for submesh in &data.submeshes {
+        let first_vertex = submesh.first_vertex as usize;
+        let first_index = submesh.first_index as usize;
+
+        let vertices =
+            &mut data.vertices[first_vertex..first_vertex + submesh.vertex_count as usize];
+        let indices = &indices[first_index..first_index + submesh.index_count as usize];
+
+        normals::generate_normals(vertices, indices);
+        tangents::generate_tangents(vertices, indices);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loader::LoadContext, mesh::Mesh, LoadableAsset};
+
+    #[test]
+    fn test_z_up_and_scale() {
+        // The fixture's meta file scales it by 2 and converts it from Z up
+        let data = Mesh::read("fixtures/z_up.obj", &LoadContext::default()).unwrap();
+
+        assert_eq!(data.vertices[1].position, glm::vec3(2.0, 0.0, 0.0));
+        assert!((data.vertices[2].position - glm::vec3(0.0, 2.0, 0.0)).norm() < 1e-6);
+        assert!((data.vertices[0].normal - glm::vec3(0.0, 0.0, 1.0)).norm() < 1e-6);
+        assert!((data.bounds.aabb.max - glm::vec3(2.0, 2.0, 0.0)).norm() < 1e-6);
+        assert!((data.bounds.sphere.radius - 2.0f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mirroring_scale() {
+        let mut data = Mesh::read("fixtures/two_materials.obj", &LoadContext::default()).unwrap();
+        let indices: Vec<u32> = data.indices.iter().collect();
+
+        apply(
+            &mut data,
+            &MeshMeta {
+                scale: -1.0,
+                ..Default::default()
+            },
+        );
+
+        // Each triangle is wound the other way and still faces its flipped normal
+        let mirrored: Vec<u32> = data.indices.iter().collect();
+        assert_eq!(mirrored[..3], [indices[0], indices[2], indices[1]]);
+        assert_eq!(data.vertices[0].normal, glm::vec3(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_skinned_mesh_unchanged() {
+        let mut data = Mesh::read("fixtures/skinned.gltf", &LoadContext::default()).unwrap();
+        let vertices = data.vertices.clone();
+
+        apply(
+            &mut data,
+            &MeshMeta {
+                scale: 2.0,
+                up_axis: UpAxis::Z,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(data.vertices, vertices);
+    }
+
+    #[test]
+    fn test_regenerate_normals() {
+        let mut data = Mesh::read("fixtures/two_materials.obj", &LoadContext::default()).unwrap();
+
+        for vertex in &mut data.vertices {
+            vertex.normal = glm::vec3(1.0, 0.0, 0.0);
+        }
+
+        apply(
+            &mut data,
+            &MeshMeta {
+                regenerate_normals: true,
+                ..Default::default()
+            },
+        );
+
+        for vertex in &data.vertices {
+            assert_eq!(vertex.normal, glm::vec3(0.0, 0.0, 1.0));
+        }
+    }
+}
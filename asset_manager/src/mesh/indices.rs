@@ -0,0 +1,86 @@
+/// The index buffer of a mesh, stored as 16 bit indices whenever they all fit
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            Indices::U16(indices) => Box::new(indices.iter().map(|index| *index as u32)),
+            Indices::U32(indices) => Box::new(indices.iter().copied()),
+        }
+    }
+
+    /// Swap the last two indices of every triangle, turning them to face the other way
+    pub fn reverse_winding(&mut self) {
+        match self {
+            Indices::U16(indices) => indices
+                .chunks_exact_mut(3)
+                .for_each(|triangle| triangle.swap(1, 2)),
+            Indices::U32(indices) => indices
+                .chunks_exact_mut(3)
+                .for_each(|triangle| triangle.swap(1, 2)),
+        }
+    }
+
+    /// Free the indices, the index width is kept so an uploaded index buffer can still be bound
+    pub fn clear(&mut self) {
+        match self {
+            Indices::U16(indices) => *indices = vec![],
+            Indices::U32(indices) => *indices = vec![],
+        }
+    }
+}
+
+impl Default for Indices {
+    fn default() -> Self {
+        Indices::U16(vec![])
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|index| *index <= u16::MAX as u32) {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_u32_compacts() {
+        assert_eq!(
+            Indices::from(vec![0, 1, 65535]),
+            Indices::U16(vec![0, 1, 65535])
+        );
+        assert_eq!(Indices::from(vec![0, 65536]), Indices::U32(vec![0, 65536]));
+    }
+
+    #[test]
+    fn test_clear_keeps_width() {
+        let mut indices = Indices::from(vec![0, 70000]);
+
+        indices.clear();
+
+        assert!(indices.is_empty());
+        assert_eq!(indices, Indices::U32(vec![]));
+    }
+}
@@ -0,0 +1,668 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use serde_derive::Deserialize;
+
+use super::{optimize, Aabb, Indices, MeshData, Submesh, Vertex, VertexAttribute};
+
+/// How many simplified levels are generated for every mesh and how far they may stray from it
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LodSettings {
+    /// Number of levels generated after the full detail mesh, none by default
+    pub levels: u32,
+    /// Fraction of the previous level's triangles each level aims to keep
+    pub reduction: f32,
+    /// The largest error a level may have, relative to the size of the submesh it simplifies
+    pub max_error: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        LodSettings {
+            levels: 0,
+            reduction: 0.5,
+            max_error: 0.05,
+        }
+    }
+}
+
+/// A simplified version of a mesh, drawing from the same vertices with indices of its own
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshLod {
+    /// The largest distance between the simplified and the full detail surface, in mesh units
+    pub error: f32,
+    /// The level's triangles of every submesh of the mesh, in the same order
+    pub submeshes: Vec<Submesh>,
+}
+
+impl MeshLod {
+    /// The error in pixels when drawn at a distance from the camera,
+    /// `projection_scale` being the viewport height over `2 * tan(fov_y / 2)`
+    pub fn screen_space_error(&self, distance: f32, projection_scale: f32) -> f32 {
+        self.error * projection_scale / distance.max(f32::EPSILON)
+    }
+}
+
+/// Append the simplified levels of every submesh to the mesh's indices
+pub fn generate_lods(data: &mut MeshData, settings: &LodSettings, optimize_vertex_cache: bool) {
+    data.lods = vec![];
+
+    if settings.levels == 0 || data.submeshes.is_empty() {
+        return;
+    }
+
+    let mut indices: Vec<u32> = data.indices.iter().collect();
+
+    // Every submesh is simplified on its own, so each gets one chain of levels
+    let chains: Vec<Vec<(Vec<u32>, f32)>> = data
+        .submeshes
+        .iter()
+        .map(|submesh| {
+            let first_vertex = submesh.first_vertex as usize;
+            let first_index = submesh.first_index as usize;
+
+            simplify_chain(
+                &data.vertices[first_vertex..first_vertex + submesh.vertex_count as usize],
+                &indices[first_index..first_index + submesh.index_count as usize],
+                settings,
+            )
+        })
+        .collect();
+
+    let mut previous_index_count: usize = data
+        .submeshes
+        .iter()
+        .map(|submesh| submesh.index_count as usize)
+        .sum();
+
+    for level in 0..settings.levels as usize {
+        let index_count: usize = chains.iter().map(|chain| chain[level].0.len()).sum();
+
+        // Simplification stopped at the error threshold, later levels would be the same
+        if index_count >= previous_index_count {
+            break;
+        }
+        previous_index_count = index_count;
+
+        let mut lod = MeshLod {
+            error: 0.0,
+            submeshes: vec![],
+        };
+
+        for (submesh, chain) in data.submeshes.iter().zip(&chains) {
+            let (level_indices, error) = &chain[level];
+
+            let level_indices = match optimize_vertex_cache {
+                true => {
+                    optimize::optimize_vertex_cache(level_indices, submesh.vertex_count as usize)
+                }
+                false => level_indices.clone(),
+            };
+
+            lod.error = lod.error.max(*error);
+            lod.submeshes.push(Submesh {
+                first_index: indices.len() as u32,
+                index_count: level_indices.len() as u32,
+                ..*submesh
+            });
+            indices.extend(level_indices);
+        }
+
+        data.lods.push(lod);
+    }
+
+    data.indices = Indices::from(indices);
+}
+
+/// Simplify the triangles step by step, keeping the indices and error of every level on the way
+///
+/// Once the error threshold is reached the remaining levels repeat the last one
+fn simplify_chain(
+    vertices: &[Vertex],
+    indices: &[u32],
+    settings: &LodSettings,
+) -> Vec<(Vec<u32>, f32)> {
+    let mut simplifier = Simplifier::new(vertices, indices);
+
+    let bounds = Aabb::from_vertices(vertices);
+    let max_error = settings.max_error * glm::distance(&bounds.min, &bounds.max);
+
+    let mut target = simplifier.triangle_count as f32;
+    let mut chain = vec![];
+
+    for _ in 0..settings.levels {
+        target *= settings.reduction;
+
+        let error = simplifier.simplify(target as usize, max_error);
+
+        chain.push((simplifier.indices(), error));
+    }
+
+    chain
+}
+
+/// The squared distance to a set of planes, summed, as a symmetric 4x4 matrix
+#[derive(Clone, Copy, Default)]
+struct Quadric {
+    // xx, xy, xz, xw, yy, yz, yw, zz, zw, ww
+    values: [f64; 10],
+}
+
+impl Quadric {
+    fn from_plane(normal: &glm::DVec3, distance: f64) -> Quadric {
+        let (a, b, c, d) = (normal.x, normal.y, normal.z, distance);
+
+        Quadric {
+            values: [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ],
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.values.iter_mut().zip(other.values) {
+            *value += other;
+        }
+    }
+
+    fn error(&self, position: &glm::DVec3) -> f64 {
+        let [xx, xy, xz, xw, yy, yz, yw, zz, zw, ww] = self.values;
+        let (x, y, z) = (position.x, position.y, position.z);
+
+        let error = x * x * xx
+            + 2.0 * x * y * xy
+            + 2.0 * x * z * xz
+            + 2.0 * x * xw
+            + y * y * yy
+            + 2.0 * y * z * yz
+            + 2.0 * y * yw
+            + z * z * zz
+            + 2.0 * z * zw
+            + ww;
+
+        // Rounding can take a perfect fit slightly below zero
+        error.max(0.0)
+    }
+}
+
+/// Collapsing the `from` position into the `to` position, cheapest first
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+/// Quadric error edge collapse simplification, after Garland and Heckbert's
+/// "Surface Simplification Using Quadric Error Metrics"
+///
+/// Edges are collapsed between positions rather than vertices, so vertices split along an
+/// attribute seam move together. Each corner then takes the vertex of the new position it shares
+/// a triangle with, or else the one with the closest attributes, keeping the levels drawing
+/// from the mesh's own vertices. Positions on open borders never move so outlines stay in place
+struct Simplifier {
+    vertices: Vec<Vertex>,
+    /// Vertices sharing a position share an id, and everything below is kept by id
+    position_ids: Vec<usize>,
+    positions: Vec<glm::DVec3>,
+    position_vertices: Vec<Vec<u32>>,
+    position_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    collapsed: Vec<bool>,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    triangle_count: usize,
+    collapses: BinaryHeap<Collapse>,
+    error: f32,
+}
+
+impl Simplifier {
+    fn new(vertices: &[Vertex], indices: &[u32]) -> Simplifier {
+        let mut position_lookup = HashMap::new();
+        let mut positions: Vec<glm::DVec3> = vec![];
+
+        let position_ids: Vec<usize> = vertices
+            .iter()
+            .map(|vertex| {
+                *position_lookup
+                    .entry(vertex.position.map(f32::to_bits))
+                    .or_insert_with(|| {
+                        positions.push(glm::convert(vertex.position));
+                        positions.len() - 1
+                    })
+            })
+            .collect();
+
+        let triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut position_vertices = vec![vec![]; positions.len()];
+        let mut position_triangles = vec![vec![]; positions.len()];
+        let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+
+        for (triangle, corners) in triangles.iter().enumerate() {
+            let ids = corners.map(|index| position_ids[index as usize]);
+
+            for (corner, index) in corners.iter().enumerate() {
+                let (id, next_id) = (ids[corner], ids[(corner + 1) % 3]);
+
+                if !position_vertices[id].contains(index) {
+                    position_vertices[id].push(*index);
+                }
+                position_triangles[id].push(triangle);
+
+                *edges.entry((id.min(next_id), id.max(next_id))).or_default() += 1;
+            }
+
+            let [a, b, c] = ids.map(|id| positions[id]);
+            let normal = (b - a).cross(&(c - a));
+
+            if normal.norm() > 0.0 {
+                let normal = normal.normalize();
+                let plane = Quadric::from_plane(&normal, -normal.dot(&a));
+
+                for id in ids {
+                    quadrics[id].add(&plane);
+                }
+            }
+        }
+
+        // An edge not shared by exactly two triangles lies on a border, or where the surface branches
+        let mut locked = vec![false; positions.len()];
+        for ((a, b), count) in edges {
+            if count != 2 {
+                locked[a] = true;
+                locked[b] = true;
+            }
+        }
+
+        let mut simplifier = Simplifier {
+            vertices: vertices.to_vec(),
+            position_ids,
+            collapsed: vec![false; positions.len()],
+            positions,
+            position_vertices,
+            position_triangles,
+            quadrics,
+            locked,
+            triangle_count: triangles.len(),
+            alive: vec![true; triangles.len()],
+            triangles,
+            collapses: BinaryHeap::new(),
+            error: 0.0,
+        };
+
+        for triangle in 0..simplifier.triangles.len() {
+            simplifier.queue_collapses(triangle);
+        }
+
+        simplifier
+    }
+
+    fn triangle_ids(&self, triangle: usize) -> [usize; 3] {
+        self.triangles[triangle].map(|index| self.position_ids[index as usize])
+    }
+
+    fn cost(&self, from: usize, to: usize) -> f64 {
+        let mut quadric = self.quadrics[from];
+        quadric.add(&self.quadrics[to]);
+
+        quadric.error(&self.positions[to])
+    }
+
+    /// Queue collapsing each position of the triangle into the others
+    fn queue_collapses(&mut self, triangle: usize) {
+        let ids = self.triangle_ids(triangle);
+
+        for from in ids {
+            if self.locked[from] {
+                continue;
+            }
+
+            for to in ids {
+                if to != from {
+                    self.collapses.push(Collapse {
+                        cost: self.cost(from, to),
+                        from,
+                        to,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Collapse edges until at most `target` triangles remain or the next collapse would cost more
+    /// than `max_error`, returning the largest error of any collapse so far
+    fn simplify(&mut self, target: usize, max_error: f32) -> f32 {
+        while self.triangle_count > target {
+            let collapse = match self.collapses.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+
+            // Every queued collapse costs at least as much as it did when it was queued
+            if collapse.cost.sqrt() as f32 > max_error {
+                self.collapses.push(collapse);
+                break;
+            }
+
+            let (from, to) = (collapse.from, collapse.to);
+
+            if self.collapsed[from] || self.collapsed[to] {
+                continue;
+            }
+
+            // Quadrics only grow, so a stale entry is queued again at its current cost
+            let cost = self.cost(from, to);
+            if cost > collapse.cost {
+                self.collapses.push(Collapse { cost, from, to });
+                continue;
+            }
+
+            if !self.can_collapse(from, to) {
+                continue;
+            }
+
+            self.collapse(from, to);
+            self.error = self.error.max(cost.sqrt() as f32);
+        }
+
+        self.error
+    }
+
+    fn alive_triangles(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.position_triangles[id]
+            .iter()
+            .copied()
+            .filter(|triangle| self.alive[*triangle])
+    }
+
+    /// Whether the positions still share a triangle and collapsing them flips none of the others
+    fn can_collapse(&self, from: usize, to: usize) -> bool {
+        let mut shares_triangle = false;
+
+        for triangle in self.alive_triangles(from) {
+            let ids = self.triangle_ids(triangle);
+
+            if ids.contains(&to) {
+                shares_triangle = true;
+                continue;
+            }
+
+            let [a, b, c] = ids.map(|id| self.positions[id]);
+            let [moved_a, moved_b, moved_c] = ids.map(|id| match id == from {
+                true => self.positions[to],
+                false => self.positions[id],
+            });
+
+            let normal = (b - a).cross(&(c - a));
+            let moved_normal = (moved_b - moved_a).cross(&(moved_c - moved_a));
+
+            if normal.dot(&moved_normal) <= 0.0 {
+                return false;
+            }
+        }
+
+        shares_triangle
+    }
+
+    /// The vertex at the `to` position that replaces a vertex at the `from` position
+    fn replacement(&self, vertex: u32, to: usize) -> u32 {
+        let neighbour = self
+            .alive_triangles(self.position_ids[vertex as usize])
+            .map(|triangle| self.triangles[triangle])
+            .filter(|corners| corners.contains(&vertex))
+            .flatten()
+            .find(|index| self.position_ids[*index as usize] == to);
+
+        if let Some(neighbour) = neighbour {
+            return neighbour;
+        }
+
+        let attribute_distance = |other: u32| -> f32 {
+            let (vertex, other) = (
+                &self.vertices[vertex as usize],
+                &self.vertices[other as usize],
+            );
+
+            VertexAttribute::ALL
+                .iter()
+                .filter(|attribute| **attribute != VertexAttribute::Position)
+                .flat_map(|attribute| attribute.read(vertex).iter().zip(attribute.read(other)))
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum()
+        };
+
+        *self.position_vertices[to]
+            .iter()
+            .min_by(|a, b| attribute_distance(**a).total_cmp(&attribute_distance(**b)))
+            .unwrap()
+    }
+
+    fn collapse(&mut self, from: usize, to: usize) {
+        let from_quadric = self.quadrics[from];
+        self.quadrics[to].add(&from_quadric);
+
+        let replacements: HashMap<u32, u32> = self.position_vertices[from]
+            .iter()
+            .map(|vertex| (*vertex, self.replacement(*vertex, to)))
+            .collect();
+
+        self.collapsed[from] = true;
+
+        for triangle in std::mem::take(&mut self.position_triangles[from]) {
+            if !self.alive[triangle] {
+                continue;
+            }
+
+            for index in &mut self.triangles[triangle] {
+                if let Some(replacement) = replacements.get(index) {
+                    *index = *replacement;
+                }
+            }
+
+            let [a, b, c] = self.triangle_ids(triangle);
+
+            // The triangles along the collapsed edge are left without any area
+            if a == b || b == c || c == a {
+                self.alive[triangle] = false;
+                self.triangle_count -= 1;
+            } else {
+                self.position_triangles[to].push(triangle);
+            }
+        }
+
+        let triangles: Vec<usize> = self.alive_triangles(to).collect();
+        for triangle in triangles {
+            self.queue_collapses(triangle);
+        }
+    }
+
+    fn indices(&self) -> Vec<u32> {
+        self.triangles
+            .iter()
+            .zip(&self.alive)
+            .filter(|(_, alive)| **alive)
+            .flat_map(|(triangle, _)| *triangle)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{Bounds, VertexLayout};
+
+    /// A grid of quads, curved into a paraboloid when `height` is not zero
+    fn grid(size: u32, height: f32) -> MeshData {
+        let vertices: Vec<Vertex> = (0..=size)
+            .flat_map(|y| (0..=size).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x, y) = (x as f32, y as f32);
+
+                Vertex {
+                    position: glm::vec3(x, y, height * (x * x + y * y)),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let mut indices = vec![];
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+
+                indices.extend([corner, corner + 1, corner + size + 1]);
+                indices.extend([corner + 1, corner + size + 2, corner + size + 1]);
+            }
+        }
+
+        MeshData {
+            bounds: Bounds::from_vertices(&vertices),
+            submeshes: vec![Submesh {
+                first_index: 0,
+                index_count: indices.len() as u32,
+                first_vertex: 0,
+                vertex_count: vertices.len() as u32,
+                material_index: None,
+                bounds: Bounds::from_vertices(&vertices),
+            }],
+            vertices,
+            vertex_layout: VertexLayout::default(),
+            indices: Indices::from(indices),
+            materials: vec![],
+            dependencies: vec![],
+            lods: vec![],
+            skeleton: None,
+            morph_targets: vec![],
+            morph_weights: vec![],
+            optimized: false,
+        }
+    }
+
+    fn settings(levels: u32, max_error: f32) -> LodSettings {
+        LodSettings {
+            levels,
+            reduction: 0.5,
+            max_error,
+        }
+    }
+
+    #[test]
+    fn test_flat_grid_levels() {
+        let mut data = grid(16, 0.0);
+
+        generate_lods(&mut data, &settings(2, 0.01), false);
+
+        assert_eq!(data.lods.len(), 2);
+
+        let mut previous_count = 16 * 16 * 6;
+        for lod in &data.lods {
+            let submesh = lod.submeshes[0];
+
+            assert!(submesh.index_count < previous_count);
+            assert!(lod.error < 0.001, "error {}", lod.error);
+            previous_count = submesh.index_count;
+
+            // Levels are appended after the full detail indices
+            let indices: Vec<u32> = data
+                .indices
+                .iter()
+                .skip(submesh.first_index as usize)
+                .take(submesh.index_count as usize)
+                .collect();
+            assert!(indices
+                .iter()
+                .all(|index| *index < data.vertices.len() as u32));
+        }
+    }
+
+    #[test]
+    fn test_keeps_borders() {
+        let mut data = grid(8, 0.0);
+
+        generate_lods(&mut data, &settings(8, 0.01), false);
+
+        let coarsest = data.lods.last().unwrap().submeshes[0];
+        let indices: Vec<u32> = data
+            .indices
+            .iter()
+            .skip(coarsest.first_index as usize)
+            .take(coarsest.index_count as usize)
+            .collect();
+
+        // Every border vertex is still used, so the grid keeps its outline
+        for (index, vertex) in data.vertices.iter().enumerate() {
+            let border = [vertex.position.x, vertex.position.y]
+                .iter()
+                .any(|value| *value == 0.0 || *value == 8.0);
+
+            if border {
+                assert!(indices.contains(&(index as u32)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_threshold() {
+        let mut data = grid(8, 1.0);
+        let index_count = data.indices.len();
+
+        // The curve cannot be flattened without straying further than the threshold allows
+        generate_lods(&mut data, &settings(3, 0.0001), false);
+
+        assert!(data.lods.is_empty());
+        assert_eq!(data.indices.len(), index_count);
+
+        generate_lods(&mut data, &settings(3, 1.0), false);
+
+        assert!(!data.lods.is_empty());
+        assert!(data.lods.last().unwrap().error > 0.1);
+    }
+
+    #[test]
+    fn test_screen_space_error() {
+        let lod = MeshLod {
+            error: 0.5,
+            submeshes: vec![],
+        };
+
+        assert_eq!(lod.screen_space_error(10.0, 1000.0), 50.0);
+        assert_eq!(lod.screen_space_error(100.0, 1000.0), 5.0);
+    }
+}
@@ -0,0 +1,21 @@
+/// The material a submesh is drawn with, as described by the source file
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshMaterial {
+    pub name: Option<String>,
+    /// Id of the `PbrMaterial` with every parameter of the material, for materials of glTF files
+    pub id: Option<String>,
+    pub base_color: glm::Vec4,
+    /// Id of the base color texture, to be requested through `AssetManager::get_texture`
+    pub base_color_texture: Option<String>,
+}
+
+impl Default for MeshMaterial {
+    fn default() -> Self {
+        MeshMaterial {
+            name: None,
+            id: None,
+            base_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+        }
+    }
+}
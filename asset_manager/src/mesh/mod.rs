@@ -1,101 +1,492 @@
+mod bounds;
+mod cache;
+mod gltf_importer;
+mod import_settings;
+mod indices;
+mod lod;
+mod mesh_material;
+mod morph;
+mod normals;
+mod obj_importer;
+mod optimize;
+mod primitives;
+mod submesh;
+mod tangents;
 mod vertex;
 
-use rand::prelude::*;
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
-pub use vertex::Vertex;
+use log::warn;
+
+pub use bounds::{Aabb, BoundingSphere, Bounds};
+pub use indices::Indices;
+pub use lod::{LodSettings, MeshLod};
+pub use mesh_material::MeshMaterial;
+pub use morph::MorphTarget;
+pub use optimize::MeshOptimizations;
+pub use primitives::{is_builtin, BUILTIN_SCHEME};
+pub use submesh::Submesh;
+pub use vertex::{Vertex, VertexAttribute, VertexLayout};
+
+pub(crate) use gltf_importer::mesh_id;
 
 use gpu_info::Buffer;
 
-use crate::asset_info::{AssetInfo, AssetStatus};
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    loader::{LoadContext, LoadableAsset},
+    meta::AssetMeta,
+    AssetError, Dependency,
+};
 
 pub struct Mesh {
     pub asset_info: AssetInfo,
     pub gpu_info: Option<Buffer>,
+    pub index_gpu_info: Option<Buffer>,
     pub vertices: Vec<Vertex>,
     pub vertex_count: u32,
+    /// The attributes the vertices have, the only ones `vertex_data` packs
+    pub vertex_layout: VertexLayout,
+    pub indices: Indices,
+    /// Kept after the vertices are freed, like those of every submesh
+    pub bounds: Bounds,
+    /// Every primitive of the source file, kept after the vertices are freed so they can be drawn
+    pub submeshes: Vec<Submesh>,
+    /// The materials of the source file, indexed by `Submesh::material_index`
+    pub materials: Vec<MeshMaterial>,
+    /// Simplified levels drawing from the same buffers, from the most to the least detailed
+    pub lods: Vec<MeshLod>,
+    /// Id of the `Skeleton` the joints of the vertices refer to, for skinned meshes
+    pub skeleton: Option<String>,
+    /// Blend shapes of the submeshes, see `Mesh::blend_morph_targets`
+    pub morph_targets: Vec<MorphTarget>,
+    /// The weights the targets are blended by when nothing animates them
+    pub morph_weights: Vec<f32>,
+}
+
+/// The vertices and indices of every submesh, with node transforms already applied
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    /// The attributes the source gave the vertices, the others hold their default value
+    pub vertex_layout: VertexLayout,
+    pub indices: Indices,
+    pub bounds: Bounds,
+    pub submeshes: Vec<Submesh>,
+    pub materials: Vec<MeshMaterial>,
+    /// Files other than the source file the mesh was read from, such as glTF buffers and MTL files
+    pub dependencies: Vec<PathBuf>,
+    /// Simplified levels, their indices appended after those of the full detail submeshes
+    pub lods: Vec<MeshLod>,
+    pub skeleton: Option<String>,
+    pub morph_targets: Vec<MorphTarget>,
+    pub morph_weights: Vec<f32>,
+    /// Whether any `MeshOptimizations` pass ran on the mesh
+    pub optimized: bool,
 }
 
 impl Mesh {
     pub fn load(&mut self) {
-        self.asset_info.status = crate::asset_info::AssetStatus::Loaded;
+        let data = Mesh::read(&self.asset_info.id, &LoadContext::default());
 
-        let import = gltf::import(&self.asset_info.id);
+        self.publish(data);
+    }
 
-        if import.is_err() {
-            self.asset_info.status = crate::asset_info::AssetStatus::Invalid;
-            return;
+    // The mesh has been uploaded to the GPU and we are storing the GPU info for later reference
+    pub fn add_gpu_info(&mut self, gpu_info: Buffer, index_gpu_info: Buffer) {
+        self.gpu_info = Some(gpu_info);
+        self.index_gpu_info = Some(index_gpu_info);
+        self.asset_info.status = AssetStatus::Uploaded;
+
+        // Free the cpu side data since we no longer need it, morphed meshes are blended from their vertices
+        if self.morph_targets.is_empty() {
+            self.vertices = vec![];
         }
+        self.indices.clear();
+    }
 
-        let (gltf, buffers, _) = import.unwrap();
+    // The mesh is being unloaded or re-uploaded, hand back the GPU info so its memory can be freed
+    pub fn take_gpu_info(&mut self) -> Vec<Buffer> {
+        self.gpu_info
+            .take()
+            .into_iter()
+            .chain(self.index_gpu_info.take())
+            .collect()
+    }
 
-        for scene in gltf.scenes() {
-            for node in scene.nodes() {
-                let mesh = node.mesh().unwrap();
+    // The mesh is being unloaded, hand back the GPU info so its memory can be freed
+    pub fn remove_gpu_info(&mut self) -> Vec<Buffer> {
+        self.vertices = vec![];
+        self.indices.clear();
+        self.asset_info.status = AssetStatus::Unloaded;
 
-                for primitive in mesh.primitives() {
-                    if primitive.mode() != gltf::mesh::Mode::Triangles {
-                        continue;
-                    }
+        self.take_gpu_info()
+    }
 
-                    self.vertices = Mesh::get_triangular_primitive_vertices(&primitive, &buffers);
-                    self.vertex_count = self.vertices.len() as u32;
-                    self.asset_info.status = AssetStatus::Loaded;
-                }
-            }
-        }
+    /// Read the mesh from its source file with the importer matching its extension, or generate it
+    /// for a builtin id, apply the settings of its meta file,
+    /// then run the optimizations enabled for it and generate its levels of detail
+    fn import(id: &str, context: &LoadContext) -> Result<MeshData, AssetError> {
+        let extension = Path::new(id)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        // Builtin ids are checked first, their parameters can look like an extension
+        let mut data = match (gltf_importer::split_mesh_id(id), extension.as_deref()) {
+            _ if is_builtin(id) => primitives::generate(id)?,
+            (Some((path, name)), _) => gltf_importer::read_mesh(path, name, &context.vfs)?,
+            (None, Some("gltf") | Some("glb")) => gltf_importer::read(id, &context.vfs)?,
+            (None, Some("obj")) => obj_importer::read(id, &context.vfs)?,
+            _ => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+        };
+
+        let meta = AssetMeta::read(id, &context.vfs)?.mesh;
+
+        import_settings::apply(&mut data, &meta);
+
+        let optimizations = meta
+            .optimizations
+            .unwrap_or_else(|| context.mesh_optimizations(id));
+
+        optimize::optimize(&mut data, &optimizations);
+        lod::generate_lods(
+            &mut data,
+            &meta.lods.unwrap_or(context.lod_settings),
+            optimizations.optimize_vertex_cache,
+        );
+
+        Ok(data)
     }
 
-    // The mesh has been uploaded to the GPU and we are storing the GPU info for later reference
-    pub fn add_gpu_info(&mut self, gpu_info: Buffer) {
-        self.gpu_info = Some(gpu_info);
-        self.asset_info.status = AssetStatus::Uploaded;
+    pub fn needs_uploaded(&self) -> bool {
+        matches!(
+            self.asset_info.status,
+            AssetStatus::Loaded | AssetStatus::Optimized
+        )
+    }
 
-        // Free the cpu side data since we no longer need it
-        self.vertices = vec![];
+    /// The submeshes of the least detailed level whose error stays under `max_screen_error` pixels
+    /// at the distance, see `MeshLod::screen_space_error`
+    pub fn lod_submeshes(
+        &self,
+        distance: f32,
+        projection_scale: f32,
+        max_screen_error: f32,
+    ) -> &[Submesh] {
+        self.lods
+            .iter()
+            .rev()
+            .find(|lod| lod.screen_space_error(distance, projection_scale) <= max_screen_error)
+            .map_or(&self.submeshes, |lod| &lod.submeshes)
     }
 
-    pub fn remove_gpu_info(&mut self) {
-        self.gpu_info = None;
-        self.asset_info.status = AssetStatus::Unloaded;
+    /// The vertices with the morph targets blended in by the weights, indexed like `morph_weights`
+    pub fn blend_morph_targets(&self, weights: &[f32]) -> Vec<Vertex> {
+        morph::blend(
+            &self.vertices,
+            &self.submeshes,
+            &self.morph_targets,
+            weights,
+        )
     }
 
-    pub fn needs_uploaded(&self) -> bool {
-        self.asset_info.status == AssetStatus::Loaded
+    /// The weights of a node's morph targets in `morph_weights`, which is where the weights
+    /// `AnimationClip::sample_weights` returns for the node go
+    pub fn node_morph_weights(&self, node: usize) -> Option<Range<usize>> {
+        morph::node_weights(&self.morph_targets, node)
+    }
+
+    /// The attributes of `vertex_layout` laid out as one tightly packed stream per attribute,
+    /// in the order of `VertexAttribute::ALL`
+    pub fn vertex_data(&self) -> Vec<f32> {
+        self.vertex_layout
+            .attributes()
+            .flat_map(|attribute| {
+                self.vertices
+                    .iter()
+                    .flat_map(move |vertex| attribute.read(vertex).iter().copied())
+            })
+            .collect()
     }
 
-    fn get_triangular_primitive_vertices(
-        primitive: &gltf::Primitive,
-        buffers: &[gltf::buffer::Data],
-    ) -> Vec<Vertex> {
-        let mut positions: Vec<glm::Vec3> = vec![];
-        let mut normals: Vec<glm::Vec3> = vec![];
+    /// Offset in bytes of the attribute's stream in the data returned by `vertex_data`,
+    /// None for attributes the mesh does not have
+    pub fn attribute_offset(&self, attribute: VertexAttribute) -> Option<u64> {
+        if !self.vertex_layout.contains(attribute) {
+            return None;
+        }
 
-        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let preceding_size: usize = self
+            .vertex_layout
+            .attributes()
+            .take_while(|existing| *existing != attribute)
+            .map(|existing| existing.size())
+            .sum();
 
-        if let Some(iter) = reader.read_positions() {
-            for position in iter {
-                positions.push(glm::vec3(position[0], position[1], position[2]));
-            }
+        Some((preceding_size * self.vertex_count as usize) as u64)
+    }
+}
+
+impl LoadableAsset for Mesh {
+    type Data = MeshData;
+
+    fn read(id: &str, context: &LoadContext) -> Result<MeshData, AssetError> {
+        // Generating a builtin mesh is quicker than reading it back from the cache
+        let cache_directory = match &context.cache_directory {
+            Some(cache_directory) if !is_builtin(id) => cache_directory,
+            _ => return Mesh::import(id, context),
+        };
+
+        match cache::read(cache_directory, context, id) {
+            Ok(Some(data)) => return Ok(data),
+            Ok(None) => {}
+            Err(e) => warn!("Ignoring the cached mesh of {}: {}", id, e),
         }
-        if let Some(iter) = reader.read_normals() {
-            for normal in iter {
-                normals.push(glm::vec3(normal[0], normal[1], normal[2]));
-            }
+
+        let data = Mesh::import(id, context)?;
+
+        if let Err(e) = cache::write(cache_directory, context, id, &data) {
+            warn!("Failed to cache mesh {}: {}", id, e);
         }
 
-        let mut rng = rand::thread_rng();
+        Ok(data)
+    }
+
+    fn publish(&mut self, data: Result<MeshData, AssetError>) {
+        match data {
+            Ok(data) => {
+                self.vertex_count = data.vertices.len() as u32;
+                self.vertices = data.vertices;
+                self.vertex_layout = data.vertex_layout;
+                self.indices = data.indices;
+                self.bounds = data.bounds;
+                self.submeshes = data.submeshes;
+                self.materials = data.materials;
+                self.lods = data.lods;
+                self.skeleton = data.skeleton;
+                self.morph_targets = data.morph_targets;
+                self.morph_weights = data.morph_weights;
+                self.asset_info.set_loaded();
+
+                if data.optimized {
+                    self.asset_info.status = AssetStatus::Optimized;
+                }
+            }
+            Err(e) => {
+                warn!("Failed to load mesh {}: {}", self.asset_info.id, e);
 
-        let mut vertices: Vec<Vertex> = vec![];
-        if let Some(indices) = reader.read_indices() {
-            for index in indices.into_u32() {
-                vertices.push(Vertex {
-                    position: positions[index as usize],
-                    normal: normals[index as usize],
-                    color: glm::vec3(rng.gen(), rng.gen(), rng.gen()),
-                })
+                self.asset_info.set_failed(e);
             }
         }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn dependencies(data: &MeshData) -> Vec<Dependency> {
+        data.dependencies
+            .iter()
+            .map(|file| Dependency::File(file.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_unsupported_format() {
+        assert_eq!(
+            Mesh::read("fixtures/two_materials.mtl", &LoadContext::default()).err(),
+            Some(AssetError::UnsupportedFormat(
+                "fixtures/two_materials.mtl".to_owned()
+            ))
+        );
+    }
+
+    /// Bounds of the fixture's triangle, which spans a unit square from `min`
+    fn triangle_bounds(min: glm::Vec3) -> Bounds {
+        let max = min + glm::vec3(1.0, 1.0, 0.0);
+
+        Bounds {
+            aabb: Aabb { min, max },
+            sphere: BoundingSphere {
+                center: (min + max) * 0.5,
+                radius: 0.5f32.sqrt(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_read_nested_nodes() {
+        let data = Mesh::read("fixtures/nested_nodes.gltf", &LoadContext::default()).unwrap();
+
+        assert_eq!(
+            data.submeshes,
+            vec![
+                Submesh {
+                    first_index: 0,
+                    index_count: 3,
+                    first_vertex: 0,
+                    vertex_count: 3,
+                    material_index: Some(0),
+                    bounds: triangle_bounds(glm::vec3(10.0, 5.0, 0.0)),
+                },
+                Submesh {
+                    first_index: 3,
+                    index_count: 3,
+                    first_vertex: 3,
+                    vertex_count: 3,
+                    material_index: Some(1),
+                    bounds: triangle_bounds(glm::Vec3::zeros()),
+                },
+                Submesh {
+                    first_index: 6,
+                    index_count: 3,
+                    first_vertex: 6,
+                    vertex_count: 3,
+                    material_index: None,
+                    bounds: triangle_bounds(glm::Vec3::zeros()),
+                },
+            ]
+        );
+        assert_eq!(data.vertices.len(), 9);
+        assert_eq!(data.indices, Indices::U16(vec![0, 1, 2, 0, 1, 2, 0, 1, 2]));
+        assert_eq!(
+            data.bounds.aabb,
+            Aabb {
+                min: glm::Vec3::zeros(),
+                max: glm::vec3(11.0, 6.0, 0.0),
+            }
+        );
+        assert_eq!(data.bounds.sphere.center, glm::vec3(5.5, 3.0, 0.0));
+        assert_eq!(
+            data.materials[1].id.as_deref(),
+            Some("fixtures/nested_nodes.gltf#materials/1")
+        );
+    }
+
+    #[test]
+    fn test_read_applies_node_transforms() {
+        let data = Mesh::read("fixtures/nested_nodes.gltf", &LoadContext::default()).unwrap();
+
+        // The child node is translated by both its own and its parent's transform
+        assert_eq!(data.vertices[0].position, glm::vec3(10.0, 5.0, 0.0));
+        assert_eq!(data.vertices[1].position, glm::vec3(11.0, 5.0, 0.0));
+        assert_eq!(data.vertices[3].position, glm::vec3(0.0, 0.0, 0.0));
+        assert_eq!(data.vertices[0].normal, glm::vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_read_single_mesh() {
+        let data = Mesh::read(
+            &mesh_id("fixtures/nested_nodes.gltf", 1),
+            &LoadContext::default(),
+        )
+        .unwrap();
+
+        // Only the mesh's own primitives, left where the file puts them
+        assert_eq!(data.submeshes.len(), 2);
+        assert_eq!(data.vertices[0].position, glm::vec3(0.0, 0.0, 0.0));
+        assert_eq!(data.materials.len(), 2);
+
+        let child = Mesh::read(
+            "fixtures/nested_nodes.gltf#meshes/0",
+            &LoadContext::default(),
+        )
+        .unwrap();
+        assert_eq!(child.vertices[1].position, glm::vec3(1.0, 0.0, 0.0));
+
+        assert!(Mesh::read(
+            "fixtures/nested_nodes.gltf#meshes/2",
+            &LoadContext::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_read_skinned() {
+        let data = Mesh::read("fixtures/skinned.gltf", &LoadContext::default()).unwrap();
+
+        assert_eq!(
+            data.skeleton.as_deref(),
+            Some("fixtures/skinned.gltf#skins/0")
+        );
+
+        // The joints place skinned vertices, the translation of their node is ignored
+        assert_eq!(data.vertices[1].position, glm::vec3(1.0, 0.0, 0.0));
+        assert_eq!(data.vertices[1].joints, glm::vec4(1.0, 0.0, 0.0, 0.0));
+        assert_eq!(data.vertices[2].weights, glm::vec4(0.5, 0.5, 0.0, 0.0));
+
+        assert!(data.vertex_layout.contains(VertexAttribute::Joints));
+        assert!(data.vertex_layout.contains(VertexAttribute::Weights));
+        assert!(!data.vertex_layout.contains(VertexAttribute::Color));
+
+        // A single mesh is skinned by the skin of the node using it
+        let mesh = Mesh::read("fixtures/skinned.gltf#meshes/0", &LoadContext::default()).unwrap();
+        assert_eq!(mesh.skeleton, data.skeleton);
+    }
+
+    #[test]
+    fn test_read_mirrored() {
+        let data = Mesh::read("fixtures/mirrored.gltf", &LoadContext::default()).unwrap();
+
+        let indices = |submesh: &Submesh| match &data.indices {
+            Indices::U16(indices) => indices[submesh.first_index as usize
+                ..(submesh.first_index + submesh.index_count) as usize]
+                .iter()
+                .map(|index| *index as u32)
+                .collect::<Vec<u32>>(),
+            Indices::U32(indices) => indices[submesh.first_index as usize
+                ..(submesh.first_index + submesh.index_count) as usize]
+                .to_vec(),
+        };
+
+        // The mirrored copy is wound the other way, so both still face the same side
+        assert_eq!(indices(&data.submeshes[0]), vec![0, 1, 2]);
+        assert_eq!(indices(&data.submeshes[1]), vec![0, 2, 1]);
+        assert_eq!(data.vertices[4].position, glm::vec3(-1.0, 0.0, 0.0));
+        assert_eq!(data.vertices[0].normal, glm::vec3(0.0, 0.0, 1.0));
+        assert_eq!(data.vertices[4].normal, glm::vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_vertex_data_streams() {
+        let data = Mesh::read("fixtures/nested_nodes.gltf", &LoadContext::default()).unwrap();
+
+        let mesh = Mesh {
+            asset_info: AssetInfo::new("fixtures/nested_nodes.gltf"),
+            gpu_info: None,
+            index_gpu_info: None,
+            vertex_count: data.vertices.len() as u32,
+            vertices: data.vertices,
+            vertex_layout: data.vertex_layout,
+            indices: data.indices,
+            bounds: data.bounds,
+            submeshes: data.submeshes,
+            materials: data.materials,
+            lods: data.lods,
+            skeleton: data.skeleton,
+            morph_targets: data.morph_targets,
+            morph_weights: data.morph_weights,
+        };
+
+        let vertex_data = mesh.vertex_data();
 
-        vertices
+        // The fixture only has positions and normals, tangents are generated for it
+        assert_eq!(vertex_data.len(), 9 * (3 + 3 + 4));
+        assert_eq!(mesh.attribute_offset(VertexAttribute::Position), Some(0));
+        assert_eq!(mesh.attribute_offset(VertexAttribute::Normal), Some(9 * 12));
+        assert_eq!(
+            mesh.attribute_offset(VertexAttribute::Tangent),
+            Some(9 * 24)
+        );
+        assert_eq!(mesh.attribute_offset(VertexAttribute::TexCoord0), None);
+        assert_eq!(mesh.attribute_offset(VertexAttribute::Color), None);
     }
 }
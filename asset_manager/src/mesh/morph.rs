@@ -0,0 +1,149 @@
+use std::ops::Range;
+
+use super::{Submesh, Vertex};
+
+/// A blend shape of a single submesh, moving its vertices by the deltas times the target's weight
+#[derive(Clone, Debug, PartialEq)]
+pub struct MorphTarget {
+    /// Index of the submesh in `Mesh::submeshes`, the deltas are indexed like its vertices
+    pub submesh: usize,
+    /// Index of the node in the source file, which animation weight channels target
+    pub node: usize,
+    /// Index of the weight the target is blended by in `Mesh::morph_weights`
+    pub weight: usize,
+    pub position_deltas: Vec<glm::Vec3>,
+    /// Empty when the target leaves the normals alone
+    pub normal_deltas: Vec<glm::Vec3>,
+}
+
+/// The vertices with every target added in by its weight, weights missing from the slice count as 0
+pub fn blend(
+    vertices: &[Vertex],
+    submeshes: &[Submesh],
+    targets: &[MorphTarget],
+    weights: &[f32],
+) -> Vec<Vertex> {
+    let mut blended = vertices.to_vec();
+    let mut moved_normals = vec![false; vertices.len()];
+
+    for target in targets {
+        let weight = weights.get(target.weight).copied().unwrap_or(0.0);
+
+        if weight == 0.0 {
+            continue;
+        }
+
+        let first_vertex = submeshes[target.submesh].first_vertex as usize;
+
+        for (vertex, delta) in blended[first_vertex..]
+            .iter_mut()
+            .zip(&target.position_deltas)
+        {
+            vertex.position += delta * weight;
+        }
+
+        for ((vertex, moved), delta) in blended[first_vertex..]
+            .iter_mut()
+            .zip(&mut moved_normals[first_vertex..])
+            .zip(&target.normal_deltas)
+        {
+            vertex.normal += delta * weight;
+            *moved = true;
+        }
+    }
+
+    for (vertex, moved) in blended.iter_mut().zip(moved_normals) {
+        if moved && vertex.normal != glm::Vec3::zeros() {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+
+    blended
+}
+
+/// The weights of the node's targets in `Mesh::morph_weights`, in the order its animation
+/// channels list them
+pub fn node_weights(targets: &[MorphTarget], node: usize) -> Option<Range<usize>> {
+    let weights = targets
+        .iter()
+        .filter(|target| target.node == node)
+        .map(|target| target.weight);
+
+    let start = weights.clone().min()?;
+    let end = weights.max()? + 1;
+
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loader::LoadContext, mesh::Mesh, LoadableAsset, MeshOptimizations};
+
+    #[test]
+    fn test_read_targets() {
+        let data = Mesh::read("fixtures/morph_targets.gltf", &LoadContext::default()).unwrap();
+
+        assert_eq!(data.morph_weights, vec![0.5, 0.0]);
+        assert_eq!(data.morph_targets.len(), 2);
+        assert_eq!(node_weights(&data.morph_targets, 0), Some(0..2));
+        assert_eq!(node_weights(&data.morph_targets, 1), None);
+
+        // Deltas are scaled by the node transform, but not translated
+        assert_eq!(
+            data.morph_targets[0].position_deltas,
+            vec![glm::vec3(0.0, 0.0, 2.0); 3]
+        );
+        assert!(data.morph_targets[0].normal_deltas.is_empty());
+        assert_eq!(data.morph_targets[1].normal_deltas.len(), 3);
+    }
+
+    #[test]
+    fn test_blend() {
+        let data = Mesh::read("fixtures/morph_targets.gltf", &LoadContext::default()).unwrap();
+
+        let blend = |weights: &[f32]| {
+            blend(
+                &data.vertices,
+                &data.submeshes,
+                &data.morph_targets,
+                weights,
+            )
+        };
+
+        assert_eq!(blend(&[]), data.vertices);
+
+        let raised = blend(&data.morph_weights);
+        assert_eq!(raised[0].position, glm::vec3(2.0, 0.0, 1.0));
+        assert_eq!(raised[0].normal, data.vertices[0].normal);
+
+        // The second target moves the tip up and tilts its normal, which stays normalized
+        let tipped = blend(&[0.0, 1.0]);
+        assert_eq!(tipped[2].position, glm::vec3(2.0, 4.0, 0.0));
+        assert_eq!(tipped[1].position, data.vertices[1].position);
+        assert!((tipped[2].normal - glm::vec3(0.0, 0.5f32.sqrt(), 0.5f32.sqrt())).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_optimize_keeps_morphed_vertices() {
+        let context = LoadContext {
+            default_mesh_optimizations: MeshOptimizations::all(),
+            ..Default::default()
+        };
+
+        let data = Mesh::read("fixtures/morph_targets.gltf", &context).unwrap();
+
+        assert!(data.optimized);
+        assert_eq!(data.vertices.len(), 3);
+        assert_eq!(
+            blend(
+                &data.vertices,
+                &data.submeshes,
+                &data.morph_targets,
+                &[0.0, 1.0]
+            )[2]
+            .position,
+            glm::vec3(2.0, 4.0, 0.0)
+        );
+    }
+}
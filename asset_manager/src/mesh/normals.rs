@@ -0,0 +1,55 @@
+use super::Vertex;
+
+/// Fill in smooth normals for a triangle list whose source has none,
+/// averaging the normals of the faces around each vertex weighted by their area
+pub fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![glm::Vec3::zeros(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [
+            triangle[0] as usize,
+            triangle[1] as usize,
+            triangle[2] as usize,
+        ];
+
+        if a >= vertices.len() || b >= vertices.len() || c >= vertices.len() {
+            continue;
+        }
+
+        // The cross product is twice the area of the triangle long
+        let face_normal = (vertices[b].position - vertices[a].position)
+            .cross(&(vertices[c].position - vertices[a].position));
+
+        for index in [a, b, c] {
+            normals[index] += face_normal;
+        }
+    }
+
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        if normal.norm_squared() > 0.0 {
+            vertex.normal = normal.normalize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_normals() {
+        let mut vertices: Vec<Vertex> = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]
+            .iter()
+            .map(|position| Vertex {
+                position: glm::vec3(position[0], position[1], 0.0),
+                ..Default::default()
+            })
+            .collect();
+
+        generate_normals(&mut vertices, &[0, 1, 2]);
+
+        for vertex in vertices {
+            assert_eq!(vertex.normal, glm::vec3(0.0, 0.0, 1.0));
+        }
+    }
+}
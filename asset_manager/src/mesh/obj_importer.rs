@@ -0,0 +1,246 @@
+use std::{cell::RefCell, io::Cursor, path::Path};
+
+use log::warn;
+
+use super::{
+    normals, tangents, Bounds, Indices, MeshData, MeshMaterial, Submesh, Vertex, VertexAttribute,
+    VertexLayout,
+};
+use crate::{vfs::Vfs, AssetError};
+
+/// Read every object and group of a Wavefront OBJ file, along with the materials of its MTL files
+pub fn read(path: &str, vfs: &Vfs) -> Result<MeshData, AssetError> {
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+    // MTL files are looked up next to the OBJ file like `tobj::load_obj` does, noting them on the way
+    let material_libraries = RefCell::new(vec![]);
+
+    let (models, materials) = tobj::load_obj_buf(
+        &mut Cursor::new(vfs.read(path)?),
+        &tobj::GPU_LOAD_OPTIONS,
+        |material_library| {
+            let material_library = directory.join(material_library);
+            material_libraries
+                .borrow_mut()
+                .push(material_library.clone());
+
+            match vfs.read(&material_library) {
+                Ok(contents) => tobj::load_mtl_buf(&mut Cursor::new(contents)),
+                Err(_) => Err(tobj::LoadError::OpenFileFailed),
+            }
+        },
+    )?;
+
+    // The geometry is still usable without its materials
+    let materials = match materials {
+        Ok(materials) => materials,
+        Err(e) => {
+            warn!("Failed to load materials of {}: {}", path, e);
+            vec![]
+        }
+    };
+
+    let mut vertices = vec![];
+    let mut vertex_layout = VertexLayout::default();
+    let mut indices = vec![];
+    let mut submeshes = vec![];
+
+    for model in &models {
+        let (model_vertices, model_indices) = read_model(&model.mesh);
+
+        if model_indices.is_empty() {
+            continue;
+        }
+
+        if !model.mesh.texcoords.is_empty() {
+            vertex_layout = vertex_layout.with(VertexAttribute::TexCoord0);
+        }
+        if !model.mesh.vertex_color.is_empty() {
+            vertex_layout = vertex_layout.with(VertexAttribute::Color);
+        }
+
+        submeshes.push(Submesh {
+            first_index: indices.len() as u32,
+            index_count: model_indices.len() as u32,
+            first_vertex: vertices.len() as u32,
+            vertex_count: model_vertices.len() as u32,
+            material_index: model.mesh.material_id,
+            bounds: Bounds::from_vertices(&model_vertices),
+        });
+        vertices.extend(model_vertices);
+        indices.extend(model_indices);
+    }
+
+    let materials = materials
+        .iter()
+        .map(|material| read_material(directory, material))
+        .collect();
+
+    Ok(MeshData {
+        bounds: Bounds::from_submeshes(&submeshes, &vertices),
+        vertices,
+        vertex_layout,
+        indices: Indices::from(indices),
+        submeshes,
+        materials,
+        dependencies: material_libraries.into_inner(),
+        lods: vec![],
+        skeleton: None,
+        morph_targets: vec![],
+        morph_weights: vec![],
+        optimized: false,
+    })
+}
+
+fn read_model(mesh: &tobj::Mesh) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices: Vec<Vertex> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|position| Vertex {
+            position: glm::vec3(position[0], position[1], position[2]),
+            ..Default::default()
+        })
+        .collect();
+
+    for (vertex, uv) in vertices.iter_mut().zip(mesh.texcoords.chunks_exact(2)) {
+        // OBJ texture coordinates start at the bottom left, ours at the top left
+        vertex.uv0 = glm::vec2(uv[0], 1.0 - uv[1]);
+    }
+    for (vertex, color) in vertices.iter_mut().zip(mesh.vertex_color.chunks_exact(3)) {
+        vertex.color = glm::vec4(color[0], color[1], color[2], 1.0);
+    }
+
+    let indices = mesh.indices.clone();
+
+    if mesh.normals.is_empty() {
+        normals::generate_normals(&mut vertices, &indices);
+    } else {
+        for (vertex, normal) in vertices.iter_mut().zip(mesh.normals.chunks_exact(3)) {
+            vertex.normal = glm::vec3(normal[0], normal[1], normal[2]).normalize();
+        }
+    }
+
+    tangents::generate_tangents(&mut vertices, &indices);
+
+    (vertices, indices)
+}
+
+fn read_material(directory: &Path, material: &tobj::Material) -> MeshMaterial {
+    let diffuse = material.diffuse.unwrap_or([1.0, 1.0, 1.0]);
+
+    // Texture paths are relative to the MTL file, which lives next to the OBJ file
+    let base_color_texture = material.diffuse_texture.as_ref().map(|texture| {
+        directory
+            .join(texture.replace('\\', "/"))
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    MeshMaterial {
+        name: Some(material.name.clone()),
+        id: None,
+        base_color: glm::vec4(
+            diffuse[0],
+            diffuse[1],
+            diffuse[2],
+            material.dissolve.unwrap_or(1.0),
+        ),
+        base_color_texture,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::mesh::{Aabb, BoundingSphere};
+
+    /// Bounds of one of the fixture's unit quads
+    fn quad_bounds(min_x: f32) -> Bounds {
+        let min = glm::vec3(min_x, 0.0, 0.0);
+        let max = glm::vec3(min_x + 1.0, 1.0, 0.0);
+
+        Bounds {
+            aabb: Aabb { min, max },
+            sphere: BoundingSphere {
+                center: (min + max) * 0.5,
+                radius: 0.5f32.sqrt(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_read_objects_and_materials() {
+        let data = read("fixtures/two_materials.obj", &Vfs::working_directory()).unwrap();
+
+        assert_eq!(
+            data.submeshes,
+            vec![
+                Submesh {
+                    first_index: 0,
+                    index_count: 6,
+                    first_vertex: 0,
+                    vertex_count: 4,
+                    material_index: Some(0),
+                    bounds: quad_bounds(0.0),
+                },
+                Submesh {
+                    first_index: 6,
+                    index_count: 6,
+                    first_vertex: 4,
+                    vertex_count: 4,
+                    material_index: Some(1),
+                    bounds: quad_bounds(2.0),
+                },
+            ]
+        );
+        assert_eq!(data.indices.len(), 12);
+        assert_eq!(
+            data.dependencies,
+            vec![PathBuf::from("fixtures/two_materials.mtl")]
+        );
+
+        assert_eq!(data.materials.len(), 2);
+        assert_eq!(data.materials[0].name.as_deref(), Some("red"));
+        assert_eq!(data.materials[0].base_color, glm::vec4(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(
+            data.materials[0].base_color_texture.as_deref(),
+            Some("fixtures/red.png")
+        );
+        assert_eq!(data.materials[1].base_color, glm::vec4(0.0, 0.0, 1.0, 0.5));
+        assert_eq!(data.materials[1].base_color_texture, None);
+    }
+
+    #[test]
+    fn test_read_flips_texture_coordinates() {
+        let data = read("fixtures/two_materials.obj", &Vfs::working_directory()).unwrap();
+
+        assert_eq!(data.vertices[0].uv0, glm::vec2(0.0, 1.0));
+        assert_eq!(data.vertices[2].uv0, glm::vec2(1.0, 0.0));
+        assert_eq!(data.vertices[0].normal, glm::vec3(0.0, 0.0, 1.0));
+        // v runs against y once flipped, so the bitangent points the other way
+        assert_eq!(data.vertices[0].tangent, glm::vec4(1.0, 0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_read_without_normals_or_materials() {
+        let data = read("fixtures/no_normals.obj", &Vfs::working_directory()).unwrap();
+
+        assert_eq!(data.submeshes.len(), 1);
+        assert_eq!(data.submeshes[0].material_index, None);
+        assert!(data.materials.is_empty());
+
+        for vertex in &data.vertices {
+            assert_eq!(vertex.normal, glm::vec3(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        assert!(matches!(
+            read("fixtures/missing.obj", &Vfs::working_directory()),
+            Err(AssetError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+
+use super::{Bounds, Indices, MeshData, Submesh, Vertex, VertexAttribute};
+
+/// Size of the vertex cache the index order is optimized for, larger than most GPUs have
+/// so the order holds up on all of them
+const CACHE_SIZE: usize = 32;
+
+/// The processing passes run on a mesh once it is imported, every pass is off by default
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshOptimizations {
+    /// Merge vertices whose attributes are all exactly equal
+    pub deduplicate_vertices: bool,
+    /// Reorder triangles so vertices are reused while they are still in the GPU's vertex cache
+    pub optimize_vertex_cache: bool,
+    /// Reorder vertices in the order the triangles use them, dropping unused ones
+    pub optimize_vertex_fetch: bool,
+    /// Fit the bounds to the vertices the triangles use
+    pub compute_bounds: bool,
+}
+
+impl MeshOptimizations {
+    pub fn all() -> MeshOptimizations {
+        MeshOptimizations {
+            deduplicate_vertices: true,
+            optimize_vertex_cache: true,
+            optimize_vertex_fetch: true,
+            compute_bounds: true,
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.deduplicate_vertices
+            || self.optimize_vertex_cache
+            || self.optimize_vertex_fetch
+            || self.compute_bounds
+    }
+}
+
+/// Run the enabled passes on every submesh of the mesh
+pub fn optimize(data: &mut MeshData, optimizations: &MeshOptimizations) {
+    if !optimizations.any() {
+        return;
+    }
+
+    let indices: Vec<u32> = data.indices.iter().collect();
+
+    let mut vertices = vec![];
+    let mut optimized_indices = vec![];
+    let mut submeshes = vec![];
+
+    for (submesh_index, submesh) in data.submeshes.iter().enumerate() {
+        // Morph target deltas are indexed like the submesh's vertices, so their order has to stay
+        let morphed = data
+            .morph_targets
+            .iter()
+            .any(|target| target.submesh == submesh_index);
+
+        let first_vertex = submesh.first_vertex as usize;
+        let first_index = submesh.first_index as usize;
+
+        let mut submesh_vertices =
+            data.vertices[first_vertex..first_vertex + submesh.vertex_count as usize].to_vec();
+        let mut submesh_indices =
+            indices[first_index..first_index + submesh.index_count as usize].to_vec();
+
+        if optimizations.deduplicate_vertices && !morphed {
+            submesh_vertices = deduplicate_vertices(&submesh_vertices, &mut submesh_indices);
+        }
+        if optimizations.optimize_vertex_cache {
+            submesh_indices = optimize_vertex_cache(&submesh_indices, submesh_vertices.len());
+        }
+        if optimizations.optimize_vertex_fetch && !morphed {
+            submesh_vertices = optimize_vertex_fetch(&submesh_vertices, &mut submesh_indices);
+        }
+
+        let bounds = match optimizations.compute_bounds {
+            true => Bounds::from_vertices(&used_vertices(&submesh_vertices, &submesh_indices)),
+            false => submesh.bounds,
+        };
+
+        submeshes.push(Submesh {
+            first_index: optimized_indices.len() as u32,
+            index_count: submesh_indices.len() as u32,
+            first_vertex: vertices.len() as u32,
+            vertex_count: submesh_vertices.len() as u32,
+            bounds,
+            ..*submesh
+        });
+        vertices.extend(submesh_vertices);
+        optimized_indices.extend(submesh_indices);
+    }
+
+    if optimizations.compute_bounds {
+        let used_vertices: Vec<Vertex> = submeshes
+            .iter()
+            .flat_map(|submesh| {
+                let first_vertex = submesh.first_vertex as usize;
+                let first_index = submesh.first_index as usize;
+
+                used_vertices(
+                    &vertices[first_vertex..first_vertex + submesh.vertex_count as usize],
+                    &optimized_indices[first_index..first_index + submesh.index_count as usize],
+                )
+            })
+            .collect();
+
+        data.bounds = Bounds::from_submeshes(&submeshes, &used_vertices);
+    }
+
+    data.vertices = vertices;
+    data.indices = Indices::from(optimized_indices);
+    data.submeshes = submeshes;
+    data.optimized = true;
+}
+
+/// Merge vertices with bitwise equal attributes, remapping the indices to the vertices kept
+pub fn deduplicate_vertices(vertices: &[Vertex], indices: &mut [u32]) -> Vec<Vertex> {
+    let mut unique_vertices = vec![];
+    let mut unique_indices = HashMap::new();
+
+    let remap: Vec<u32> = vertices
+        .iter()
+        .map(|vertex| {
+            let key: Vec<u32> = VertexAttribute::ALL
+                .iter()
+                .flat_map(|attribute| attribute.read(vertex).iter().map(|value| value.to_bits()))
+                .collect();
+
+            *unique_indices.entry(key).or_insert_with(|| {
+                unique_vertices.push(*vertex);
+                unique_vertices.len() as u32 - 1
+            })
+        })
+        .collect();
+
+    for index in indices.iter_mut() {
+        *index = remap[*index as usize];
+    }
+
+    unique_vertices
+}
+
+/// Score of a vertex for the vertex cache optimization, following Tom Forsyth's
+/// "Linear-Speed Vertex Cache Optimisation"
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        // The vertices of the last triangle get a fixed score so its neighbours are not
+        // preferred too strongly over triangles that use older cache entries
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+        None => 0.0,
+    };
+
+    // Vertices with few triangles left are finished off first, so they can leave the cache
+    let valence_score = 2.0 * (remaining_triangles as f32).powf(-0.5);
+
+    cache_score + valence_score
+}
+
+/// Reorder the triangles so each one reuses as many vertices of the previous ones as possible
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+
+    let mut vertex_triangles = vec![vec![]; vertex_count];
+    for (triangle, triangle_indices) in indices.chunks_exact(3).enumerate() {
+        for index in triangle_indices {
+            vertex_triangles[*index as usize].push(triangle);
+        }
+    }
+
+    let mut cache_positions = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = vertex_triangles
+        .iter()
+        .map(|triangles| vertex_score(None, triangles.len()))
+        .collect();
+
+    let triangle_score = |triangle: usize, vertex_scores: &[f32]| -> f32 {
+        indices[triangle * 3..triangle * 3 + 3]
+            .iter()
+            .map(|index| vertex_scores[*index as usize])
+            .sum()
+    };
+
+    let mut emitted = vec![false; triangle_count];
+    let mut next_unemitted = 0;
+    let mut cache: Vec<u32> = vec![];
+    let mut optimized = Vec::with_capacity(triangle_count * 3);
+
+    let mut best_triangle = (0..triangle_count).max_by(|a, b| {
+        triangle_score(*a, &vertex_scores).total_cmp(&triangle_score(*b, &vertex_scores))
+    });
+
+    while let Some(triangle) = best_triangle {
+        emitted[triangle] = true;
+
+        let triangle_indices = &indices[triangle * 3..triangle * 3 + 3];
+        optimized.extend_from_slice(triangle_indices);
+
+        for index in triangle_indices {
+            vertex_triangles[*index as usize].retain(|existing| *existing != triangle);
+        }
+
+        // The triangle's vertices move to the front of the cache, pushing the oldest ones out
+        let mut touched: Vec<u32> = vec![];
+        for index in triangle_indices.iter().chain(&cache) {
+            if !touched.contains(index) {
+                touched.push(*index);
+            }
+        }
+
+        for (position, index) in touched.iter().enumerate() {
+            cache_positions[*index as usize] = (position < CACHE_SIZE).then_some(position);
+            vertex_scores[*index as usize] = vertex_score(
+                cache_positions[*index as usize],
+                vertex_triangles[*index as usize].len(),
+            );
+        }
+
+        cache = touched.iter().take(CACHE_SIZE).copied().collect();
+
+        // Only triangles of cached vertices changed score, so the best one is looked for among them
+        best_triangle = cache
+            .iter()
+            .flat_map(|index| vertex_triangles[*index as usize].iter().copied())
+            .max_by(|a, b| {
+                triangle_score(*a, &vertex_scores).total_cmp(&triangle_score(*b, &vertex_scores))
+            });
+
+        // Nothing in the cache has triangles left, continue with the next one in the input order
+        if best_triangle.is_none() {
+            while next_unemitted < triangle_count && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+
+            best_triangle = (next_unemitted < triangle_count).then_some(next_unemitted);
+        }
+    }
+
+    optimized
+}
+
+/// Renumber the vertices in the order the indices first use them, dropping vertices no index uses
+pub fn optimize_vertex_fetch(vertices: &[Vertex], indices: &mut [u32]) -> Vec<Vertex> {
+    let mut remap = vec![None; vertices.len()];
+    let mut ordered_vertices = vec![];
+
+    for index in indices.iter_mut() {
+        let remapped = remap[*index as usize].get_or_insert_with(|| {
+            ordered_vertices.push(vertices[*index as usize]);
+            ordered_vertices.len() as u32 - 1
+        });
+
+        *index = *remapped;
+    }
+
+    ordered_vertices
+}
+
+/// The vertices the triangles use, so bounds can leave out the ones no triangle draws
+fn used_vertices(vertices: &[Vertex], indices: &[u32]) -> Vec<Vertex> {
+    indices
+        .iter()
+        .map(|index| vertices[*index as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::mesh::{Aabb, VertexLayout};
+
+    fn vertex(x: f32, y: f32) -> Vertex {
+        Vertex {
+            position: glm::vec3(x, y, 0.0),
+            ..Default::default()
+        }
+    }
+
+    /// Average number of vertices transformed per triangle with a FIFO cache
+    fn average_cache_miss_ratio(indices: &[u32], cache_size: usize) -> f32 {
+        let mut cache = VecDeque::new();
+        let mut misses = 0;
+
+        for index in indices {
+            if !cache.contains(index) {
+                misses += 1;
+                cache.push_back(*index);
+
+                if cache.len() > cache_size {
+                    cache.pop_front();
+                }
+            }
+        }
+
+        misses as f32 / (indices.len() / 3) as f32
+    }
+
+    /// A grid of quads with its triangles in a scrambled order
+    fn scrambled_grid(size: u32) -> Vec<u32> {
+        let mut triangles = vec![];
+
+        for y in 0..size {
+            for x in 0..size {
+                let corner = y * (size + 1) + x;
+
+                triangles.push([corner, corner + 1, corner + size + 1]);
+                triangles.push([corner + 1, corner + size + 2, corner + size + 1]);
+            }
+        }
+
+        // A fixed stride walk, so the test does not depend on a random generator
+        let count = triangles.len();
+        (0..count)
+            .flat_map(|i| triangles[(i * 97) % count])
+            .collect()
+    }
+
+    #[test]
+    fn test_deduplicate_vertices() {
+        let vertices = vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 0.0)];
+        let mut indices = vec![0, 1, 2, 2, 1, 0];
+
+        let unique = deduplicate_vertices(&vertices, &mut indices);
+
+        assert_eq!(unique, vec![vertex(0.0, 0.0), vertex(1.0, 0.0)]);
+        assert_eq!(indices, vec![0, 1, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_optimize_vertex_cache() {
+        let size = 16;
+        let indices = scrambled_grid(size);
+
+        let optimized = optimize_vertex_cache(&indices, ((size + 1) * (size + 1)) as usize);
+
+        // Every triangle is kept, with its winding
+        let mut triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        let mut optimized_triangles: Vec<[u32; 3]> = optimized
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        triangles.sort();
+        optimized_triangles.sort();
+        assert_eq!(optimized_triangles, triangles);
+
+        let before = average_cache_miss_ratio(&indices, 16);
+        let after = average_cache_miss_ratio(&optimized, 16);
+        assert!(after < 1.0, "{} misses per triangle", after);
+        assert!(after < before, "{} is no better than {}", after, before);
+    }
+
+    #[test]
+    fn test_optimize_vertex_fetch() {
+        let vertices = vec![
+            vertex(0.0, 0.0),
+            vertex(1.0, 0.0),
+            vertex(2.0, 0.0),
+            vertex(3.0, 0.0),
+        ];
+        let mut indices = vec![3, 1, 0];
+
+        let ordered = optimize_vertex_fetch(&vertices, &mut indices);
+
+        assert_eq!(
+            ordered,
+            vec![vertex(3.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 0.0)]
+        );
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_optimize_submeshes() {
+        let vertices = vec![
+            vertex(0.0, 0.0),
+            vertex(1.0, 0.0),
+            vertex(0.0, 1.0),
+            vertex(0.0, 0.0),
+            // Not used by any triangle, so it does not count towards the tight bounds
+            vertex(-5.0, -5.0),
+            vertex(2.0, 2.0),
+            vertex(3.0, 2.0),
+            vertex(2.0, 3.0),
+        ];
+
+        let mut data = MeshData {
+            vertex_layout: VertexLayout::default(),
+            bounds: Bounds::from_vertices(&vertices),
+            indices: Indices::from(vec![0, 1, 2, 3, 2, 1, 0, 1, 2]),
+            submeshes: vec![
+                Submesh {
+                    first_index: 0,
+                    index_count: 6,
+                    first_vertex: 0,
+                    vertex_count: 5,
+                    material_index: Some(0),
+                    bounds: Bounds::from_vertices(&vertices[0..5]),
+                },
+                Submesh {
+                    first_index: 6,
+                    index_count: 3,
+                    first_vertex: 5,
+                    vertex_count: 3,
+                    material_index: None,
+                    bounds: Bounds::from_vertices(&vertices[5..8]),
+                },
+            ],
+            materials: vec![],
+            dependencies: vec![],
+            lods: vec![],
+            skeleton: None,
+            morph_targets: vec![],
+            morph_weights: vec![],
+            optimized: false,
+            vertices,
+        };
+
+        optimize(&mut data, &MeshOptimizations::default());
+        assert!(!data.optimized);
+
+        optimize(&mut data, &MeshOptimizations::all());
+        assert!(data.optimized);
+
+        assert_eq!(data.vertices.len(), 6);
+        assert_eq!(data.submeshes[0].vertex_count, 3);
+        assert_eq!(data.submeshes[1].first_vertex, 3);
+        assert_eq!(data.submeshes[1].material_index, None);
+        assert_eq!(data.indices.len(), 9);
+        assert_eq!(
+            data.bounds.aabb,
+            Aabb {
+                min: glm::vec3(0.0, 0.0, 0.0),
+                max: glm::vec3(3.0, 3.0, 0.0),
+            }
+        );
+        assert_eq!(
+            data.submeshes[0].bounds.aabb,
+            Aabb {
+                min: glm::vec3(0.0, 0.0, 0.0),
+                max: glm::vec3(1.0, 1.0, 0.0),
+            }
+        );
+        assert_eq!(data.submeshes[0].bounds.sphere.radius, 0.5f32.sqrt());
+    }
+}
@@ -0,0 +1,623 @@
+use std::{collections::HashMap, f32::consts::PI, ops::RangeInclusive};
+
+use super::{tangents, Bounds, Indices, MeshData, Submesh, Vertex, VertexAttribute, VertexLayout};
+use crate::AssetError;
+
+/// Ids starting with it name a generated mesh instead of a file, like `builtin://cube?size=2`
+pub const BUILTIN_SCHEME: &str = "builtin://";
+
+pub fn is_builtin(id: &str) -> bool {
+    id.starts_with(BUILTIN_SCHEME)
+}
+
+/// Generate the primitive an id names, with the parameters of its query
+///
+/// - `plane?size=1&subdivisions=1`, facing +y
+/// - `cube?size=1`
+/// - `uv_sphere?radius=0.5&segments=32&rings=16`
+/// - `icosphere?radius=0.5&subdivisions=2`
+/// - `cylinder?radius=0.5&height=1&segments=32`
+/// - `cone?radius=0.5&height=1&segments=32`
+/// - `capsule?radius=0.5&height=2&segments=32&rings=8`, the height including both caps
+/// - `torus?radius=0.5&tube_radius=0.2&segments=32&sides=16`
+///
+/// Every primitive is centered on the origin with y up
+pub fn generate(id: &str) -> Result<MeshData, AssetError> {
+    let (name, mut parameters) = Parameters::parse(id)?;
+
+    let mut builder = Builder::default();
+
+    match name {
+        "plane" => {
+            let size = parameters.float("size", 1.0)?;
+            let subdivisions = parameters.count("subdivisions", 1, 1..=1024)?;
+
+            builder.face(
+                &glm::vec3(0.0, 1.0, 0.0),
+                &glm::vec3(1.0, 0.0, 0.0),
+                &glm::vec3(0.0, 0.0, -1.0),
+                0.0,
+                size,
+                subdivisions,
+            );
+        }
+        "cube" => {
+            let size = parameters.float("size", 1.0)?;
+
+            let x = glm::vec3(1.0, 0.0, 0.0);
+            let y = glm::vec3(0.0, 1.0, 0.0);
+            let z = glm::vec3(0.0, 0.0, 1.0);
+
+            // Each face's right and up directions cross into its normal, so it winds outwards
+            for (normal, right, up) in [
+                (x, -z, y),
+                (-x, z, y),
+                (y, x, -z),
+                (-y, x, z),
+                (z, x, y),
+                (-z, -x, y),
+            ] {
+                builder.face(&normal, &right, &up, size / 2.0, size, 1);
+            }
+        }
+        "uv_sphere" => {
+            let radius = parameters.float("radius", 0.5)?;
+            let segments = parameters.count("segments", 32, 3..=1024)?;
+            let rings = parameters.count("rings", 16, 2..=1024)?;
+
+            let profile = arc(radius, 0.0, -PI / 2.0, PI / 2.0, rings);
+            builder.lathe(&profile, segments);
+        }
+        "icosphere" => {
+            let radius = parameters.float("radius", 0.5)?;
+            let subdivisions = parameters.count("subdivisions", 2, 0..=8)?;
+
+            builder.icosphere(radius, subdivisions);
+        }
+        "cylinder" => {
+            let radius = parameters.float("radius", 0.5)?;
+            let height = parameters.float("height", 1.0)?;
+            let segments = parameters.count("segments", 32, 3..=1024)?;
+
+            let side = glm::vec2(1.0, 0.0);
+            builder.lathe(
+                &[
+                    ProfilePoint::new(radius, -height / 2.0, side),
+                    ProfilePoint::new(radius, height / 2.0, side),
+                ],
+                segments,
+            );
+            builder.disc(radius, -height / 2.0, false, segments);
+            builder.disc(radius, height / 2.0, true, segments);
+        }
+        "cone" => {
+            let radius = parameters.float("radius", 0.5)?;
+            let height = parameters.float("height", 1.0)?;
+            let segments = parameters.count("segments", 32, 3..=1024)?;
+
+            let side = glm::vec2(height, radius).normalize();
+            builder.lathe(
+                &[
+                    ProfilePoint::new(radius, -height / 2.0, side),
+                    ProfilePoint::new(0.0, height / 2.0, side),
+                ],
+                segments,
+            );
+            builder.disc(radius, -height / 2.0, false, segments);
+        }
+        "capsule" => {
+            let radius = parameters.float("radius", 0.5)?;
+            let height = parameters.float("height", 2.0)?;
+            let segments = parameters.count("segments", 32, 3..=1024)?;
+            let rings = parameters.count("rings", 8, 1..=1024)?;
+
+            // A capsule shorter than its diameter is a sphere
+            let half_length = (height / 2.0 - radius).max(0.0);
+
+            let mut profile = arc(radius, -half_length, -PI / 2.0, 0.0, rings);
+            profile.extend(arc(radius, half_length, 0.0, PI / 2.0, rings));
+            builder.lathe(&profile, segments);
+        }
+        "torus" => {
+            let radius = parameters.float("radius", 0.5)?;
+            let tube_radius = parameters.float("tube_radius", 0.2)?;
+            let segments = parameters.count("segments", 32, 3..=1024)?;
+            let sides = parameters.count("sides", 16, 3..=1024)?;
+
+            builder.grid(segments, sides, |u, v| {
+                let (around, tube) = (u * 2.0 * PI, v * 2.0 * PI);
+                let ring = glm::vec3(around.cos(), 0.0, -around.sin());
+
+                let normal = ring * tube.cos() + glm::vec3(0.0, tube.sin(), 0.0);
+
+                (ring * radius + normal * tube_radius, normal)
+            });
+        }
+        _ => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+    }
+
+    parameters.finish(name)?;
+
+    Ok(builder.build())
+}
+
+/// The `key=value` pairs after the `?` of a builtin id
+struct Parameters {
+    values: HashMap<String, String>,
+}
+
+impl Parameters {
+    fn parse(id: &str) -> Result<(&str, Parameters), AssetError> {
+        let rest = match id.strip_prefix(BUILTIN_SCHEME) {
+            Some(rest) => rest,
+            None => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+        };
+
+        let (name, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let values = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) => Ok((key.to_owned(), value.to_owned())),
+                None => Err(AssetError::Parse(format!(
+                    "Parameter {} has no value",
+                    pair
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok((name, Parameters { values }))
+    }
+
+    fn float(&mut self, key: &str, default: f32) -> Result<f32, AssetError> {
+        let value = match self.values.remove(key) {
+            Some(value) => value,
+            None => return Ok(default),
+        };
+
+        match value.parse::<f32>() {
+            Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+            _ => Err(AssetError::Parse(format!(
+                "{} must be a positive number, not {}",
+                key, value
+            ))),
+        }
+    }
+
+    /// A number of steps, limited so a typo cannot take forever to generate
+    fn count(
+        &mut self,
+        key: &str,
+        default: u32,
+        range: RangeInclusive<u32>,
+    ) -> Result<u32, AssetError> {
+        let value = match self.values.remove(key) {
+            Some(value) => value,
+            None => return Ok(default),
+        };
+
+        match value.parse::<u32>() {
+            Ok(value) if range.contains(&value) => Ok(value),
+            _ => Err(AssetError::Parse(format!(
+                "{} must be a whole number from {} to {}, not {}",
+                key,
+                range.start(),
+                range.end(),
+                value
+            ))),
+        }
+    }
+
+    /// Fail on parameters the primitive does not have, rather than silently ignoring a typo
+    fn finish(self, name: &str) -> Result<(), AssetError> {
+        match self.values.keys().next() {
+            Some(key) => Err(AssetError::Parse(format!(
+                "Unknown parameter {} of {}",
+                key, name
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A point of the outline a `Builder::lathe` spins around the y axis
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    /// The normal's distance from the axis and its height
+    normal: glm::Vec2,
+}
+
+impl ProfilePoint {
+    fn new(radius: f32, y: f32, normal: glm::Vec2) -> ProfilePoint {
+        ProfilePoint { radius, y, normal }
+    }
+}
+
+/// Points along a circle arc in the plane of the y axis from the `from` to the `to` angle,
+/// the angles measured upwards from the horizontal
+fn arc(radius: f32, center_y: f32, from: f32, to: f32, steps: u32) -> Vec<ProfilePoint> {
+    (0..=steps)
+        .map(|step| {
+            let t = step as f32 / steps as f32;
+            let angle = from * (1.0 - t) + to * t;
+
+            // The poles are put exactly on the axis so the triangles meeting there are dropped
+            let cos = match angle.abs() == PI / 2.0 {
+                true => 0.0,
+                false => angle.cos(),
+            };
+
+            ProfilePoint::new(
+                radius * cos,
+                center_y + radius * angle.sin(),
+                glm::vec2(cos, angle.sin()),
+            )
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Builder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Builder {
+    fn vertex(&mut self, position: glm::Vec3, normal: glm::Vec3, uv: glm::Vec2) -> u32 {
+        self.vertices.push(Vertex {
+            position,
+            normal,
+            uv0: uv,
+            ..Default::default()
+        });
+
+        self.vertices.len() as u32 - 1
+    }
+
+    /// Add a triangle wound counter clockwise seen from the front, unless it has no area
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let [a_position, b_position, c_position] =
+            [a, b, c].map(|index| self.vertices[index as usize].position);
+
+        if a_position == b_position || b_position == c_position || c_position == a_position {
+            return;
+        }
+
+        self.indices.extend([a, b, c]);
+    }
+
+    /// A surface of `columns` by `rows` quads, `surface` mapping the u and v of each corner,
+    /// from 0 to 1, to its position and normal
+    ///
+    /// The derivatives of the position along u and then v have to cross into the normal
+    fn grid(
+        &mut self,
+        columns: u32,
+        rows: u32,
+        surface: impl Fn(f32, f32) -> (glm::Vec3, glm::Vec3),
+    ) {
+        let first = self.vertices.len() as u32;
+
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (u, v) = (column as f32 / columns as f32, row as f32 / rows as f32);
+                let (position, normal) = surface(u, v);
+
+                // Textures start at the top, so v runs against the surface's
+                self.vertex(position, normal, glm::vec2(u, 1.0 - v));
+            }
+        }
+
+        let corner = |column: u32, row: u32| first + row * (columns + 1) + column;
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let [a, b, c, d] = [
+                    corner(column, row),
+                    corner(column + 1, row),
+                    corner(column + 1, row + 1),
+                    corner(column, row + 1),
+                ];
+
+                self.triangle(a, b, c);
+                self.triangle(a, c, d);
+            }
+        }
+    }
+
+    /// A square of `size` facing `normal` at a distance from the origin,
+    /// `right` and `up` crossing into the normal
+    fn face(
+        &mut self,
+        normal: &glm::Vec3,
+        right: &glm::Vec3,
+        up: &glm::Vec3,
+        distance: f32,
+        size: f32,
+        subdivisions: u32,
+    ) {
+        self.grid(subdivisions, subdivisions, |u, v| {
+            let position =
+                normal * distance + (right * (2.0 * u - 1.0) + up * (2.0 * v - 1.0)) * size / 2.0;
+
+            (position, *normal)
+        });
+    }
+
+    /// Spin the profile, from bottom to top, around the y axis
+    fn lathe(&mut self, profile: &[ProfilePoint], segments: u32) {
+        self.grid(segments, profile.len() as u32 - 1, |u, v| {
+            let point = &profile[(v * (profile.len() - 1) as f32).round() as usize];
+            let angle = u * 2.0 * PI;
+            let direction = glm::vec3(angle.cos(), 0.0, -angle.sin());
+
+            (
+                direction * point.radius + glm::vec3(0.0, point.y, 0.0),
+                direction * point.normal.x + glm::vec3(0.0, point.normal.y, 0.0),
+            )
+        });
+    }
+
+    /// A flat cap at the height, facing up or down
+    fn disc(&mut self, radius: f32, y: f32, facing_up: bool, segments: u32) {
+        let normal = match facing_up {
+            true => glm::vec3(0.0, 1.0, 0.0),
+            false => glm::vec3(0.0, -1.0, 0.0),
+        };
+
+        let center = self.vertex(glm::vec3(0.0, y, 0.0), normal, glm::vec2(0.5, 0.5));
+
+        for segment in 0..=segments {
+            let angle = segment as f32 / segments as f32 * 2.0 * PI;
+            let direction = glm::vec3(angle.cos(), 0.0, -angle.sin());
+
+            // Seen from below the disc is mirrored, so its texture is too
+            let uv = glm::vec2(0.5 + direction.x * 0.5, 0.5 + direction.z * normal.y * 0.5);
+
+            self.vertex(direction * radius + glm::vec3(0.0, y, 0.0), normal, uv);
+        }
+
+        for segment in 0..segments {
+            let (a, b) = (center + 1 + segment, center + 2 + segment);
+
+            match facing_up {
+                true => self.triangle(center, a, b),
+                false => self.triangle(center, b, a),
+            }
+        }
+    }
+
+    /// An icosahedron with every triangle split in four `subdivisions` times, pushed out onto the sphere
+    fn icosphere(&mut self, radius: f32, subdivisions: u32) {
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+
+        let mut positions: Vec<glm::Vec3> = [
+            [-1.0, t, 0.0],
+            [1.0, t, 0.0],
+            [-1.0, -t, 0.0],
+            [1.0, -t, 0.0],
+            [0.0, -1.0, t],
+            [0.0, 1.0, t],
+            [0.0, -1.0, -t],
+            [0.0, 1.0, -t],
+            [t, 0.0, -1.0],
+            [t, 0.0, 1.0],
+            [-t, 0.0, -1.0],
+            [-t, 0.0, 1.0],
+        ]
+        .iter()
+        .map(|position| glm::Vec3::from(*position).normalize())
+        .collect();
+
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+
+            let mut midpoint = |a: u32, b: u32| -> u32 {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let position = (positions[a as usize] + positions[b as usize]).normalize();
+                    positions.push(position);
+                    positions.len() as u32 - 1
+                })
+            };
+
+            triangles = triangles
+                .iter()
+                .flat_map(|[a, b, c]| {
+                    let (ab, bc, ca) = (midpoint(*a, *b), midpoint(*b, *c), midpoint(*c, *a));
+
+                    [[*a, ab, ca], [*b, bc, ab], [*c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+
+        // Mapped like the uv sphere, u going around the y axis and v from pole to pole
+        let uv = |position: &glm::Vec3| {
+            let u = (-position.z).atan2(position.x) / (2.0 * PI);
+
+            glm::vec2(u.rem_euclid(1.0), 0.5 - position.y.asin() / PI)
+        };
+
+        // Triangles across the seam where u wraps around get copies of their vertices past 1
+        let mut vertices = HashMap::new();
+
+        for triangle in triangles {
+            let uvs = triangle.map(|index| uv(&positions[index as usize]));
+            let wraps = uvs.iter().any(|uv| uv.x > 0.75) && uvs.iter().any(|uv| uv.x < 0.25);
+
+            let corners = triangle.map(|index| {
+                let position = positions[index as usize];
+                let mut uv = uv(&position);
+                let wrapped = wraps && uv.x < 0.5;
+
+                if wrapped {
+                    uv.x += 1.0;
+                }
+
+                *vertices
+                    .entry((index, wrapped))
+                    .or_insert_with(|| self.vertex(position * radius, position, uv))
+            });
+
+            self.triangle(corners[0], corners[1], corners[2]);
+        }
+    }
+
+    fn build(mut self) -> MeshData {
+        tangents::generate_tangents(&mut self.vertices, &self.indices);
+
+        let submesh = Submesh {
+            first_index: 0,
+            index_count: self.indices.len() as u32,
+            first_vertex: 0,
+            vertex_count: self.vertices.len() as u32,
+            material_index: None,
+            bounds: Bounds::from_vertices(&self.vertices),
+        };
+
+        MeshData {
+            bounds: submesh.bounds,
+            vertices: self.vertices,
+            vertex_layout: VertexLayout::default().with(VertexAttribute::TexCoord0),
+            indices: Indices::from(self.indices),
+            submeshes: vec![submesh],
+            materials: vec![],
+            dependencies: vec![],
+            lods: vec![],
+            skeleton: None,
+            morph_targets: vec![],
+            morph_weights: vec![],
+            optimized: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMITIVES: [&str; 8] = [
+        "plane",
+        "cube",
+        "uv_sphere",
+        "icosphere",
+        "cylinder",
+        "cone",
+        "capsule",
+        "torus",
+    ];
+
+    fn triangles(data: &MeshData) -> Vec<[Vertex; 3]> {
+        let indices: Vec<u32> = data.indices.iter().collect();
+
+        indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| data.vertices[triangle[corner] as usize]))
+            .collect()
+    }
+
+    #[test]
+    fn test_primitives_face_outwards() {
+        for name in PRIMITIVES {
+            let data = generate(&format!("{}{}", BUILTIN_SCHEME, name)).unwrap();
+
+            assert!(!data.indices.is_empty(), "{} has no triangles", name);
+
+            for [a, b, c] in triangles(&data) {
+                let face_normal = (b.position - a.position).cross(&(c.position - a.position));
+
+                assert!(
+                    face_normal.norm() > 0.0,
+                    "{} has a degenerate triangle",
+                    name
+                );
+
+                // The winding agrees with the normals, which point away from the center
+                for vertex in [a, b, c] {
+                    assert!(
+                        face_normal.dot(&vertex.normal) > 0.0,
+                        "{} is wound against its normals",
+                        name
+                    );
+                    assert!((vertex.normal.norm() - 1.0).abs() < 1e-4);
+                }
+
+                // Apart from the torus' inner side every face looks away from the origin
+                if name != "torus" && name != "plane" {
+                    let center = (a.position + b.position + c.position) / 3.0;
+                    assert!(
+                        face_normal.dot(&center) > 0.0,
+                        "{} has an inwards face",
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parameters() {
+        let data = generate("builtin://cube?size=2").unwrap();
+        assert_eq!(data.bounds.aabb.min, glm::vec3(-1.0, -1.0, -1.0));
+        assert_eq!(data.bounds.aabb.max, glm::vec3(1.0, 1.0, 1.0));
+        assert_eq!(data.vertices.len(), 24);
+        assert_eq!(data.indices.len(), 36);
+
+        let data = generate("builtin://plane?size=4&subdivisions=2").unwrap();
+        assert_eq!(data.indices.len(), 2 * 2 * 6);
+        assert_eq!(data.bounds.aabb.max, glm::vec3(2.0, 0.0, 2.0));
+
+        let data = generate("builtin://uv_sphere?radius=2&segments=8&rings=4").unwrap();
+        for vertex in &data.vertices {
+            assert!((vertex.position.norm() - 2.0).abs() < 1e-5);
+        }
+
+        let data = generate("builtin://capsule?radius=0.5&height=3").unwrap();
+        assert!((data.bounds.aabb.max.y - 1.5).abs() < 1e-5);
+        assert!((data.bounds.aabb.max.x - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        assert_eq!(
+            generate("builtin://teapot").err(),
+            Some(AssetError::UnsupportedFormat("builtin://teapot".to_owned()))
+        );
+        assert!(matches!(
+            generate("builtin://cube?sise=2"),
+            Err(AssetError::Parse(_))
+        ));
+        assert!(matches!(
+            generate("builtin://cube?size=-1"),
+            Err(AssetError::Parse(_))
+        ));
+        assert!(matches!(
+            generate("builtin://uv_sphere?segments=2"),
+            Err(AssetError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,16 @@
+use super::Bounds;
+
+/// A range of a mesh's indices that is drawn with a single material,
+/// one for every primitive of the source file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Indices are relative to the first vertex of their submesh
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+    /// Index of the material in the source file, if the primitive has one
+    pub material_index: Option<usize>,
+    /// Kept after the vertices are freed
+    pub bounds: Bounds,
+}
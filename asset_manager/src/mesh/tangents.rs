@@ -0,0 +1,130 @@
+use super::Vertex;
+
+/// Fill in tangents for a triangle list whose source has none, following the direction of
+/// increasing u of the first texture coordinates
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut u_directions = vec![glm::Vec3::zeros(); vertices.len()];
+    let mut v_directions = vec![glm::Vec3::zeros(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [
+            triangle[0] as usize,
+            triangle[1] as usize,
+            triangle[2] as usize,
+        ];
+
+        if a >= vertices.len() || b >= vertices.len() || c >= vertices.len() {
+            continue;
+        }
+
+        let edge1 = vertices[b].position - vertices[a].position;
+        let edge2 = vertices[c].position - vertices[a].position;
+        let delta_uv1 = vertices[b].uv0 - vertices[a].uv0;
+        let delta_uv2 = vertices[c].uv0 - vertices[a].uv0;
+
+        let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+
+        // Triangles with degenerate texture coordinates say nothing about the tangent
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let u_direction = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) / determinant;
+        let v_direction = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) / determinant;
+
+        for index in [a, b, c] {
+            u_directions[index] += u_direction;
+            v_directions[index] += v_direction;
+        }
+    }
+
+    for (index, vertex) in vertices.iter_mut().enumerate() {
+        let normal = vertex.normal;
+
+        if normal.norm_squared() < f32::EPSILON {
+            continue;
+        }
+
+        // Gram-Schmidt orthogonalize against the normal
+        let mut tangent = u_directions[index] - normal * normal.dot(&u_directions[index]);
+
+        if tangent.norm_squared() < f32::EPSILON {
+            tangent = perpendicular(&normal);
+        }
+
+        let tangent = tangent.normalize();
+
+        let handedness = if normal.cross(&tangent).dot(&v_directions[index]) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        vertex.tangent = tangent.push(handedness);
+    }
+}
+
+/// Any direction perpendicular to the given one
+fn perpendicular(direction: &glm::Vec3) -> glm::Vec3 {
+    let axis = if direction.x.abs() < 0.9 {
+        glm::vec3(1.0, 0.0, 0.0)
+    } else {
+        glm::vec3(0.0, 1.0, 0.0)
+    };
+
+    direction.cross(&axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(uvs: [[f32; 2]; 4]) -> Vec<Vertex> {
+        let positions = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+        positions
+            .iter()
+            .zip(uvs)
+            .map(|(position, uv)| Vertex {
+                position: glm::vec3(position[0], position[1], 0.0),
+                normal: glm::vec3(0.0, 0.0, 1.0),
+                uv0: glm::Vec2::from(uv),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tangents_follow_u() {
+        let mut vertices = quad([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+
+        generate_tangents(&mut vertices, &[0, 1, 2, 0, 2, 3]);
+
+        for vertex in vertices {
+            assert_eq!(vertex.tangent, glm::vec4(1.0, 0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_mirrored_uvs_flip_handedness() {
+        let mut vertices = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+
+        generate_tangents(&mut vertices, &[0, 1, 2, 0, 2, 3]);
+
+        for vertex in vertices {
+            assert_eq!(vertex.tangent, glm::vec4(1.0, 0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn test_without_uvs_tangent_is_perpendicular() {
+        let mut vertices = quad([[0.0, 0.0]; 4]);
+
+        generate_tangents(&mut vertices, &[0, 1, 2, 0, 2, 3]);
+
+        for vertex in vertices {
+            assert!(vertex.tangent.xyz().dot(&vertex.normal).abs() < 1e-6);
+            assert!((vertex.tangent.xyz().norm() - 1.0).abs() < 1e-6);
+        }
+    }
+}
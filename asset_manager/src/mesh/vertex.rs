@@ -1,10 +1,175 @@
-// TODO: remove the alignment help and when doing upload,
-//       have the gpu build the format that it is looking for,
-//       less knowing about other places' stuff
-#[repr(C, align(16))]
-#[derive(Clone, Copy)]
+/// Every attribute a mesh vertex can have, with defaults filled in for those the source lacks
+/// The GPU side layout only has the attributes the source has, see `VertexLayout`
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vertex {
     pub position: glm::Vec3,
     pub normal: glm::Vec3,
-    pub color: glm::Vec3,
+    /// xyz is the tangent, w the handedness of the bitangent
+    pub tangent: glm::Vec4,
+    pub uv0: glm::Vec2,
+    pub uv1: glm::Vec2,
+    pub color: glm::Vec4,
+    /// Indices into the skeleton's joints, stored as floats like every other attribute
+    pub joints: glm::Vec4,
+    /// How much each of `joints` moves the vertex, all zero for vertices that are not skinned
+    pub weights: glm::Vec4,
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Vertex {
+            position: glm::Vec3::zeros(),
+            normal: glm::Vec3::zeros(),
+            tangent: glm::vec4(1.0, 0.0, 0.0, 1.0),
+            uv0: glm::Vec2::zeros(),
+            uv1: glm::Vec2::zeros(),
+            color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            joints: glm::Vec4::zeros(),
+            weights: glm::Vec4::zeros(),
+        }
+    }
+}
+
+/// A single attribute of a vertex, as uploaded to the GPU
+///
+/// Each attribute is uploaded as its own tightly packed stream of 32 bit floats,
+/// so a material only has to bind the attributes its shaders use
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexAttribute {
+    Position,
+    Normal,
+    Tangent,
+    TexCoord0,
+    TexCoord1,
+    Color,
+    Joints,
+    Weights,
+}
+
+impl VertexAttribute {
+    /// Every attribute, in the order their streams are laid out in a mesh's vertex buffer
+    pub const ALL: [VertexAttribute; 8] = [
+        VertexAttribute::Position,
+        VertexAttribute::Normal,
+        VertexAttribute::Tangent,
+        VertexAttribute::TexCoord0,
+        VertexAttribute::TexCoord1,
+        VertexAttribute::Color,
+        VertexAttribute::Joints,
+        VertexAttribute::Weights,
+    ];
+
+    pub fn component_count(&self) -> usize {
+        match self {
+            VertexAttribute::Position | VertexAttribute::Normal => 3,
+            VertexAttribute::TexCoord0 | VertexAttribute::TexCoord1 => 2,
+            VertexAttribute::Tangent
+            | VertexAttribute::Color
+            | VertexAttribute::Joints
+            | VertexAttribute::Weights => 4,
+        }
+    }
+
+    /// Size of a single value of the attribute in bytes
+    pub fn size(&self) -> usize {
+        self.component_count() * std::mem::size_of::<f32>()
+    }
+
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+
+    pub fn read<'a>(&self, vertex: &'a Vertex) -> &'a [f32] {
+        match self {
+            VertexAttribute::Position => vertex.position.as_slice(),
+            VertexAttribute::Normal => vertex.normal.as_slice(),
+            VertexAttribute::Tangent => vertex.tangent.as_slice(),
+            VertexAttribute::TexCoord0 => vertex.uv0.as_slice(),
+            VertexAttribute::TexCoord1 => vertex.uv1.as_slice(),
+            VertexAttribute::Color => vertex.color.as_slice(),
+            VertexAttribute::Joints => vertex.joints.as_slice(),
+            VertexAttribute::Weights => vertex.weights.as_slice(),
+        }
+    }
+
+    pub fn read_mut<'a>(&self, vertex: &'a mut Vertex) -> &'a mut [f32] {
+        match self {
+            VertexAttribute::Position => vertex.position.as_mut_slice(),
+            VertexAttribute::Normal => vertex.normal.as_mut_slice(),
+            VertexAttribute::Tangent => vertex.tangent.as_mut_slice(),
+            VertexAttribute::TexCoord0 => vertex.uv0.as_mut_slice(),
+            VertexAttribute::TexCoord1 => vertex.uv1.as_mut_slice(),
+            VertexAttribute::Color => vertex.color.as_mut_slice(),
+            VertexAttribute::Joints => vertex.joints.as_mut_slice(),
+            VertexAttribute::Weights => vertex.weights.as_mut_slice(),
+        }
+    }
+}
+
+/// The attributes a mesh's vertices really have, which are the only ones uploaded,
+/// see `Mesh::vertex_data`
+///
+/// Position, normal and tangent are always there, as they are generated when the source lacks them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    bits: u8,
+}
+
+impl VertexLayout {
+    /// Every attribute there is, a bit for each of the eight of `VertexAttribute::ALL`
+    pub const ALL: VertexLayout = VertexLayout { bits: u8::MAX };
+
+    pub fn new(attributes: &[VertexAttribute]) -> VertexLayout {
+        attributes
+            .iter()
+            .fold(VertexLayout { bits: 0 }, |layout, attribute| {
+                layout.with(*attribute)
+            })
+    }
+
+    pub fn with(self, attribute: VertexAttribute) -> VertexLayout {
+        VertexLayout {
+            bits: self.bits | attribute.bit(),
+        }
+    }
+
+    pub fn union(self, other: VertexLayout) -> VertexLayout {
+        VertexLayout {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    pub fn contains(&self, attribute: VertexAttribute) -> bool {
+        self.bits & attribute.bit() != 0
+    }
+
+    /// The attributes of the layout, in the order of `VertexAttribute::ALL`
+    pub fn attributes(&self) -> impl Iterator<Item = VertexAttribute> + '_ {
+        VertexAttribute::ALL
+            .into_iter()
+            .filter(|attribute| self.contains(*attribute))
+    }
+
+    /// Size of every attribute of a single vertex in bytes
+    pub fn vertex_size(&self) -> usize {
+        self.attributes().map(|attribute| attribute.size()).sum()
+    }
+
+    pub(crate) fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    pub(crate) fn from_bits(bits: u8) -> VertexLayout {
+        VertexLayout { bits }
+    }
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        VertexLayout::new(&[
+            VertexAttribute::Position,
+            VertexAttribute::Normal,
+            VertexAttribute::Tangent,
+        ])
+    }
 }
@@ -0,0 +1,170 @@
+use serde_derive::Deserialize;
+
+use crate::{gltf_file, is_builtin, vfs::Vfs, AssetError, LodSettings, MeshOptimizations};
+
+/// Import settings of an asset, read from an optional TOML file next to the file it is loaded
+/// from, named after it with `.meta` appended like `monkey.glb.meta`
+///
+/// Every asset read from the same file shares its meta file, a glTF file's images, skins
+/// and animations included. Only the section matching the asset's type is used
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetMeta {
+    pub mesh: MeshMeta,
+    pub texture: TextureMeta,
+    pub sound: SoundMeta,
+}
+
+/// The axis pointing up in a source file
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpAxis {
+    /// The engine's own convention, nothing is converted
+    #[default]
+    Y,
+    /// Used by Blender and most CAD tools, rotated to Y up on import
+    Z,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshMeta {
+    /// Uniform scale applied to every vertex
+    pub scale: f32,
+    pub up_axis: UpAxis,
+    /// Generate normals and tangents from the triangles instead of using those of the source file
+    pub regenerate_normals: bool,
+    /// Passes run on the mesh instead of the configured ones
+    pub optimizations: Option<MeshOptimizations>,
+    /// Levels of detail generated for the mesh instead of the configured ones
+    pub lods: Option<LodSettings>,
+}
+
+impl Default for MeshMeta {
+    fn default() -> Self {
+        MeshMeta {
+            scale: 1.0,
+            up_axis: UpAxis::Y,
+            regenerate_normals: false,
+            optimizations: None,
+            lods: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextureMeta {
+    /// Whether the pixels are sRGB encoded colors, or linear data like normal and roughness maps
+    pub srgb: bool,
+    /// Generate every mip level down to 1x1 when the texture is loaded
+    pub generate_mips: bool,
+}
+
+impl Default for TextureMeta {
+    fn default() -> Self {
+        TextureMeta {
+            srgb: true,
+            generate_mips: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoundMeta {
+    /// Always stream the sound or always decode it up front,
+    /// by default only sounds longer than `LoadContext::sound_streaming_threshold` are streamed
+    pub stream: Option<bool>,
+}
+
+impl AssetMeta {
+    /// Read the meta file of an asset, the defaults are used when there is none
+    pub fn read(id: &str, vfs: &Vfs) -> Result<AssetMeta, AssetError> {
+        let contents = contents(id, vfs)?;
+
+        let contents = match std::str::from_utf8(&contents) {
+            Ok(contents) => contents,
+            Err(e) => return Err(AssetError::Parse(e.to_string())),
+        };
+
+        match toml::from_str(contents) {
+            Ok(meta) => Ok(meta),
+            Err(e) => Err(AssetError::Parse(format!(
+                "Invalid meta file of {}: {}",
+                id, e
+            ))),
+        }
+    }
+}
+
+/// The meta file of an asset, builtin assets have no file so they have no meta file either
+pub fn meta_path(id: &str) -> Option<String> {
+    match is_builtin(id) {
+        true => None,
+        false => Some(format!("{}.meta", gltf_file::source_file(id))),
+    }
+}
+
+/// The raw contents of the meta file of an asset, empty when there is none
+pub fn contents(id: &str, vfs: &Vfs) -> Result<Vec<u8>, AssetError> {
+    match meta_path(id) {
+        Some(path) if vfs.exists(&path) => vfs.read(&path),
+        _ => Ok(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_path() {
+        assert_eq!(
+            meta_path("models/monkey.glb").as_deref(),
+            Some("models/monkey.glb.meta")
+        );
+        assert_eq!(
+            meta_path("models/bottle.gltf#0").as_deref(),
+            Some("models/bottle.gltf.meta")
+        );
+        assert_eq!(meta_path("builtin://cube"), None);
+    }
+
+    #[test]
+    fn test_read() {
+        let meta = AssetMeta::read("fixtures/z_up.obj", &Vfs::working_directory()).unwrap();
+
+        assert_eq!(meta.mesh.scale, 2.0);
+        assert_eq!(meta.mesh.up_axis, UpAxis::Z);
+        assert_eq!(meta.mesh.lods.unwrap().levels, 2);
+        assert_eq!(meta.mesh.lods.unwrap().reduction, 0.5);
+        assert!(meta.texture.srgb);
+
+        // Assets without a meta file get the defaults
+        assert_eq!(
+            AssetMeta::read("fixtures/nested_nodes.gltf", &Vfs::working_directory()).unwrap(),
+            AssetMeta::default()
+        );
+    }
+
+    #[test]
+    fn test_read_unknown_setting() {
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("", std::env::temp_dir());
+
+        let path = format!("meta_unknown_setting_{}.obj", std::process::id());
+        std::fs::write(
+            std::env::temp_dir().join(path.clone() + ".meta"),
+            "[mesh]\nscael = 2.0\n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            AssetMeta::read(&path, &vfs),
+            Err(AssetError::Parse(_))
+        ));
+
+        std::fs::remove_file(std::env::temp_dir().join(path + ".meta")).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use log::warn;
+
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    gltf_file,
+    loader::{LoadContext, LoadableAsset},
+    material, mesh,
+    skeleton::{self, JointTransform},
+    AssetError, Dependency,
+};
+
+/// The array of a glTF file scenes are read from, see `gltf_file::fragment_id`
+const KIND: &str = "scenes";
+
+/// A node of a scene, placed relative to its parent
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneNode {
+    pub name: Option<String>,
+    /// Index of the node in the glTF file, which is what animation channels target
+    pub node: usize,
+    /// Index of the parent in `Scene::nodes`, parents always come before their children
+    pub parent: Option<usize>,
+    pub transform: JointTransform,
+    /// Id of the node's mesh, read without any node transform, see `AssetManager::get_mesh`
+    pub mesh: Option<String>,
+    /// Id of the material of every submesh of the mesh, None for those without one
+    pub materials: Vec<Option<String>>,
+    /// Id of the skeleton deforming the mesh, for skinned nodes
+    pub skeleton: Option<String>,
+}
+
+/// The node hierarchy of a glTF scene, which raindrop's `SceneSpawner` turns into entities
+///
+/// Scenes are referenced as `<path>#scenes/<index or name>`, for example `level.glb#scenes/0`.
+/// Anything that parses as a number is taken as an index
+pub struct Scene {
+    pub asset_info: AssetInfo,
+    pub nodes: Vec<SceneNode>,
+}
+
+impl Scene {
+    pub fn unload(&mut self) {
+        self.nodes = vec![];
+        self.asset_info.status = AssetStatus::Unloaded;
+    }
+
+    fn read_node(path: &str, node: &gltf::Node, parent: Option<usize>, nodes: &mut Vec<SceneNode>) {
+        let (translation, rotation, scale) = node.transform().decomposed();
+
+        // Submeshes are only made of triangle primitives, the materials have to line up with them
+        let materials = match node.mesh() {
+            Some(mesh) => mesh
+                .primitives()
+                .filter(|primitive| primitive.mode() == gltf::mesh::Mode::Triangles)
+                .map(|primitive| {
+                    primitive
+                        .material()
+                        .index()
+                        .map(|index| material::material_id(path, index))
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        nodes.push(SceneNode {
+            name: node.name().map(str::to_owned),
+            node: node.index(),
+            parent,
+            transform: JointTransform {
+                translation: glm::Vec3::from(translation),
+                rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                scale: glm::Vec3::from(scale),
+            },
+            mesh: node.mesh().map(|mesh| mesh::mesh_id(path, mesh.index())),
+            materials,
+            skeleton: node
+                .skin()
+                .map(|skin| skeleton::skeleton_id(path, skin.index())),
+        });
+
+        let index = nodes.len() - 1;
+
+        for child in node.children() {
+            Scene::read_node(path, &child, Some(index), nodes);
+        }
+    }
+}
+
+impl LoadableAsset for Scene {
+    type Data = Vec<SceneNode>;
+
+    fn read(id: &str, context: &LoadContext) -> Result<Vec<SceneNode>, AssetError> {
+        let (path, name) = match gltf_file::split_fragment_id(id, KIND) {
+            Some((path, name)) => (path, name),
+            None => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+        };
+
+        // The hierarchy is only made of JSON, the meshes read the buffers themselves
+        let document = gltf::Gltf::from_slice(&context.vfs.read(path)?)?.document;
+
+        let scene = match name.parse::<usize>() {
+            Ok(index) => document.scenes().nth(index),
+            Err(_) => document.scenes().find(|scene| scene.name() == Some(name)),
+        };
+
+        let scene = match scene {
+            Some(scene) => scene,
+            None => return Err(AssetError::Parse(format!("{} has no scene {}", path, name))),
+        };
+
+        let mut nodes = vec![];
+
+        for node in scene.nodes() {
+            Scene::read_node(path, &node, None, &mut nodes);
+        }
+
+        Ok(nodes)
+    }
+
+    fn publish(&mut self, nodes: Result<Vec<SceneNode>, AssetError>) {
+        match nodes {
+            Ok(nodes) => {
+                self.nodes = nodes;
+                self.asset_info.set_loaded();
+            }
+            Err(e) => {
+                warn!("Failed to load scene {}: {}", self.asset_info.id, e);
+
+                self.asset_info.set_failed(e);
+            }
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    /// The meshes, materials and skeletons the nodes use
+    fn dependencies(nodes: &Vec<SceneNode>) -> Vec<Dependency> {
+        let mut dependencies: Vec<Dependency> = vec![];
+
+        for node in nodes {
+            let ids = node
+                .mesh
+                .iter()
+                .chain(node.materials.iter().flatten())
+                .chain(node.skeleton.iter());
+
+            for id in ids {
+                let dependency = Dependency::Asset(id.clone());
+
+                if !dependencies.contains(&dependency) {
+                    dependencies.push(dependency);
+                }
+            }
+        }
+
+        dependencies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_hierarchy() {
+        let nodes = Scene::read(
+            "fixtures/nested_nodes.gltf#scenes/0",
+            &LoadContext::default(),
+        )
+        .unwrap();
+
+        let names: Vec<Option<&str>> = nodes.iter().map(|node| node.name.as_deref()).collect();
+        assert_eq!(
+            names,
+            vec![
+                Some("parent"),
+                Some("child"),
+                Some("camera"),
+                Some("two primitives")
+            ]
+        );
+
+        // Children are placed relative to their parent, not to the scene
+        let child = &nodes[1];
+        assert_eq!(child.parent, Some(0));
+        assert_eq!(child.transform.translation, glm::vec3(0.0, 5.0, 0.0));
+        assert_eq!(
+            child.mesh.as_deref(),
+            Some("fixtures/nested_nodes.gltf#meshes/0")
+        );
+
+        assert_eq!(nodes[2].mesh, None);
+        assert_eq!(
+            nodes[3].materials,
+            vec![
+                Some("fixtures/nested_nodes.gltf#materials/1".to_owned()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let nodes = Scene::read(
+            "fixtures/nested_nodes.gltf#scenes/0",
+            &LoadContext::default(),
+        )
+        .unwrap();
+
+        let dependencies = Scene::dependencies(&nodes);
+
+        assert_eq!(dependencies.len(), 4);
+        assert!(dependencies.contains(&Dependency::Asset(
+            "fixtures/nested_nodes.gltf#materials/0".to_owned()
+        )));
+    }
+
+    #[test]
+    fn test_read_missing_scene() {
+        assert!(Scene::read(
+            "fixtures/nested_nodes.gltf#scenes/1",
+            &LoadContext::default()
+        )
+        .is_err());
+        assert!(Scene::read("fixtures/nested_nodes.gltf", &LoadContext::default()).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use log::warn;
+
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    gltf_file::{self, GltfFile},
+    loader::{LoadContext, LoadableAsset},
+    AssetError,
+};
+
+/// The array of a glTF file skeletons are read from, see `gltf_file::fragment_id`
+const KIND: &str = "skins";
+
+/// Translation, rotation and scale of a joint relative to its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointTransform {
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+}
+
+impl JointTransform {
+    pub fn matrix(&self) -> glm::Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&self.rotation)
+            * glm::scaling(&self.scale)
+    }
+}
+
+impl Default for JointTransform {
+    fn default() -> Self {
+        JointTransform {
+            translation: glm::Vec3::zeros(),
+            rotation: glm::quat_identity(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Joint {
+    pub name: Option<String>,
+    /// Index of the joint's node in the glTF file, which is what animation channels target
+    pub node: usize,
+    /// The closest ancestor that is a joint as well
+    pub parent: Option<usize>,
+    /// Transform of the nodes between the joint and its parent joint, or the scene root
+    pub offset: glm::Mat4,
+    /// The transform of the joint's node, used for anything an animation leaves alone
+    pub rest: JointTransform,
+    /// Moves a skinned vertex from the space of the mesh into that of the joint at bind time
+    pub inverse_bind_matrix: glm::Mat4,
+}
+
+/// The joint hierarchy of a glTF skin, that skinned meshes are deformed by
+///
+/// Skins are referenced as `<path>#skins/<skin index>`, for example `robot.glb#skins/0`,
+/// which is the id `Mesh::skeleton` holds for skinned meshes
+pub struct Skeleton {
+    pub asset_info: AssetInfo,
+    /// Indexed by the joints of `Vertex::joints`
+    pub joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn unload(&mut self) {
+        self.joints = vec![];
+        self.asset_info.status = AssetStatus::Unloaded;
+    }
+
+    /// Index of the joint of a node, as targeted by an animation channel
+    pub fn joint_index(&self, node: usize) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.node == node)
+    }
+
+    /// The transform of every joint at rest, a pose to start sampling animations into
+    pub fn rest_pose(&self) -> Vec<JointTransform> {
+        self.joints.iter().map(|joint| joint.rest).collect()
+    }
+
+    /// The transform of every joint relative to the scene root, `pose` holding one for each joint
+    pub fn global_transforms(&self, pose: &[JointTransform]) -> Vec<glm::Mat4> {
+        let mut transforms = vec![None; self.joints.len()];
+
+        (0..self.joints.len())
+            .map(|joint| self.global_transform(joint, pose, &mut transforms))
+            .collect()
+    }
+
+    /// The matrices moving skinned vertices from their bind position into the pose,
+    /// which a vertex is transformed by weighted by `Vertex::weights`
+    pub fn joint_matrices(&self, pose: &[JointTransform]) -> Vec<glm::Mat4> {
+        self.global_transforms(pose)
+            .iter()
+            .zip(&self.joints)
+            .map(|(transform, joint)| transform * joint.inverse_bind_matrix)
+            .collect()
+    }
+
+    /// Joints are not ordered parents first, so the transforms of parents are filled in as needed
+    fn global_transform(
+        &self,
+        joint: usize,
+        pose: &[JointTransform],
+        transforms: &mut [Option<glm::Mat4>],
+    ) -> glm::Mat4 {
+        if let Some(transform) = transforms[joint] {
+            return transform;
+        }
+
+        let local = self.joints[joint].offset * pose[joint].matrix();
+
+        let transform = match self.joints[joint].parent {
+            Some(parent) => self.global_transform(parent, pose, transforms) * local,
+            None => local,
+        };
+
+        transforms[joint] = Some(transform);
+
+        transform
+    }
+
+    fn read_skin(path: &str, index: usize, gltf: &GltfFile) -> Result<Vec<Joint>, AssetError> {
+        let document = &gltf.document;
+
+        let skin = match document.skins().nth(index) {
+            Some(skin) => skin,
+            None => return Err(AssetError::Parse(format!("{} has no skin {}", path, index))),
+        };
+
+        let mut parents = vec![None; document.nodes().len()];
+
+        for node in document.nodes() {
+            for child in node.children() {
+                parents[child.index()] = Some(node.index());
+            }
+        }
+
+        let node_transforms: Vec<glm::Mat4> = document
+            .nodes()
+            .map(|node| glm::Mat4::from(node.transform().matrix()))
+            .collect();
+
+        let joint_indices: HashMap<usize, usize> = skin
+            .joints()
+            .enumerate()
+            .map(|(joint, node)| (node.index(), joint))
+            .collect();
+
+        // Skins without inverse bind matrices are bound in the pose of the scene
+        let mut inverse_bind_matrices = skin
+            .reader(|buffer| Some(&gltf.buffers[buffer.index()]))
+            .read_inverse_bind_matrices()
+            .into_iter()
+            .flatten()
+            .map(glm::Mat4::from);
+
+        let joints = skin
+            .joints()
+            .map(|node| {
+                let mut offset = glm::Mat4::identity();
+                let mut ancestor = parents[node.index()];
+
+                // Nodes in between joints are not animated, their transforms are folded into the offset
+                let parent = loop {
+                    match ancestor {
+                        Some(ancestor) if joint_indices.contains_key(&ancestor) => {
+                            break Some(joint_indices[&ancestor]);
+                        }
+                        Some(node) => {
+                            offset = node_transforms[node] * offset;
+                            ancestor = parents[node];
+                        }
+                        None => break None,
+                    }
+                };
+
+                let (translation, rotation, scale) = node.transform().decomposed();
+
+                Joint {
+                    name: node.name().map(str::to_owned),
+                    node: node.index(),
+                    parent,
+                    offset,
+                    rest: JointTransform {
+                        translation: glm::Vec3::from(translation),
+                        rotation: glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]),
+                        scale: glm::Vec3::from(scale),
+                    },
+                    inverse_bind_matrix: inverse_bind_matrices
+                        .next()
+                        .unwrap_or_else(glm::Mat4::identity),
+                }
+            })
+            .collect();
+
+        Ok(joints)
+    }
+}
+
+/// Id of the skeleton of a skin of the glTF file at `path`
+pub(crate) fn skeleton_id(path: &str, skin: usize) -> String {
+    gltf_file::fragment_id(path, KIND, skin)
+}
+
+impl LoadableAsset for Skeleton {
+    type Data = Vec<Joint>;
+
+    fn read(id: &str, context: &LoadContext) -> Result<Vec<Joint>, AssetError> {
+        let (path, index) = match gltf_file::split_fragment_id(id, KIND) {
+            Some((path, index)) => (path, index),
+            None => return Err(AssetError::UnsupportedFormat(id.to_owned())),
+        };
+
+        let index = match index.parse() {
+            Ok(index) => index,
+            Err(_) => return Err(AssetError::Parse(format!("Invalid skin index {}", index))),
+        };
+
+        Skeleton::read_skin(path, index, &GltfFile::open(path, &context.vfs)?)
+    }
+
+    fn publish(&mut self, joints: Result<Vec<Joint>, AssetError>) {
+        match joints {
+            Ok(joints) => {
+                self.joints = joints;
+                self.asset_info.set_loaded();
+            }
+            Err(e) => {
+                warn!("Failed to load skeleton {}: {}", self.asset_info.id, e);
+
+                self.asset_info.set_failed(e);
+            }
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_fixture() -> Vec<Joint> {
+        Skeleton::read("fixtures/skinned.gltf#skins/0", &LoadContext::default()).unwrap()
+    }
+
+    #[test]
+    fn test_read_hierarchy() {
+        let joints = read_fixture();
+
+        assert_eq!(joints.len(), 2);
+        assert_eq!(joints[0].name.as_deref(), Some("hip"));
+        assert_eq!(joints[0].parent, None);
+        assert_eq!(joints[1].parent, Some(0));
+
+        // The root node above the hip is not a joint, so it is folded into the hip's offset
+        assert_eq!(
+            joints[0].offset,
+            glm::translation(&glm::vec3(0.0, 1.0, 0.0))
+        );
+        assert_eq!(joints[1].offset, glm::Mat4::identity());
+        assert_eq!(joints[1].rest.translation, glm::vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_rest_pose_matrices() {
+        let skeleton = Skeleton {
+            asset_info: AssetInfo::new("fixtures/skinned.gltf#skins/0"),
+            joints: read_fixture(),
+        };
+
+        let pose = skeleton.rest_pose();
+
+        assert_eq!(
+            skeleton.global_transforms(&pose)[1],
+            glm::translation(&glm::vec3(0.0, 2.0, 0.0))
+        );
+
+        // At rest the skin is in its bind pose, so vertices stay where they are
+        for matrix in skeleton.joint_matrices(&pose) {
+            assert_eq!(matrix, glm::Mat4::identity());
+        }
+    }
+
+    #[test]
+    fn test_read_missing_skin() {
+        assert!(Skeleton::read("fixtures/skinned.gltf#skins/1", &LoadContext::default()).is_err());
+        assert!(
+            Skeleton::read("fixtures/skinned.gltf#skins/hip", &LoadContext::default()).is_err()
+        );
+    }
+}
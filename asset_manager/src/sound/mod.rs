@@ -1,40 +1,382 @@
-use std::{fs::File, io::BufReader};
+use std::{sync::Arc, time::Duration};
 
-use log::warn;
-use rodio::Decoder;
+use log::{info, warn};
+use rodio::{Decoder, Source};
 
-use crate::asset_info::{AssetInfo, AssetStatus};
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    loader::{LoadContext, LoadableAsset},
+    meta::AssetMeta,
+    vfs::{Vfs, VfsReader},
+    AssetError,
+};
 
+/// A decoder reading a sound file as it plays
+type StreamDecoder = Decoder<Box<dyn VfsReader>>;
+
+/// The samples of a sound, shared by every instance playing it
+#[derive(Clone)]
+pub enum SoundData {
+    /// Short sounds are decoded once up front
+    Buffered {
+        /// Interleaved 16 bit samples of every channel
+        samples: Arc<[i16]>,
+        channels: u16,
+        sample_rate: u32,
+    },
+    /// Long sounds are decoded while they play, every playback reads their file on its own
+    Streamed {
+        vfs: Vfs,
+        path: String,
+        channels: u16,
+        sample_rate: u32,
+        /// Not every format tells the length of a sound without decoding all of it
+        duration: Option<Duration>,
+    },
+}
+
+impl SoundData {
+    pub fn channels(&self) -> u16 {
+        match self {
+            SoundData::Buffered { channels, .. } | SoundData::Streamed { channels, .. } => {
+                *channels
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        match self {
+            SoundData::Buffered { sample_rate, .. } | SoundData::Streamed { sample_rate, .. } => {
+                *sample_rate
+            }
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            SoundData::Buffered {
+                samples,
+                channels,
+                sample_rate,
+            } => Some(Duration::from_secs_f64(
+                samples.len() as f64 / *channels as f64 / *sample_rate as f64,
+            )),
+            SoundData::Streamed { duration, .. } => *duration,
+        }
+    }
+
+    /// Start a new playback of the samples, independent of any other playing them
+    pub fn play(&self) -> Result<SoundInstance, AssetError> {
+        match self {
+            SoundData::Buffered {
+                samples,
+                channels,
+                sample_rate,
+            } => Ok(SoundInstance::Buffered {
+                samples: samples.clone(),
+                position: 0,
+                channels: *channels,
+                sample_rate: *sample_rate,
+            }),
+            SoundData::Streamed { vfs, path, .. } => {
+                Ok(SoundInstance::Streamed(Box::new(decode(vfs.open(path)?)?)))
+            }
+        }
+    }
+}
+
+/// A single playback of a sound, handed to a rodio `Sink` or `OutputStreamHandle` to be heard
+pub enum SoundInstance {
+    Buffered {
+        samples: Arc<[i16]>,
+        position: usize,
+        channels: u16,
+        sample_rate: u32,
+    },
+    Streamed(Box<StreamDecoder>),
+}
+
+impl Iterator for SoundInstance {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        match self {
+            SoundInstance::Buffered {
+                samples, position, ..
+            } => {
+                let sample = samples.get(*position).copied();
+                *position += 1;
+
+                sample
+            }
+            SoundInstance::Streamed(decoder) => decoder.next(),
+        }
+    }
+}
+
+impl Source for SoundInstance {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self {
+            SoundInstance::Buffered {
+                samples, position, ..
+            } => Some(samples.len().saturating_sub(*position)),
+            SoundInstance::Streamed(decoder) => decoder.current_frame_len(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self {
+            SoundInstance::Buffered { channels, .. } => *channels,
+            SoundInstance::Streamed(decoder) => decoder.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            SoundInstance::Buffered { sample_rate, .. } => *sample_rate,
+            SoundInstance::Streamed(decoder) => decoder.sample_rate(),
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self {
+            SoundInstance::Buffered {
+                samples,
+                channels,
+                sample_rate,
+                ..
+            } => Some(Duration::from_secs_f64(
+                samples.len() as f64 / *channels as f64 / *sample_rate as f64,
+            )),
+            SoundInstance::Streamed(decoder) => decoder.total_duration(),
+        }
+    }
+}
+
+fn decode(reader: Box<dyn VfsReader>) -> Result<StreamDecoder, AssetError> {
+    match Decoder::new(reader) {
+        Ok(decoder) => Ok(decoder),
+        Err(e) => Err(AssetError::Parse(e.to_string())),
+    }
+}
+
+/// A WAV, OGG Vorbis, FLAC or MP3 sound, the format is found from the contents
+///
+/// Sounds up to `LoadContext::sound_streaming_threshold` long are decoded when loaded,
+/// longer ones like music are decoded as they play, unless their meta file says otherwise
 pub struct Sound {
     pub asset_info: AssetInfo,
-    pub source: Option<Decoder<BufReader<File>>>,
+    pub data: Option<SoundData>,
 }
 
 impl Sound {
-    pub fn load(&mut self) {
-        let file = File::open(&self.asset_info.id);
+    pub fn unload(&mut self) {
+        self.data = None;
+        self.asset_info.status = AssetStatus::Unloaded;
+    }
 
-        if file.is_err() {
-            self.asset_info.status = AssetStatus::Invalid;
+    /// Start a new playback of the sound, any number of them can play at once
+    /// Returns `None` while the sound is not loaded
+    pub fn play(&self) -> Option<SoundInstance> {
+        match self.data.as_ref()?.play() {
+            Ok(instance) => Some(instance),
+            Err(e) => {
+                warn!("Failed to play sound {}: {}", self.asset_info.id, e);
 
-            warn!("Failed to load sound file: {}", self.asset_info.id);
-            return;
+                None
+            }
         }
+    }
 
-        let buffer = BufReader::new(file.unwrap());
+    pub fn channels(&self) -> Option<u16> {
+        self.data.as_ref().map(SoundData::channels)
+    }
+
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.data.as_ref().map(SoundData::sample_rate)
+    }
 
-        let source = Decoder::new(buffer);
+    pub fn duration(&self) -> Option<Duration> {
+        self.data.as_ref()?.duration()
+    }
 
-        if source.is_err() {
-            self.asset_info.status = AssetStatus::Invalid;
+    pub fn is_streamed(&self) -> bool {
+        matches!(self.data, Some(SoundData::Streamed { .. }))
+    }
+}
 
-            warn!("Failed to decode sound file: {}", self.asset_info.id);
-            return;
+impl LoadableAsset for Sound {
+    type Data = SoundData;
+
+    fn read(id: &str, context: &LoadContext) -> Result<SoundData, AssetError> {
+        let mut decoder = decode(context.vfs.open(id)?)?;
+
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+
+        // Durations are divided by both, a malformed header must not make them infinite
+        if channels == 0 || sample_rate == 0 {
+            return Err(AssetError::Parse(format!(
+                "{} has {} channels at {} Hz",
+                id, channels, sample_rate
+            )));
+        }
+
+        let threshold = match AssetMeta::read(id, &context.vfs)?.sound.stream {
+            Some(true) => Duration::ZERO,
+            Some(false) => Duration::MAX,
+            None => context.sound_streaming_threshold,
+        };
+
+        let streamed = |duration| SoundData::Streamed {
+            vfs: context.vfs.clone(),
+            path: id.to_owned(),
+            channels,
+            sample_rate,
+            duration,
+        };
+
+        // Most formats know their length up front, the rest find out while being decoded
+        if let Some(duration) = decoder.total_duration() {
+            if duration > threshold {
+                return Ok(streamed(Some(duration)));
+            }
+        }
+
+        // Float to integer casts saturate, so a threshold of `Duration::MAX` never streams
+        let max_samples = ((threshold.as_secs_f64() * sample_rate as f64).ceil() as usize)
+            .saturating_mul(channels as usize);
+
+        let mut samples = vec![];
+
+        for sample in decoder.by_ref() {
+            if samples.len() == max_samples {
+                return Ok(streamed(decoder.total_duration()));
+            }
+
+            samples.push(sample);
         }
 
-        self.source = Some(source.unwrap());
-        self.asset_info.status = AssetStatus::Loaded;
+        Ok(SoundData::Buffered {
+            samples: samples.into(),
+            channels,
+            sample_rate,
+        })
+    }
+
+    fn publish(&mut self, data: Result<SoundData, AssetError>) {
+        match data {
+            Ok(data) => {
+                self.data = Some(data);
+                self.asset_info.set_loaded();
+
+                info!("Loaded sound file: {}", self.asset_info.id);
+            }
+            Err(e) => {
+                warn!("Failed to load sound {}: {}", self.asset_info.id, e);
+
+                self.asset_info.set_failed(e);
+            }
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(data: SoundData) -> Sound {
+        Sound {
+            asset_info: AssetInfo::new("fixtures/beep.wav"),
+            data: Some(data),
+        }
+    }
+
+    #[test]
+    fn test_read_buffered() {
+        let data = Sound::read("fixtures/beep.wav", &LoadContext::default()).unwrap();
+
+        assert!(matches!(data, SoundData::Buffered { .. }));
+        assert_eq!(data.channels(), 1);
+        assert_eq!(data.sample_rate(), 8000);
+        assert_eq!(data.duration(), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn test_play_many_times() {
+        let sound = sound(Sound::read("fixtures/beep.wav", &LoadContext::default()).unwrap());
+
+        let first: Vec<i16> = sound.play().unwrap().collect();
+        let second = sound.play().unwrap();
+
+        assert_eq!(first.len(), 2000);
+        assert_eq!(second.total_duration(), Some(Duration::from_millis(250)));
+        assert_eq!(second.collect::<Vec<i16>>(), first);
+    }
+
+    #[test]
+    fn test_read_streamed() {
+        let context = LoadContext {
+            sound_streaming_threshold: Duration::from_millis(100),
+            ..Default::default()
+        };
+
+        let data = Sound::read("fixtures/beep.wav", &context).unwrap();
+        let buffered = Sound::read("fixtures/beep.wav", &LoadContext::default()).unwrap();
+
+        assert!(matches!(data, SoundData::Streamed { .. }));
+        assert_eq!(data.duration(), Some(Duration::from_millis(250)));
+
+        // Streaming decodes the same samples, just while playing
+        let sound = sound(data);
+        assert!(sound.is_streamed());
+        assert!(sound.play().unwrap().eq(buffered.play().unwrap()));
+    }
+
+    #[test]
+    fn test_decode_flac() {
+        let wav = Sound::read("fixtures/beep.wav", &LoadContext::default()).unwrap();
+        let flac = Sound::read("fixtures/beep.flac", &LoadContext::default()).unwrap();
+
+        // FLAC is lossless, it decodes to the very samples it was encoded from
+        assert!(matches!(flac, SoundData::Buffered { .. }));
+        assert_eq!(flac.channels(), 1);
+        assert_eq!(flac.sample_rate(), 8000);
+        assert_eq!(flac.duration(), Some(Duration::from_millis(250)));
+        assert!(flac.play().unwrap().eq(wav.play().unwrap()));
+
+        let context = LoadContext {
+            sound_streaming_threshold: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let streamed = Sound::read("fixtures/beep.flac", &context).unwrap();
+
+        assert!(matches!(streamed, SoundData::Streamed { .. }));
+        assert!(streamed.play().unwrap().eq(wav.play().unwrap()));
+    }
+
+    #[test]
+    fn test_meta_stream() {
+        let directory = std::env::temp_dir().join(format!("sound_meta_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::copy("fixtures/beep.wav", directory.join("beep.wav")).unwrap();
+        std::fs::write(directory.join("beep.wav.meta"), "[sound]\nstream = true\n").unwrap();
+
+        let mut context = LoadContext::default();
+        context.vfs.mount_directory("meta", &directory);
+
+        let data = Sound::read("meta/beep.wav", &context).unwrap();
+        assert!(matches!(data, SoundData::Streamed { .. }));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 
-        warn!("Loaded sound file: {}", self.asset_info.id);
+    #[test]
+    fn test_read_invalid() {
+        assert!(Sound::read("fixtures/two_materials.mtl", &LoadContext::default()).is_err());
     }
 }
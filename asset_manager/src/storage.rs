@@ -0,0 +1,426 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use crate::{Handle, StrongHandle};
+
+/// Number of shards the assets are spread over, so requests for different ids rarely wait on
+/// each other
+const SHARDS: usize = 16;
+
+struct Slot<T> {
+    generation: u32,
+    asset: Option<Arc<Mutex<T>>>,
+    id: String,
+    /// Cloned into every `StrongHandle`, the asset is referenced while there are more than this one
+    references: Arc<()>,
+    /// Explicit references taken through `AssetStorage::retain`
+    retained: AtomicU32,
+    /// Whether the asset was requested since the references were last taken
+    requested: AtomicBool,
+}
+
+struct Slots<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+}
+
+impl<T> Slots<T> {
+    /// Empty the slot for reuse, invalidating every outstanding handle to its asset
+    fn free(&mut self, index: usize, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        let slot = match self.slots.get_mut(index) {
+            Some(slot) if slot.generation == handle.generation() => slot,
+            _ => return None,
+        };
+
+        let asset = slot.asset.take();
+        slot.generation = slot.generation.wrapping_add(1);
+
+        self.free_slots.push(index as u32);
+
+        asset
+    }
+}
+
+/// The ids hashing to the shard and the slots of their assets, aligned to a cache line so
+/// threads reading different shards don't write to the same one when taking the locks
+#[repr(align(64))]
+struct Shard<T> {
+    ids: RwLock<HashMap<String, Handle<T>>>,
+    slots: RwLock<Slots<T>>,
+}
+
+/// Generational storage of assets, addressable by `Handle` or by their string id
+///
+/// Every method takes `&self`, so any number of threads can request and read assets at once.
+/// Looking up an asset only takes read locks, and inserting one only blocks lookups of assets
+/// in the same shard. An asset lives in the shard its id hashes to, and the index of its handle
+/// tells which one that is
+pub struct AssetStorage<T> {
+    shards: Vec<Shard<T>>,
+    hasher: RandomState,
+}
+
+impl<T> AssetStorage<T> {
+    pub fn new() -> AssetStorage<T> {
+        AssetStorage::with_shards(SHARDS)
+    }
+
+    /// A storage spreading its assets over the number of shards, with a single one every lookup
+    /// takes the same lock
+    pub fn with_shards(shards: usize) -> AssetStorage<T> {
+        AssetStorage {
+            shards: (0..shards.max(1))
+                .map(|_| Shard {
+                    ids: RwLock::default(),
+                    slots: RwLock::new(Slots {
+                        slots: vec![],
+                        free_slots: vec![],
+                    }),
+                })
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard_index(&self, id: &str) -> usize {
+        self.hasher.hash_one(id) as usize % self.shards.len()
+    }
+
+    /// The shard the handle's asset is in and the index of its slot there
+    fn locate(&self, handle: Handle<T>) -> (&Shard<T>, usize) {
+        let shards = self.shards.len();
+
+        (
+            &self.shards[handle.index() % shards],
+            handle.index() / shards,
+        )
+    }
+
+    /// Look up the handle of an already inserted asset by its id
+    pub fn handle(&self, id: &str) -> Option<Handle<T>> {
+        let shard = &self.shards[self.shard_index(id)];
+
+        let ids = shard.ids.read().unwrap();
+        ids.get(id).copied()
+    }
+
+    /// Insert the asset under the id, removing the asset that was inserted under it before
+    pub fn insert(&self, id: &str, asset: T) -> Handle<T> {
+        let index = self.shard_index(id);
+        let mut ids = self.shards[index].ids.write().unwrap();
+
+        if let Some(replaced) = ids.get(id).copied() {
+            let (shard, slot) = self.locate(replaced);
+            shard.slots.write().unwrap().free(slot, replaced);
+        }
+
+        let handle = self.allocate(index, id, asset);
+        ids.insert(id.to_owned(), handle);
+
+        handle
+    }
+
+    /// The handle of the asset with the id, inserting the one made by `asset` if there is none yet.
+    /// Also returns whether it was inserted, only one of several threads racing to insert the
+    /// same id does
+    pub fn get_or_insert_with(&self, id: &str, asset: impl FnOnce() -> T) -> (Handle<T>, bool) {
+        if let Some(handle) = self.handle(id) {
+            return (handle, false);
+        }
+
+        let index = self.shard_index(id);
+        let mut ids = self.shards[index].ids.write().unwrap();
+
+        // Another thread may have inserted it between the two locks
+        if let Some(handle) = ids.get(id) {
+            return (*handle, false);
+        }
+
+        let handle = self.allocate(index, id, asset());
+        ids.insert(id.to_owned(), handle);
+
+        (handle, true)
+    }
+
+    fn allocate(&self, shard: usize, id: &str, asset: T) -> Handle<T> {
+        let mut slots = self.shards[shard].slots.write().unwrap();
+
+        let asset = Some(Arc::new(Mutex::new(asset)));
+
+        let (slot, generation) = match slots.free_slots.pop() {
+            Some(slot) => {
+                let reused = &mut slots.slots[slot as usize];
+                reused.asset = asset;
+                reused.id = id.to_owned();
+                // Strong handles to the previous asset must not keep this one alive
+                reused.references = Arc::new(());
+                reused.retained = AtomicU32::new(0);
+                reused.requested = AtomicBool::new(false);
+
+                (slot as usize, reused.generation)
+            }
+            None => {
+                slots.slots.push(Slot {
+                    generation: 0,
+                    asset,
+                    id: id.to_owned(),
+                    references: Arc::new(()),
+                    retained: AtomicU32::new(0),
+                    requested: AtomicBool::new(false),
+                });
+
+                (slots.slots.len() - 1, 0)
+            }
+        };
+
+        Handle::new((slot * self.shards.len() + shard) as u32, generation)
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        self.with_slot(handle, |slot| slot.asset.clone()).flatten()
+    }
+
+    /// A handle keeping the asset alive, which keeps nothing alive if it was already removed
+    pub fn strong(&self, handle: Handle<T>) -> StrongHandle<T> {
+        let references = self.with_slot(handle, |slot| Arc::clone(&slot.references));
+
+        StrongHandle::new(handle, references.unwrap_or_default())
+    }
+
+    fn with_slot<R>(&self, handle: Handle<T>, f: impl FnOnce(&Slot<T>) -> R) -> Option<R> {
+        let (shard, index) = self.locate(handle);
+        let slots = shard.slots.read().unwrap();
+
+        match slots.slots.get(index) {
+            Some(slot) if slot.generation == handle.generation() => Some(f(slot)),
+            _ => None,
+        }
+    }
+
+    /// Remove the asset, invalidating every outstanding handle to it
+    pub fn remove(&self, handle: Handle<T>) -> Option<Arc<Mutex<T>>> {
+        let (shard, index) = self.locate(handle);
+
+        // Ids are always locked before the slots, the same order inserting takes them in. The
+        // asset's id hashes to the shard it is in, so both locks are of the same shard
+        let mut ids = shard.ids.write().unwrap();
+        let mut slots = shard.slots.write().unwrap();
+
+        let asset = slots.free(index, handle)?;
+        let id = &slots.slots[index].id;
+
+        if ids.get(id) == Some(&handle) {
+            ids.remove(id);
+        }
+
+        Some(asset)
+    }
+
+    /// Every stored asset, collected so no lock is held while going through them
+    pub fn iter(&self) -> impl Iterator<Item = Arc<Mutex<T>>> {
+        let mut assets: Vec<Arc<Mutex<T>>> = vec![];
+
+        for shard in &self.shards {
+            let slots = shard.slots.read().unwrap();
+
+            assets.extend(slots.slots.iter().filter_map(|slot| slot.asset.clone()));
+        }
+
+        assets.into_iter()
+    }
+
+    /// Keep the asset from being reported as unreferenced, until a matching `release`
+    pub fn retain(&self, handle: Handle<T>) {
+        self.with_slot(handle, |slot| slot.retained.fetch_add(1, Ordering::Relaxed));
+    }
+
+    pub fn release(&self, handle: Handle<T>) {
+        self.with_slot(handle, |slot| {
+            let _ = slot
+                .retained
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |retained| {
+                    Some(retained.saturating_sub(1))
+                });
+        });
+    }
+
+    pub fn mark_requested(&self, handle: Handle<T>) {
+        self.with_slot(handle, |slot| slot.requested.store(true, Ordering::Relaxed));
+    }
+
+    /// The handles of every stored asset that no `StrongHandle` refers to, is not retained and was
+    /// not requested since the last call, marking every asset as not requested again
+    pub fn take_unrequested(&self) -> Vec<Handle<T>> {
+        let mut handles: Vec<Handle<T>> = vec![];
+
+        for (index, shard) in self.shards.iter().enumerate() {
+            let slots = shard.slots.read().unwrap();
+
+            let unrequested = slots
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| slot.asset.is_some())
+                .filter(|(_, slot)| {
+                    let requested = slot.requested.swap(false, Ordering::Relaxed);
+
+                    Arc::strong_count(&slot.references) == 1
+                        && slot.retained.load(Ordering::Relaxed) == 0
+                        && !requested
+                })
+                .map(|(slot, asset)| {
+                    Handle::new((slot * self.shards.len() + index) as u32, asset.generation)
+                });
+
+            handles.extend(unrequested);
+        }
+
+        handles
+    }
+}
+
+impl<T> Default for AssetStorage<T> {
+    fn default() -> Self {
+        AssetStorage::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_and_get() {
+        let storage = AssetStorage::new();
+
+        let handle = storage.insert("a", 1);
+
+        assert_eq!(storage.handle("a"), Some(handle));
+        assert_eq!(*storage.get(handle).unwrap().lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_handle_missing() {
+        let storage = AssetStorage::<u32>::new();
+
+        assert_eq!(storage.handle("a"), None);
+    }
+
+    #[test]
+    fn test_remove_invalidates_handle() {
+        // Both ids are in the same shard, so the slot of the first one is reused
+        let storage = AssetStorage::with_shards(1);
+
+        let old_handle = storage.insert("a", 1);
+        assert!(storage.remove(old_handle).is_some());
+
+        let new_handle = storage.insert("b", 2);
+
+        assert_eq!(old_handle.index(), new_handle.index());
+        assert!(storage.get(old_handle).is_none());
+        assert!(storage.remove(old_handle).is_none());
+        assert_eq!(storage.handle("a"), None);
+        assert_eq!(*storage.get(new_handle).unwrap().lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_insert_replaces() {
+        let storage = AssetStorage::with_shards(1);
+
+        let replaced = storage.insert("a", 1);
+        let handle = storage.insert("a", 2);
+
+        // The slot of the replaced asset is freed and reused by the new one
+        assert!(storage.get(replaced).is_none());
+        assert_eq!(storage.handle("a"), Some(handle));
+        assert_eq!(handle.index(), replaced.index());
+        assert_eq!(storage.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_shards() {
+        let storage = AssetStorage::with_shards(4);
+
+        let handles: Vec<Handle<usize>> = (0..64)
+            .map(|value| storage.insert(&value.to_string(), value))
+            .collect();
+
+        // Handles of different shards never share an index
+        let mut indices: Vec<usize> = handles.iter().map(|handle| handle.index()).collect();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), 64);
+
+        for (value, handle) in handles.iter().enumerate() {
+            assert_eq!(storage.handle(&value.to_string()), Some(*handle));
+            assert_eq!(*storage.get(*handle).unwrap().lock().unwrap(), value);
+        }
+
+        assert!(storage.remove(handles[10]).is_some());
+        assert_eq!(storage.handle("10"), None);
+        assert_eq!(storage.iter().count(), 63);
+
+        let reinserted = storage.insert("10", 10);
+        assert!(storage.get(handles[10]).is_none());
+        assert_eq!(*storage.get(reinserted).unwrap().lock().unwrap(), 10);
+    }
+
+    #[test]
+    fn test_get_or_insert_races() {
+        let storage = &AssetStorage::new();
+
+        let results: Vec<(Handle<u32>, bool)> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|value| scope.spawn(move || storage.get_or_insert_with("a", || value)))
+                .collect();
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect()
+        });
+
+        // Every thread gets the same asset, and only one of them inserted it
+        assert!(results.iter().all(|(handle, _)| *handle == results[0].0));
+        assert_eq!(results.iter().filter(|(_, inserted)| *inserted).count(), 1);
+        assert_eq!(storage.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_take_unrequested() {
+        let storage = AssetStorage::new();
+
+        let requested = storage.insert("requested", 0);
+        let retained = storage.insert("retained", 1);
+        let unreferenced = storage.insert("unreferenced", 2);
+        let strong = storage.strong(storage.insert("strong", 3));
+
+        storage.mark_requested(requested);
+        storage.retain(retained);
+
+        assert_eq!(storage.take_unrequested(), vec![unreferenced]);
+
+        // Requests only count until the next call, strong handles until they are dropped
+        storage.release(retained);
+        let mut unrequested = storage.take_unrequested();
+        let mut expected = vec![requested, retained, unreferenced];
+        unrequested.sort();
+        expected.sort();
+        assert_eq!(unrequested, expected);
+
+        let weak = strong.weak();
+        drop(strong.clone());
+        assert!(!storage.take_unrequested().contains(&weak));
+
+        drop(strong);
+        assert!(storage.take_unrequested().contains(&weak));
+    }
+}
@@ -0,0 +1,110 @@
+/// Every mip level after the full size one down to 1x1, each a box filtered half of the one before
+///
+/// sRGB colors are averaged in linear space so the levels do not darken, alpha is always linear
+pub fn generate(pixels: &[u8], width: u32, height: u32, srgb: bool) -> Vec<Vec<u8>> {
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|value| {
+            let value = value as f32 / 255.0;
+
+            match srgb {
+                true => srgb_to_linear(value),
+                false => value,
+            }
+        })
+        .collect();
+
+    let mut levels = vec![];
+    let (mut width, mut height) = (width as usize, height as usize);
+    let mut level = pixels.to_vec();
+
+    while width > 1 || height > 1 {
+        let next_width = (width / 2).max(1);
+        let next_height = (height / 2).max(1);
+
+        let mut next = Vec::with_capacity(next_width * next_height * 4);
+
+        for y in 0..next_height {
+            for x in 0..next_width {
+                // A side of 1 can not be halved, the same pixel is sampled twice instead
+                let xs = [(x * 2).min(width - 1), (x * 2 + 1).min(width - 1)];
+                let ys = [(y * 2).min(height - 1), (y * 2 + 1).min(height - 1)];
+
+                for channel in 0..4 {
+                    let sum: f32 = ys
+                        .iter()
+                        .flat_map(|y| xs.iter().map(move |x| (y * width + x) * 4 + channel))
+                        .map(|index| match channel {
+                            3 => level[index] as f32 / 255.0,
+                            _ => to_linear[level[index] as usize],
+                        })
+                        .sum();
+
+                    let average = sum / 4.0;
+
+                    let value = match (channel, srgb) {
+                        (0..=2, true) => linear_to_srgb(average),
+                        _ => average,
+                    };
+
+                    next.push((value * 255.0).round() as u8);
+                }
+            }
+        }
+
+        levels.push(next.clone());
+        level = next;
+        width = next_width;
+        height = next_height;
+    }
+
+    levels
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_sizes() {
+        let levels = generate(&[255; 8 * 2 * 4], 8, 2, true);
+
+        let sizes: Vec<usize> = levels.iter().map(|level| level.len() / 4).collect();
+        assert_eq!(sizes, vec![4, 2, 1]);
+        assert!(levels.iter().flatten().all(|value| *value == 255));
+    }
+
+    #[test]
+    fn test_srgb_average() {
+        // A black and white checker averages to linear half grey, which is brighter in sRGB
+        let pixels = [
+            [0, 0, 0, 255],
+            [255, 255, 255, 255],
+            [255, 255, 255, 255],
+            [0, 0, 0, 255],
+        ]
+        .concat();
+
+        assert_eq!(
+            generate(&pixels, 2, 2, true),
+            vec![vec![188, 188, 188, 255]]
+        );
+        assert_eq!(
+            generate(&pixels, 2, 2, false),
+            vec![vec![128, 128, 128, 255]]
+        );
+    }
+}
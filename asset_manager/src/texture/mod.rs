@@ -0,0 +1,312 @@
+mod mips;
+
+use std::path::{Path, PathBuf};
+
+use gltf::image::Format;
+use gpu_info::Image;
+use log::warn;
+
+use crate::{
+    asset_info::{AssetInfo, AssetStatus},
+    gltf_file::{self, GltfFile},
+    loader::{LoadContext, LoadableAsset},
+    meta::AssetMeta,
+    vfs::Vfs,
+    AssetError, Dependency,
+};
+
+/// Decoded RGBA8 pixels of a texture, waiting to be published into it
+pub struct TextureData {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub srgb: bool,
+    pub mips: Vec<Vec<u8>>,
+    /// Files other than the id's own the pixels were read from, such as the image file or
+    /// buffers of a glTF image
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// A PNG or JPEG image, either a standalone file or embedded in a glTF file
+///
+/// Images of a glTF file are referenced as `<path>#<image index>`, for example
+/// `WaterBottle.gltf#0`, everything else is decoded as a standalone image file
+pub struct Texture {
+    pub asset_info: AssetInfo,
+    pub gpu_info: Option<Image>,
+    /// Tightly packed RGBA8 pixels, row by row
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Whether the pixels are sRGB encoded colors rather than linear data, set by the meta file
+    pub srgb: bool,
+    /// Pixels of every mip level after the full size one, each half the size of the one before.
+    /// Only generated when the meta file asks for them
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl Texture {
+    // The texture has been uploaded to the GPU and we are storing the GPU info for later reference
+    pub fn add_gpu_info(&mut self, gpu_info: Image) {
+        self.gpu_info = Some(gpu_info);
+        self.asset_info.status = AssetStatus::Uploaded;
+
+        // Free the cpu side data since we no longer need it
+        self.pixels = vec![];
+        self.mips = vec![];
+    }
+
+    // The texture is being re-uploaded, hand back the GPU info so its memory can be freed
+    pub fn take_gpu_info(&mut self) -> Option<Image> {
+        self.gpu_info.take()
+    }
+
+    // The texture is being unloaded, hand back the GPU info so its memory can be freed
+    pub fn remove_gpu_info(&mut self) -> Option<Image> {
+        self.pixels = vec![];
+        self.mips = vec![];
+        self.asset_info.status = AssetStatus::Unloaded;
+
+        self.take_gpu_info()
+    }
+
+    pub fn needs_uploaded(&self) -> bool {
+        self.asset_info.status == AssetStatus::Loaded
+    }
+
+    fn read_file(path: &Path, vfs: &Vfs) -> Result<TextureData, AssetError> {
+        // The format is guessed from the contents, as there is no file to go by inside a pack
+        let image = image::load_from_memory(&vfs.read(path)?)?.into_rgba8();
+
+        Ok(TextureData {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw(),
+            srgb: true,
+            mips: vec![],
+            dependencies: vec![],
+        })
+    }
+
+    fn read_gltf_image(path: &str, index: usize, vfs: &Vfs) -> Result<TextureData, AssetError> {
+        let gltf = GltfFile::open(path, vfs)?;
+
+        let image = match gltf.document.images().nth(index) {
+            Some(image) => image,
+            None => {
+                return Err(AssetError::Parse(format!(
+                    "The gltf file has no image {}",
+                    index
+                )))
+            }
+        };
+
+        // Images in files of their own are read like any other image file
+        if let gltf::image::Source::Uri { uri, .. } = image.source() {
+            if let Some(file) = gltf_file::uri_file(path, uri) {
+                let mut data = Texture::read_file(&file, vfs)?;
+                data.dependencies = vec![file];
+
+                return Ok(data);
+            }
+        }
+
+        // Only the requested image is decoded, unlike `gltf::import` which decodes all of them.
+        // What is left never reads a file, the base path just has to be set for data URIs
+        let data =
+            gltf::image::Data::from_source(image.source(), Some(Path::new("")), &gltf.buffers)?;
+
+        Ok(TextureData {
+            pixels: Texture::to_rgba8(data.format, data.pixels)?,
+            width: data.width,
+            height: data.height,
+            srgb: true,
+            mips: vec![],
+            dependencies: gltf.buffer_files(path),
+        })
+    }
+
+    fn to_rgba8(format: Format, pixels: Vec<u8>) -> Result<Vec<u8>, AssetError> {
+        let rgba = match format {
+            Format::R8G8B8A8 => return Ok(pixels),
+            Format::R8G8B8 => pixels
+                .chunks_exact(3)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+                .collect(),
+            Format::R8G8 => pixels
+                .chunks_exact(2)
+                .flat_map(|pixel| [pixel[0], pixel[1], 0, 255])
+                .collect(),
+            Format::R8 => pixels
+                .iter()
+                .flat_map(|value| [*value, *value, *value, 255])
+                .collect(),
+            format => return Err(AssetError::UnsupportedFormat(format!("{:?}", format))),
+        };
+
+        Ok(rgba)
+    }
+}
+
+/// Split a `<path>#<image index>` texture id into its parts
+fn gltf_image_id(id: &str) -> Option<(&str, usize)> {
+    let (path, index) = id.rsplit_once('#')?;
+
+    let extension = Path::new(path).extension()?.to_str()?;
+
+    if !extension.eq_ignore_ascii_case("gltf") && !extension.eq_ignore_ascii_case("glb") {
+        return None;
+    }
+
+    Some((path, index.parse().ok()?))
+}
+
+/// Id of the image a texture of the glTF file at `path` samples, images are requested by their index
+pub(crate) fn gltf_texture_id(path: &str, texture: &gltf::Texture) -> String {
+    format!("{}#{}", path, texture.source().index())
+}
+
+/// The file a texture is read from, which is the glTF file for images embedded in one
+pub(crate) fn source_file(id: &str) -> &str {
+    match gltf_image_id(id) {
+        Some((path, _)) => path,
+        None => id,
+    }
+}
+
+impl LoadableAsset for Texture {
+    type Data = TextureData;
+
+    fn read(id: &str, context: &LoadContext) -> Result<TextureData, AssetError> {
+        let meta = AssetMeta::read(id, &context.vfs)?.texture;
+
+        let mut data = match gltf_image_id(id) {
+            Some((path, index)) => Texture::read_gltf_image(path, index, &context.vfs)?,
+            None => Texture::read_file(Path::new(id), &context.vfs)?,
+        };
+
+        data.srgb = meta.srgb;
+
+        if meta.generate_mips {
+            data.mips = mips::generate(&data.pixels, data.width, data.height, data.srgb);
+        }
+
+        Ok(data)
+    }
+
+    fn publish(&mut self, data: Result<TextureData, AssetError>) {
+        match data {
+            Ok(data) => {
+                self.pixels = data.pixels;
+                self.width = data.width;
+                self.height = data.height;
+                self.srgb = data.srgb;
+                self.mips = data.mips;
+                self.asset_info.set_loaded();
+            }
+            Err(e) => {
+                warn!("Failed to load texture {}: {}", self.asset_info.id, e);
+
+                self.asset_info.set_failed(e);
+            }
+        }
+    }
+
+    fn asset_info(&self) -> &AssetInfo {
+        &self.asset_info
+    }
+
+    fn dependencies(data: &TextureData) -> Vec<Dependency> {
+        data.dependencies
+            .iter()
+            .map(|file| Dependency::File(file.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gltf_image_id() {
+        assert_eq!(
+            gltf_image_id("models/bottle.gltf#2"),
+            Some(("models/bottle.gltf", 2))
+        );
+        assert_eq!(gltf_image_id("monkey.GLB#0"), Some(("monkey.GLB", 0)));
+        assert_eq!(gltf_image_id("models/bottle.gltf"), None);
+        assert_eq!(gltf_image_id("textures/#1.png"), None);
+        assert_eq!(gltf_image_id("bottle.gltf#normal"), None);
+    }
+
+    #[test]
+    fn test_source_file() {
+        assert_eq!(source_file("models/bottle.gltf#2"), "models/bottle.gltf");
+        assert_eq!(source_file("textures/bottle.png"), "textures/bottle.png");
+    }
+
+    #[test]
+    fn test_to_rgba8() {
+        assert_eq!(
+            Texture::to_rgba8(Format::R8G8B8, vec![1, 2, 3, 4, 5, 6]).unwrap(),
+            vec![1, 2, 3, 255, 4, 5, 6, 255]
+        );
+        assert_eq!(
+            Texture::to_rgba8(Format::R8, vec![7]).unwrap(),
+            vec![7, 7, 7, 255]
+        );
+        assert!(Texture::to_rgba8(Format::R16, vec![0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_upload_drops_pixels() {
+        let mut texture = Texture {
+            asset_info: AssetInfo::new("fixtures/checker.png"),
+            gpu_info: None,
+            pixels: vec![],
+            width: 0,
+            height: 0,
+            srgb: true,
+            mips: vec![],
+        };
+        texture.publish(Texture::read(
+            "fixtures/checker.png",
+            &LoadContext::default(),
+        ));
+        assert!(texture.needs_uploaded());
+
+        // Stands in for the image the renderer makes, nothing is ever done with it
+        let image: Image = unsafe { std::mem::zeroed() };
+        texture.add_gpu_info(image);
+
+        assert!(!texture.needs_uploaded());
+        assert!(texture.pixels.is_empty());
+        assert!(texture.mips.is_empty());
+        assert_eq!((texture.width, texture.height), (4, 4));
+
+        // A reload uploads the new pixels while the old image is handed back to be retired
+        texture.publish(Texture::read(
+            "fixtures/checker.png",
+            &LoadContext::default(),
+        ));
+        assert!(texture.needs_uploaded());
+        assert_eq!(texture.pixels.len(), 4 * 4 * 4);
+        assert!(texture.take_gpu_info().is_some());
+    }
+
+    #[test]
+    fn test_read_gltf_image_matches_file() {
+        let file = Texture::read("fixtures/checker.png", &LoadContext::default()).unwrap();
+        assert_eq!((file.width, file.height), (4, 4));
+        assert_eq!(file.pixels.len(), 4 * 4 * 4);
+
+        // The first image points at the file, the second is the same file in a buffer view
+        for id in ["fixtures/textured.gltf#0", "fixtures/textured.gltf#1"] {
+            let embedded = Texture::read(id, &LoadContext::default()).unwrap();
+
+            assert_eq!((embedded.width, embedded.height), (file.width, file.height));
+            assert!(embedded.pixels == file.pixels);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{MountSource, VfsReader};
+
+/// A directory on disk, mounted as is
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> DirectorySource {
+        DirectorySource { root: root.into() }
+    }
+}
+
+impl MountSource for DirectorySource {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn VfsReader>> {
+        Ok(Box::new(fs::File::open(self.root.join(path))?))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(self.root.join(path))?.len())
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(self.root.join(path)).ok()?.modified().ok()
+    }
+
+    fn real_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+}
@@ -0,0 +1,335 @@
+mod directory;
+mod tar_pack;
+mod zip_pack;
+
+use std::{
+    io::{self, Cursor, Read, Seek},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+pub use directory::DirectorySource;
+pub use tar_pack::TarPack;
+pub use zip_pack::ZipPack;
+
+use crate::AssetError;
+
+/// A file opened through the virtual filesystem, see `Vfs::open`
+pub trait VfsReader: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> VfsReader for T {}
+
+/// Somewhere files can be mounted from, such as a directory or a pack file
+///
+/// Paths handed to a source are relative to its mount point and already normalized
+pub trait MountSource: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Open the file to be read a little at a time, sources that can not do so read all of it
+    fn open(&self, path: &Path) -> io::Result<Box<dyn VfsReader>> {
+        Ok(Box::new(Cursor::new(self.read(path)?)))
+    }
+
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Size of the file in bytes, without reading it
+    fn size(&self, path: &Path) -> io::Result<u64>;
+
+    /// When the file last changed, for sources that know
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        None
+    }
+
+    /// The file on disk behind the path, whether it exists or not, for sources that have one
+    fn real_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[derive(Clone)]
+struct Mount {
+    point: PathBuf,
+    source: Arc<dyn MountSource>,
+}
+
+impl Mount {
+    /// The path relative to the mount point, if the path is under it
+    fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.point).ok()
+    }
+}
+
+/// The virtual filesystem every asset is read through
+///
+/// Sources are mounted at a virtual path, later mounts overlay earlier ones so a mod or patch
+/// can replace single files of the base game by mounting on top of it
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Vec<Mount>,
+}
+
+impl Vfs {
+    /// A filesystem with the working directory mounted at its root,
+    /// so asset ids resolve the same way as plain relative paths
+    pub fn working_directory() -> Vfs {
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("", ".");
+
+        vfs
+    }
+
+    pub fn mount(&mut self, point: impl AsRef<Path>, source: impl MountSource + 'static) {
+        self.mounts.push(Mount {
+            point: normalize(point.as_ref()),
+            source: Arc::new(source),
+        });
+    }
+
+    pub fn mount_directory(&mut self, point: impl AsRef<Path>, directory: impl Into<PathBuf>) {
+        self.mount(point, DirectorySource::new(directory));
+    }
+
+    /// Mount a read only zip or tar pack file, chosen by its extension
+    pub fn mount_pack(&mut self, point: impl AsRef<Path>, pack: &Path) -> Result<(), AssetError> {
+        let extension = pack
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("zip") => self.mount(point, ZipPack::open(pack)?),
+            Some("tar") => self.mount(point, TarPack::open(pack)?),
+            _ => {
+                return Err(AssetError::UnsupportedFormat(
+                    pack.to_string_lossy().into_owned(),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove every source mounted at the point
+    pub fn unmount(&mut self, point: impl AsRef<Path>) {
+        let point = normalize(point.as_ref());
+
+        self.mounts.retain(|mount| mount.point != point);
+    }
+
+    /// Read the whole file from the topmost source that has it
+    pub fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, AssetError> {
+        let path = normalize(path.as_ref());
+
+        for mount in self.mounts.iter().rev() {
+            if let Some(relative) = mount.relative(&path) {
+                if mount.source.exists(relative) {
+                    return Ok(mount.source.read(relative)?);
+                }
+            }
+        }
+
+        Err(AssetError::Io(format!(
+            "{} not found in any mount",
+            path.display()
+        )))
+    }
+
+    /// Open the file of the topmost source that has it, to read it as it is needed instead of all
+    /// at once, like a long sound being decoded while it plays
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<Box<dyn VfsReader>, AssetError> {
+        let path = normalize(path.as_ref());
+
+        for mount in self.mounts.iter().rev() {
+            if let Some(relative) = mount.relative(&path) {
+                if mount.source.exists(relative) {
+                    return Ok(mount.source.open(relative)?);
+                }
+            }
+        }
+
+        Err(AssetError::Io(format!(
+            "{} not found in any mount",
+            path.display()
+        )))
+    }
+
+    pub fn read_to_string(&self, path: impl AsRef<Path>) -> Result<String, AssetError> {
+        match String::from_utf8(self.read(path)?) {
+            Ok(text) => Ok(text),
+            Err(e) => Err(AssetError::Parse(e.to_string())),
+        }
+    }
+
+    pub fn exists(&self, path: impl AsRef<Path>) -> bool {
+        let path = normalize(path.as_ref());
+
+        self.mounts
+            .iter()
+            .rev()
+            .any(|mount| match mount.relative(&path) {
+                Some(relative) => mount.source.exists(relative),
+                None => false,
+            })
+    }
+
+    /// Size in bytes of the file in the topmost source that has it
+    pub fn size(&self, path: impl AsRef<Path>) -> Option<u64> {
+        let path = normalize(path.as_ref());
+
+        for mount in self.mounts.iter().rev() {
+            if let Some(relative) = mount.relative(&path) {
+                if mount.source.exists(relative) {
+                    return mount.source.size(relative).ok();
+                }
+            }
+        }
+
+        None
+    }
+
+    /// When the file in the topmost source that has it last changed
+    pub fn modified(&self, path: impl AsRef<Path>) -> Option<SystemTime> {
+        let path = normalize(path.as_ref());
+
+        for mount in self.mounts.iter().rev() {
+            if let Some(relative) = mount.relative(&path) {
+                if mount.source.exists(relative) {
+                    return mount.source.modified(relative);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The file on disk a path resolves to, used to watch it for changes
+    ///
+    /// Files that do not exist yet resolve to where the topmost directory would have them,
+    /// files in pack files have no path on disk
+    pub fn real_path(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        let path = normalize(path.as_ref());
+
+        for mount in self.mounts.iter().rev() {
+            if let Some(relative) = mount.relative(&path) {
+                if mount.source.exists(relative) {
+                    return mount.source.real_path(relative);
+                }
+            }
+        }
+
+        self.mounts
+            .iter()
+            .rev()
+            .find_map(|mount| mount.source.real_path(mount.relative(&path)?))
+    }
+}
+
+/// Resolve `.` and `..` in a virtual path, so `models/../textures/a.png` is found as `textures/a.png`
+///
+/// Leading `..` are kept, a directory mounted at the root can still reach files next to it
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(".."),
+            },
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Path::new("./models/../textures/a.png")),
+            PathBuf::from("textures/a.png")
+        );
+        assert_eq!(
+            normalize(Path::new("../examples/a.png")),
+            PathBuf::from("../examples/a.png")
+        );
+    }
+
+    #[test]
+    fn test_mount_point() {
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("meshes", "fixtures");
+
+        assert!(vfs.exists("meshes/two_materials.obj"));
+        assert!(!vfs.exists("two_materials.obj"));
+        assert_eq!(
+            vfs.read("meshes/two_materials.obj").unwrap(),
+            std::fs::read("fixtures/two_materials.obj").unwrap()
+        );
+        assert!(matches!(
+            vfs.read("meshes/missing.obj"),
+            Err(AssetError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_overlay() {
+        let mut vfs = Vfs::default();
+        vfs.mount_directory("", "fixtures");
+        vfs.mount_pack("", Path::new("fixtures/patch.tar")).unwrap();
+
+        // The pack replaces the MTL file, everything else still comes from the directory
+        assert_eq!(
+            vfs.read_to_string("two_materials.mtl").unwrap(),
+            "newmtl patched\nKd 0 1 0\n"
+        );
+        assert!(vfs.exists("no_normals.obj"));
+        assert_eq!(vfs.size("two_materials.mtl"), Some(24));
+
+        // Files of a tar pack are read in place, seeking only within the file
+        let mut reader = vfs.open("two_materials.mtl").unwrap();
+        let mut patched = String::new();
+        reader.seek(io::SeekFrom::Start(7)).unwrap();
+        reader.read_to_string(&mut patched).unwrap();
+        assert_eq!(patched, "patched\nKd 0 1 0\n");
+        assert_eq!(reader.seek(io::SeekFrom::End(-5)).unwrap(), 19);
+        assert_eq!(vfs.size("missing.obj"), None);
+        assert_eq!(vfs.real_path("two_materials.mtl"), None);
+        assert_eq!(
+            vfs.real_path("no_normals.obj"),
+            Some(PathBuf::from("fixtures/no_normals.obj"))
+        );
+
+        vfs.unmount("");
+        assert!(!vfs.exists("no_normals.obj"));
+    }
+
+    #[test]
+    fn test_zip_pack() {
+        let mut vfs = Vfs::default();
+        vfs.mount_pack("packed", Path::new("fixtures/pack.zip"))
+            .unwrap();
+
+        assert_eq!(
+            vfs.read("packed/models/two_materials.obj").unwrap(),
+            std::fs::read("fixtures/two_materials.obj").unwrap()
+        );
+        assert_eq!(
+            vfs.size("packed/models/two_materials.obj"),
+            Some(
+                std::fs::metadata("fixtures/two_materials.obj")
+                    .unwrap()
+                    .len()
+            )
+        );
+        assert!(!vfs.exists("packed/models/missing.obj"));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{normalize, MountSource, VfsReader};
+use crate::AssetError;
+
+/// A read only tar pack file
+///
+/// Tar files are not compressed, so only the position of every file is read when mounting
+/// and files are read straight from the pack when requested
+pub struct TarPack {
+    path: PathBuf,
+    /// Offset and size in bytes of every file in the pack
+    entries: HashMap<PathBuf, (u64, u64)>,
+    /// When the pack file changed, which is when any of its files last could have
+    modified: Option<SystemTime>,
+}
+
+impl TarPack {
+    pub fn open(path: &Path) -> Result<TarPack, AssetError> {
+        let mut archive = tar::Archive::new(File::open(path)?);
+
+        let mut entries = HashMap::new();
+
+        for entry in archive.entries()? {
+            let entry = entry?;
+
+            if entry.header().entry_type() != tar::EntryType::Regular {
+                continue;
+            }
+
+            entries.insert(
+                normalize(&entry.path()?),
+                (entry.raw_file_position(), entry.size()),
+            );
+        }
+
+        Ok(TarPack {
+            path: path.to_path_buf(),
+            entries,
+            modified: fs::metadata(path)?.modified().ok(),
+        })
+    }
+}
+
+impl MountSource for TarPack {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let (offset, size) = match self.entries.get(path) {
+            Some(entry) => *entry,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        // Every read opens the pack again, so loads on different threads never wait on each other
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut contents = vec![0; size as usize];
+        file.read_exact(&mut contents)?;
+
+        Ok(contents)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn VfsReader>> {
+        let (offset, size) = match self.entries.get(path) {
+            Some(entry) => *entry,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        Ok(Box::new(EntryReader {
+            file,
+            offset,
+            size,
+            position: 0,
+        }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        match self.entries.get(path) {
+            Some((_, size)) => Ok(*size),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+/// Reads a single file of the pack as if it were a file of its own
+struct EntryReader {
+    file: File,
+    /// Where the file starts in the pack
+    offset: u64,
+    size: u64,
+    /// Position in the file, not in the pack
+    position: u64,
+}
+
+impl Read for EntryReader {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.position);
+        let length = (buffer.len() as u64).min(remaining) as usize;
+
+        let read = self.file.read(&mut buffer[..length])?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for EntryReader {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let position = match from {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        let position = match position {
+            Some(position) => position,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Seek before the start of the file",
+                ))
+            }
+        };
+
+        self.file.seek(SeekFrom::Start(self.offset + position))?;
+        self.position = position;
+
+        Ok(position)
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use zip::ZipArchive;
+
+use super::MountSource;
+use crate::AssetError;
+
+/// A read only zip pack file, files are decompressed when requested
+pub struct ZipPack {
+    // Reading an entry seeks the shared file, so only one can be read at a time
+    archive: Mutex<ZipArchive<File>>,
+    /// When the pack file changed, which is when any of its files last could have
+    modified: Option<SystemTime>,
+}
+
+impl ZipPack {
+    pub fn open(path: &Path) -> Result<ZipPack, AssetError> {
+        let archive = match ZipArchive::new(File::open(path)?) {
+            Ok(archive) => archive,
+            Err(e) => return Err(AssetError::Parse(e.to_string())),
+        };
+
+        Ok(ZipPack {
+            archive: Mutex::new(archive),
+            modified: fs::metadata(path)?.modified().ok(),
+        })
+    }
+}
+
+/// Zip entries are always named with forward slashes
+fn entry_name(path: &Path) -> Option<String> {
+    let names: Option<Vec<&str>> = path.iter().map(|name| name.to_str()).collect();
+
+    Some(names?.join("/"))
+}
+
+impl MountSource for ZipPack {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let name = match entry_name(path) {
+            Some(name) => name,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        let mut archive = self.archive.lock().unwrap();
+
+        let mut entry = match archive.by_name(&name) {
+            Ok(entry) => entry,
+            Err(e) => return Err(io::Error::new(io::ErrorKind::NotFound, e)),
+        };
+
+        let mut contents = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut contents)?;
+
+        Ok(contents)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        match entry_name(path) {
+            Some(name) => self.archive.lock().unwrap().index_for_name(&name).is_some(),
+            None => false,
+        }
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        let name = match entry_name(path) {
+            Some(name) => name,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        match self.archive.lock().unwrap().by_name(&name) {
+            Ok(entry) => Ok(entry.size()),
+            Err(e) => Err(io::Error::new(io::ErrorKind::NotFound, e)),
+        }
+    }
+    fn modified(&self, _path: &Path) -> Option<SystemTime> {
+        self.modified
+    }
+}
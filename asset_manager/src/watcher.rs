@@ -0,0 +1,135 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long a file has to go without events before it is reported as changed
+const QUIET_PERIOD: Duration = Duration::from_millis(100);
+
+/// Watches the directories of loaded assets and reports which files have changed on disk
+pub struct AssetWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    watched_directories: HashSet<PathBuf>,
+    // When each changed file last had an event, it is reported once they stop
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl AssetWatcher {
+    pub fn new() -> Result<AssetWatcher, String> {
+        let (sender, events) = channel();
+
+        let watcher = match notify::recommended_watcher(sender) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                return Err("Failed to create filesystem watcher: ".to_owned() + &e.to_string())
+            }
+        };
+
+        Ok(AssetWatcher {
+            watcher,
+            events,
+            watched_directories: HashSet::new(),
+            pending: HashMap::new(),
+        })
+    }
+
+    /// Start watching for changes of the file at the given absolute path
+    pub fn watch(&mut self, path: &Path) {
+        // Editors often save by replacing the file, which would drop a watch on the file itself,
+        // so the containing directory is watched instead
+        let directory = match path.parent() {
+            Some(directory) => directory.to_path_buf(),
+            None => return,
+        };
+
+        if self.watched_directories.contains(&directory) {
+            return;
+        }
+
+        match self.watcher.watch(&directory, RecursiveMode::NonRecursive) {
+            Ok(_) => {
+                self.watched_directories.insert(directory);
+            }
+            Err(e) => warn!("Failed to watch directory {}: {}", directory.display(), e),
+        }
+    }
+
+    /// Drain every pending filesystem event, returning the set of files that were written to and
+    /// have had no events for `QUIET_PERIOD` since. Editors often save in several steps, like
+    /// truncating then writing, and reading the file in between would parse half of it
+    pub fn changed_paths(&mut self) -> HashSet<PathBuf> {
+        let now = Instant::now();
+
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in event.paths {
+                            self.pending.insert(path, now);
+                        }
+                    }
+                }
+                Err(e) => warn!("Filesystem watcher error: {}", e),
+            }
+        }
+
+        settled_paths(&mut self.pending, now)
+    }
+}
+
+/// Take the files whose last event is at least `QUIET_PERIOD` old
+fn settled_paths(pending: &mut HashMap<PathBuf, Instant>, now: Instant) -> HashSet<PathBuf> {
+    let settled: HashSet<PathBuf> = pending
+        .iter()
+        .filter(|(_, last_event)| now.duration_since(**last_event) >= QUIET_PERIOD)
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    pending.retain(|path, _| !settled.contains(path));
+
+    settled
+}
+
+/// Resolve a file to the absolute path that filesystem events will be reported with
+pub fn absolute_path(path: &Path) -> PathBuf {
+    match std::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => match std::env::current_dir() {
+            Ok(current_dir) => current_dir.join(path),
+            Err(_) => path.to_path_buf(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settled_paths() {
+        let start = Instant::now();
+        let mut pending = HashMap::from([
+            (PathBuf::from("a.gltf"), start),
+            (PathBuf::from("b.png"), start + Duration::from_millis(80)),
+        ]);
+
+        // Both files are still being written to
+        assert!(settled_paths(&mut pending, start + Duration::from_millis(50)).is_empty());
+
+        assert_eq!(
+            settled_paths(&mut pending, start + Duration::from_millis(120)),
+            HashSet::from([PathBuf::from("a.gltf")])
+        );
+        assert_eq!(
+            settled_paths(&mut pending, start + Duration::from_millis(200)),
+            HashSet::from([PathBuf::from("b.png")])
+        );
+        assert!(pending.is_empty());
+    }
+}
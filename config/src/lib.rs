@@ -1,7 +1,11 @@
+use std::collections::HashMap;
+
 #[derive(serde_derive::Deserialize, Clone)]
 pub struct Config {
     pub info: InfoConfig,
     pub renderer: RendererConfig,
+    #[serde(default)]
+    pub assets: AssetsConfig,
 }
 
 #[derive(serde_derive::Deserialize, Clone)]
@@ -17,6 +21,112 @@ pub struct RendererConfig {
     pub frame_overlap: u32,
 }
 
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct AssetsConfig {
+    /// Reload assets automatically when their files change on disk
+    #[serde(default)]
+    pub hot_reload: bool,
+    /// Number of worker threads assets are loaded on
+    #[serde(default = "default_loader_threads")]
+    pub loader_threads: usize,
+    /// Directory preprocessed meshes are cached in between runs, nothing is cached when unset
+    #[serde(default)]
+    pub cache_directory: Option<String>,
+    /// Directories and pack files assets are read from, later mounts overlay earlier ones
+    /// The working directory is mounted at the root when none are given
+    #[serde(default)]
+    pub mounts: Vec<MountConfig>,
+    /// Passes run on every imported mesh
+    #[serde(default)]
+    pub mesh_optimizations: MeshOptimizationsConfig,
+    /// Passes run on single meshes instead of `mesh_optimizations`, by asset id
+    #[serde(default)]
+    pub mesh_optimization_overrides: HashMap<String, MeshOptimizationsConfig>,
+    /// Simplified levels of detail generated for every mesh
+    #[serde(default)]
+    pub lods: LodConfig,
+    /// Sounds longer than this many seconds are streamed while they play instead of decoded up front
+    #[serde(default = "default_sound_streaming_threshold")]
+    pub sound_streaming_threshold: f32,
+}
+
+/// The processing passes run on imported meshes, see `asset_manager::MeshOptimizations`
+#[derive(serde_derive::Deserialize, Clone, Default)]
+pub struct MeshOptimizationsConfig {
+    #[serde(default)]
+    pub deduplicate_vertices: bool,
+    #[serde(default)]
+    pub optimize_vertex_cache: bool,
+    #[serde(default)]
+    pub optimize_vertex_fetch: bool,
+    #[serde(default)]
+    pub compute_bounds: bool,
+}
+
+/// How many levels of detail are generated for meshes, see `asset_manager::LodSettings`
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct LodConfig {
+    /// Number of simplified levels after the full detail mesh, none when left out
+    #[serde(default)]
+    pub levels: u32,
+    /// Fraction of the previous level's triangles each level aims to keep
+    #[serde(default = "default_lod_reduction")]
+    pub reduction: f32,
+    /// Largest error of a level, relative to the size of the mesh
+    #[serde(default = "default_lod_max_error")]
+    pub max_error: f32,
+}
+
+fn default_lod_reduction() -> f32 {
+    0.5
+}
+
+fn default_lod_max_error() -> f32 {
+    0.05
+}
+
+impl Default for LodConfig {
+    fn default() -> Self {
+        LodConfig {
+            levels: 0,
+            reduction: default_lod_reduction(),
+            max_error: default_lod_max_error(),
+        }
+    }
+}
+
+/// A directory or zip/tar pack file mounted into the virtual filesystem assets are read from
+#[derive(serde_derive::Deserialize, Clone)]
+pub struct MountConfig {
+    /// Virtual path the files appear under, the root when left out
+    #[serde(default)]
+    pub point: String,
+    pub path: String,
+}
+
+fn default_loader_threads() -> usize {
+    4
+}
+
+fn default_sound_streaming_threshold() -> f32 {
+    10.0
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        AssetsConfig {
+            hot_reload: false,
+            loader_threads: default_loader_threads(),
+            cache_directory: None,
+            mounts: vec![],
+            mesh_optimizations: MeshOptimizationsConfig::default(),
+            mesh_optimization_overrides: HashMap::new(),
+            lods: LodConfig::default(),
+            sound_streaming_threshold: default_sound_streaming_threshold(),
+        }
+    }
+}
+
 impl Config {
     pub fn from_file(path: &str) -> Config {
         let contents = std::fs::read_to_string(path).expect("Failed to load config file");
@@ -39,6 +149,98 @@ impl Default for Config {
                 window_height: 600,
                 frame_overlap: 2,
             },
+            assets: AssetsConfig::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assets_section_optional() {
+        let config: Config = toml::from_str(
+            r#"
+            [info]
+            name = "Test"
+
+            [renderer]
+            vsync = false
+            window_width = 800
+            window_height = 600
+            frame_overlap = 2
+            "#,
+        )
+        .unwrap();
+
+        assert!(!config.assets.hot_reload);
+        assert_eq!(config.assets.loader_threads, 4);
+        assert_eq!(config.assets.cache_directory, None);
+        assert!(config.assets.mounts.is_empty());
+        assert!(!config.assets.mesh_optimizations.deduplicate_vertices);
+        assert_eq!(config.assets.lods.levels, 0);
+        assert_eq!(config.assets.sound_streaming_threshold, 10.0);
+    }
+
+    #[test]
+    fn test_assets_hot_reload() {
+        let config: Config = toml::from_str(
+            r#"
+            [info]
+            name = "Test"
+
+            [renderer]
+            vsync = false
+            window_width = 800
+            window_height = 600
+            frame_overlap = 2
+
+            [assets]
+            hot_reload = true
+            loader_threads = 2
+            cache_directory = "cache"
+            sound_streaming_threshold = 30.0
+
+            [[assets.mounts]]
+            path = "assets"
+            point = "assets"
+
+            [[assets.mounts]]
+            path = "patch.zip"
+
+            [assets.mesh_optimizations]
+            deduplicate_vertices = true
+            optimize_vertex_cache = true
+
+            [assets.mesh_optimization_overrides."models/terrain.glb"]
+            compute_bounds = true
+
+            [assets.lods]
+            levels = 3
+            max_error = 0.01
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.assets.hot_reload);
+        assert_eq!(config.assets.loader_threads, 2);
+        assert_eq!(config.assets.cache_directory.as_deref(), Some("cache"));
+        assert_eq!(config.assets.sound_streaming_threshold, 30.0);
+        assert_eq!(config.assets.mounts.len(), 2);
+        assert_eq!(config.assets.mounts[0].point, "assets");
+        assert_eq!(config.assets.mounts[1].point, "");
+        assert_eq!(config.assets.mounts[1].path, "patch.zip");
+        assert!(config.assets.mesh_optimizations.deduplicate_vertices);
+        assert!(config.assets.mesh_optimizations.optimize_vertex_cache);
+        assert!(!config.assets.mesh_optimizations.compute_bounds);
+
+        let terrain = &config.assets.mesh_optimization_overrides["models/terrain.glb"];
+        assert!(terrain.compute_bounds);
+        assert!(!terrain.deduplicate_vertices);
+
+        assert_eq!(config.assets.lods.levels, 3);
+        assert_eq!(config.assets.lods.reduction, 0.5);
+        assert_eq!(config.assets.lods.max_error, 0.01);
+    }
+}
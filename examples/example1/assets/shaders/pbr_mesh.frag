@@ -0,0 +1,23 @@
+#version 450
+
+// Set for the masked variants of the pipeline
+layout(constant_id = 0) const bool ALPHA_MASK = false;
+
+layout(set = 0, binding = 0) uniform sampler2D baseColorTexture;
+
+layout(location = 0) in vec4 inColor;
+layout(location = 1) in vec2 inTexCoord;
+layout(location = 2) flat in float inAlphaCutoff;
+
+//output write
+layout(location = 0) out vec4 outFragColor;
+
+void main() {
+    vec4 color = inColor * texture(baseColorTexture, inTexCoord);
+
+    if (ALPHA_MASK && color.a < inAlphaCutoff) {
+        discard;
+    }
+
+    outFragColor = color;
+}
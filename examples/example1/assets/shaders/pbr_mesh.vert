@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec3 vPosition;
+layout(location = 1) in vec3 vNormal;
+layout(location = 2) in vec4 vColor;
+layout(location = 3) in vec2 vTexCoord0;
+layout(location = 4) in vec2 vTexCoord1;
+
+layout(location = 0) out vec4 outColor;
+layout(location = 1) out vec2 outTexCoord;
+layout(location = 2) flat out float outAlphaCutoff;
+
+layout(push_constant) uniform constants {
+    vec4 data;
+    mat4 render_matrix;
+    // The alpha cutoff, and the set of texture coordinates the base color texture is sampled with
+    vec4 parameters;
+} PushConstants;
+
+void main() {
+    gl_Position = PushConstants.render_matrix * vec4(vPosition, 1.0);
+    // Light from a fixed direction, same as the mesh pipeline
+    float light = 0.4 + 0.6 * max(dot(normalize(vNormal), normalize(vec3(0.5, 1.0, 0.8))), 0.0);
+    // The base color of the material is passed in the data
+    outColor = vec4(vColor.rgb * PushConstants.data.rgb * light, vColor.a * PushConstants.data.a);
+    outTexCoord = PushConstants.parameters.y == 0.0 ? vTexCoord0 : vTexCoord1;
+    outAlphaCutoff = PushConstants.parameters.x;
+}
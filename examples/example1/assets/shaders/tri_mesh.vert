@@ -2,7 +2,7 @@
 
 layout(location = 0) in vec3 vPosition;
 layout(location = 1) in vec3 vNormal;
-layout(location = 2) in vec3 vColor;
+layout(location = 2) in vec4 vColor;
 
 layout(location = 0) out vec3 outColor;
 
@@ -13,5 +13,8 @@ layout(push_constant) uniform constants {
 
 void main() {
     gl_Position = PushConstants.render_matrix * vec4(vPosition, 1.0);
-    outColor = vColor;
+    // Light from a fixed direction so the shape is visible without textures
+    float light = 0.4 + 0.6 * max(dot(normalize(vNormal), normalize(vec3(0.5, 1.0, 0.8))), 0.0);
+    // The base color of the material is passed in the data
+    outColor = vColor.rgb * PushConstants.data.rgb * light;
 }
\ No newline at end of file
@@ -1,12 +1,22 @@
 use std::f32::consts::PI;
 
 use raindrop::{
-    bevy_ecs::system::{Commands, Res},
+    bevy_ecs::system::{Commands, NonSend, Res},
     components::{Camera, Material, Mesh, Player, Transform},
-    glm, Config, GameConfig, Raindrop, ScheduleType,
+    glm, AssetManagerResource, Config, GameConfig, Raindrop, RendererResource, ScheduleType,
 };
 
-fn init_scene(mut commands: Commands, config: Res<GameConfig>) {
+fn init_scene(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    asset_manager: Res<AssetManagerResource>,
+    renderer: NonSend<RendererResource>,
+) {
+    let monkey_mesh = asset_manager
+        .asset_manager
+        .get_mesh("assets/models/monkey/monkey.glb");
+    let default_material = renderer.renderer.get_material("defaultmesh").unwrap();
+
     commands.spawn((
         Camera::new(
             (config.config.renderer.window_width as f32)
@@ -22,10 +32,11 @@ fn init_scene(mut commands: Commands, config: Res<GameConfig>) {
     commands.spawn((
         Transform::new(),
         Mesh {
-            id: "assets/models/monkey/monkey.glb".to_string(),
+            handle: monkey_mesh.clone(),
+            submesh: None,
         },
         Material {
-            id: "defaultmesh".to_string(),
+            handle: default_material,
         },
     ));
 
@@ -35,15 +46,14 @@ fn init_scene(mut commands: Commands, config: Res<GameConfig>) {
             transform.set_translation(glm::vec3(x as f32 * 2.0, 0.0, y as f32 * 2.0));
             transform.set_scale(glm::vec3(0.2, 0.2, 0.2));
 
-            let mesh_str = "assets/models/monkey/monkey.glb";
-            
             commands.spawn((
                 transform,
                 Mesh {
-                    id: mesh_str.to_string(),
+                    handle: monkey_mesh.clone(),
+                    submesh: None,
                 },
                 Material {
-                    id: "defaultmesh".to_string(),
+                    handle: default_material,
                 },
             ));
         }
@@ -3,4 +3,6 @@ use vk_mem::Allocation;
 pub struct Image {
     pub image: ash::vk::Image,
     pub allocation: Allocation,
+    /// The view shaders sample the image through
+    pub view: ash::vk::ImageView,
 }
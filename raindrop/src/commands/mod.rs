@@ -0,0 +1,3 @@
+pub mod scene_spawner;
+
+pub use scene_spawner::SceneSpawner;
@@ -0,0 +1,130 @@
+use asset_manager::SceneNode;
+use bevy_ecs::{entity::Entity, system::Command, world::World};
+
+use crate::{
+    components::{Mesh, Parent, SceneMaterial, Transform},
+    resources::{AssetManagerResource, PendingScenes},
+};
+
+/// Spawns every node of a glTF scene as an entity with its local `Transform`, a `Parent` link,
+/// and the `Mesh` and `SceneMaterial` of its mesh, for example
+///
+/// ```ignore
+/// commands.add(SceneSpawner::new("models/level.glb#scenes/0"));
+/// ```
+///
+/// The scene is requested right away and spawned by `scene_spawner_system` once it is loaded.
+/// Meshes with several submeshes get a child entity per submesh, so each draws with its own
+/// material
+pub struct SceneSpawner {
+    /// Id of the `Scene` asset, like `models/level.glb#scenes/0`
+    pub scene: String,
+    /// The entity the root nodes are children of, to move the whole scene at once
+    pub parent: Option<Entity>,
+}
+
+impl SceneSpawner {
+    pub fn new(scene: &str) -> SceneSpawner {
+        SceneSpawner {
+            scene: scene.to_owned(),
+            parent: None,
+        }
+    }
+
+    pub fn with_parent(mut self, parent: Entity) -> SceneSpawner {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Spawn the nodes, returning their entities in the same order
+    pub(crate) fn spawn(&self, world: &mut World, nodes: &[SceneNode]) -> Vec<Entity> {
+        let drawables: Vec<Vec<(Mesh, SceneMaterial)>> = {
+            let asset_manager = &world.resource::<AssetManagerResource>().asset_manager;
+
+            // Submeshes without a material of their own use the default one
+            let material = |id: &Option<String>| SceneMaterial {
+                handle: id.as_deref().map(|id| asset_manager.get_material(id)),
+            };
+
+            nodes
+                .iter()
+                .map(|node| match &node.mesh {
+                    Some(mesh) => {
+                        let handle = asset_manager.get_mesh(mesh);
+
+                        match node.materials.len() {
+                            1 => vec![(
+                                Mesh {
+                                    handle: handle.clone(),
+                                    submesh: None,
+                                },
+                                material(&node.materials[0]),
+                            )],
+                            _ => node
+                                .materials
+                                .iter()
+                                .enumerate()
+                                .map(|(submesh, id)| {
+                                    (
+                                        Mesh {
+                                            handle: handle.clone(),
+                                            submesh: Some(submesh),
+                                        },
+                                        material(id),
+                                    )
+                                })
+                                .collect(),
+                        }
+                    }
+                    None => vec![],
+                })
+                .collect()
+        };
+
+        let mut entities: Vec<Entity> = Vec::with_capacity(nodes.len());
+
+        for (node, drawables) in nodes.iter().zip(drawables) {
+            let mut transform = Transform::new();
+            transform.set_translation(node.transform.translation);
+            transform.set_rotation_quat(&node.transform.rotation);
+            transform.set_scale(node.transform.scale);
+
+            let mut entity = world.spawn(transform);
+
+            // Parents always come before their children
+            if let Some(parent) = node.parent.map(|parent| entities[parent]).or(self.parent) {
+                entity.insert(Parent { entity: parent });
+            }
+
+            let entity = entity.id();
+            entities.push(entity);
+
+            let single = drawables.len() == 1;
+
+            for (mesh, material) in drawables {
+                let mut drawable = match single {
+                    true => world.entity_mut(entity),
+                    false => world.spawn((Transform::new(), Parent { entity })),
+                };
+
+                drawable.insert((mesh, material));
+            }
+        }
+
+        entities
+    }
+}
+
+impl Command for SceneSpawner {
+    fn apply(self, world: &mut World) {
+        world
+            .resource::<AssetManagerResource>()
+            .asset_manager
+            .get_scene(&self.scene);
+
+        world
+            .get_resource_or_insert_with(PendingScenes::default)
+            .scenes
+            .push(self);
+    }
+}
@@ -1,7 +1,9 @@
+use asset_manager::{Sound, StrongHandle};
 use bevy_ecs::component::Component;
 
 #[derive(Component)]
 pub struct AudioSource {
-    pub id: String,
+    /// Keeps the sound loaded for as long as the component exists
+    pub handle: StrongHandle<Sound>,
     pub spatial: bool,
 }
@@ -1,7 +1,10 @@
+use asset_manager::StrongHandle;
 use bevy_ecs::component::Component;
 
 #[derive(Component)]
 pub struct Mesh {
-    // TODO: This needs to become a number or some cheaply comparable and copyable type
-    pub id: String,
+    /// Keeps the mesh loaded for as long as the component exists
+    pub handle: StrongHandle<asset_manager::Mesh>,
+    /// Only this submesh is drawn, so each can have its own `Material`. Every submesh when None
+    pub submesh: Option<usize>,
 }
@@ -2,12 +2,16 @@ pub mod audio_source;
 pub mod camera;
 pub mod material;
 pub mod mesh;
+pub mod parent;
 pub mod player;
+pub mod scene_material;
 pub mod transform;
 
 pub use audio_source::AudioSource;
 pub use camera::Camera;
 pub use material::Material;
 pub use mesh::Mesh;
+pub use parent::Parent;
 pub use player::Player;
+pub use scene_material::SceneMaterial;
 pub use transform::Transform;
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{component::Component, entity::Entity};
+
+/// Places the entity's `Transform` relative to the one of another entity, like a glTF node
+/// relative to its parent node
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parent {
+    pub entity: Entity,
+}
+
+/// The world space model matrix of every entity, from their local matrix and parent.
+/// Entities whose parent has no transform, or is one of their own children, are placed in world
+/// space as if they had no parent
+pub(crate) fn world_matrices(
+    locals: &HashMap<Entity, (glm::Mat4, Option<Entity>)>,
+) -> HashMap<Entity, glm::Mat4> {
+    let mut matrices: HashMap<Entity, glm::Mat4> = HashMap::with_capacity(locals.len());
+
+    for &entity in locals.keys() {
+        if matrices.contains_key(&entity) {
+            continue;
+        }
+
+        // Walk up to the first ancestor that is already placed, then place the chain top down
+        let mut chain = vec![entity];
+        let mut parent = locals[&entity].1;
+
+        while let Some(next) = parent {
+            if matrices.contains_key(&next) || !locals.contains_key(&next) || chain.contains(&next)
+            {
+                break;
+            }
+
+            chain.push(next);
+            parent = locals[&next].1;
+        }
+
+        let mut matrix = parent
+            .and_then(|parent| matrices.get(&parent).copied())
+            .unwrap_or_else(glm::Mat4::identity);
+
+        for entity in chain.into_iter().rev() {
+            matrix *= locals[&entity].0;
+            matrices.insert(entity, matrix);
+        }
+    }
+
+    matrices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translation(x: f32) -> glm::Mat4 {
+        glm::translate(&glm::Mat4::identity(), &glm::vec3(x, 0.0, 0.0))
+    }
+
+    fn x(matrix: &glm::Mat4) -> f32 {
+        matrix[(0, 3)]
+    }
+
+    #[test]
+    fn test_world_matrices() {
+        let root = Entity::from_raw(0);
+        let child = Entity::from_raw(1);
+        let grandchild = Entity::from_raw(2);
+        let orphan = Entity::from_raw(3);
+
+        let locals = HashMap::from([
+            (grandchild, (translation(100.0), Some(child))),
+            (child, (translation(10.0), Some(root))),
+            (root, (translation(1.0), None)),
+            (orphan, (translation(1000.0), Some(Entity::from_raw(4)))),
+        ]);
+
+        let matrices = world_matrices(&locals);
+
+        assert_eq!(x(&matrices[&root]), 1.0);
+        assert_eq!(x(&matrices[&child]), 11.0);
+        assert_eq!(x(&matrices[&grandchild]), 111.0);
+        assert_eq!(x(&matrices[&orphan]), 1000.0);
+    }
+
+    #[test]
+    fn test_world_matrices_cycle() {
+        let a = Entity::from_raw(0);
+        let b = Entity::from_raw(1);
+
+        let locals = HashMap::from([
+            (a, (translation(1.0), Some(b))),
+            (b, (translation(10.0), Some(a))),
+        ]);
+
+        // Which one ends up as the root is arbitrary, but both are placed
+        let matrices = world_matrices(&locals);
+
+        assert_eq!(matrices.len(), 2);
+        assert_eq!(x(&matrices[&a]).max(x(&matrices[&b])), 11.0);
+    }
+}
@@ -0,0 +1,11 @@
+use asset_manager::{PbrMaterial, StrongHandle};
+use bevy_ecs::component::Component;
+
+/// The imported material a node spawned from a scene is drawn with, `scene_material_system`
+/// gives the entity the matching `Material` of the renderer
+#[derive(Component)]
+pub struct SceneMaterial {
+    /// Keeps the material loaded for as long as the component exists, the default material is
+    /// drawn with when None
+    pub handle: Option<StrongHandle<PbrMaterial>>,
+}
@@ -53,6 +53,25 @@ impl Transform {
         self.dirty = true;
     }
 
+    /// Set the rotation from a quaternion, like the ones of glTF nodes
+    pub fn set_rotation_quat(&mut self, rotation: &glm::Quat) {
+        let matrix = glm::quat_to_mat3(rotation);
+
+        // The model matrix rotates around x, then y, then z, so the sine of y is in the corner
+        let y = matrix[(0, 2)].atan2(matrix[(0, 0)].hypot(matrix[(0, 1)]));
+
+        // At 90 degrees around y, x and z rotate around the same axis and only their sum counts
+        let (x, z) = match matrix[(0, 2)].abs() < 0.9999 {
+            true => (
+                (-matrix[(1, 2)]).atan2(matrix[(2, 2)]),
+                (-matrix[(0, 1)]).atan2(matrix[(0, 0)]),
+            ),
+            false => (matrix[(2, 1)].atan2(matrix[(1, 1)]), 0.0),
+        };
+
+        self.set_rotation(glm::vec3(x, y, z));
+    }
+
     pub fn set_scale(&mut self, scale: glm::Vec3) {
         self.scale = scale;
         self.dirty = true;
@@ -106,6 +125,8 @@ impl Default for Transform {
 
 #[cfg(test)]
 mod tests {
+    use std::f32::consts::PI;
+
     use super::*;
 
     #[test]
@@ -182,6 +203,26 @@ mod tests {
         assert_eq!(transform.rotation, glm::vec3(2.0, 4.0, 6.0));
     }
 
+    #[test]
+    fn test_rotation_set_quat() {
+        let rotations = [
+            glm::quat_angle_axis(1.0, &glm::vec3(1.0, 0.0, 0.0)),
+            glm::quat_angle_axis(-2.0, &glm::normalize(&glm::vec3(1.0, 2.0, 3.0))),
+            glm::quat_angle_axis(PI / 2.0, &glm::vec3(0.0, 1.0, 0.0)),
+            glm::quat_angle_axis(PI / 2.0, &glm::vec3(0.0, 1.0, 0.0))
+                * glm::quat_angle_axis(0.5, &glm::vec3(0.0, 0.0, 1.0)),
+        ];
+
+        for rotation in rotations {
+            let mut transform = Transform::new();
+
+            transform.set_rotation_quat(&rotation);
+
+            let expected = glm::quat_to_mat4(&rotation);
+            assert!(glm::abs(&(transform.model_matrix() - expected)).max() < 1e-5);
+        }
+    }
+
     #[test]
     fn test_set_scale() {
         let mut transform = Transform::new();
@@ -1,4 +1,5 @@
 use bevy_ecs::{
+    event::{event_update_system, Events},
     schedule::{IntoSystemConfigs, Schedule},
     world::World,
 };
@@ -11,7 +12,11 @@ use winit::{
 };
 
 use crate::{
-    resources::{AssetManagerResource, ControlInput, GameConfig, RendererResource},
+    events::AssetManagerEvent,
+    resources::{
+        AssetManagerResource, ControlInput, GameConfig, LoadingState, PendingScenes,
+        RendererResource,
+    },
     systems, Time,
 };
 
@@ -144,11 +149,20 @@ impl Engine {
     fn default_world(config: &Config, window: &Window) -> World {
         let mut world = World::new();
 
-        world.insert_resource(AssetManagerResource::default());
+        world.insert_resource(AssetManagerResource::new(config));
+        world.init_resource::<Events<AssetManagerEvent>>();
         world.insert_resource(GameConfig::from(config.clone()));
         world.insert_resource(ControlInput::default());
         world.insert_resource(Time::new());
-        world.insert_non_send_resource(RendererResource::new(config.clone(), window));
+        world.init_resource::<LoadingState>();
+        world.init_resource::<PendingScenes>();
+
+        let renderer = RendererResource::new(
+            config.clone(),
+            window,
+            world.resource::<AssetManagerResource>().asset_manager.vfs(),
+        );
+        world.insert_non_send_resource(renderer);
 
         world
     }
@@ -160,6 +174,13 @@ impl Engine {
     fn default_update_schedule() -> Schedule {
         let mut schedule = Schedule::default();
 
+        // Events are kept for two updates so every system gets to read them
+        schedule.add_systems(
+            event_update_system::<AssetManagerEvent>.before(systems::asset_manager_system),
+        );
+        schedule.add_systems(systems::asset_manager_system);
+        schedule.add_systems(systems::asset_eviction_system.after(systems::asset_manager_system));
+        schedule.add_systems(systems::scene_spawner_system.after(systems::asset_manager_system));
         schedule.add_systems(systems::player_control_system);
         schedule.add_systems(systems::spin_system);
 
@@ -169,6 +190,8 @@ impl Engine {
     fn default_render_schedule() -> Schedule {
         let mut schedule = Schedule::default();
 
+        schedule.add_systems(systems::loading_system.before(systems::renderer_system));
+        schedule.add_systems(systems::scene_material_system.before(systems::renderer_system));
         schedule.add_systems(systems::renderer_system);
 
         schedule
@@ -0,0 +1,6 @@
+use asset_manager::AssetEvent;
+use bevy_ecs::event::Event;
+
+/// Something that happened to an asset, read with an `EventReader<AssetManagerEvent>`
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct AssetManagerEvent(pub AssetEvent);
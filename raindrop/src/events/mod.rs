@@ -0,0 +1,3 @@
+pub mod asset_manager_event;
+
+pub use asset_manager_event::AssetManagerEvent;
@@ -1,14 +1,21 @@
 extern crate log;
 pub extern crate nalgebra_glm as glm;
 
+mod commands;
 pub mod components;
 mod engine;
+mod events;
 pub mod raindrop;
 mod resources;
 mod systems;
 
+pub use asset_manager::{AssetError, AssetEvent, AssetGroup, Handle, LoadProgress};
 pub use bevy_ecs;
+pub use commands::SceneSpawner;
 pub use config::Config;
 pub use engine::ScheduleType;
+pub use events::AssetManagerEvent;
 pub use raindrop::Raindrop;
-pub use resources::{GameConfig, Time};
+pub use resources::{
+    loading_finished, AssetManagerResource, GameConfig, LoadingState, RendererResource, Time,
+};
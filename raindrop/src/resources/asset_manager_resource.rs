@@ -1,7 +1,71 @@
-use asset_manager::AssetManager;
+use std::{path::Path, time::Duration};
+
+use asset_manager::{AssetManager, LodSettings, MeshOptimizations, Vfs};
 use bevy_ecs::system::Resource;
+use config::{Config, MeshOptimizationsConfig};
+use log::warn;
 
 #[derive(Resource, Default)]
 pub struct AssetManagerResource {
-    pub asset_manager: AssetManager
+    pub asset_manager: AssetManager,
+}
+
+impl AssetManagerResource {
+    pub fn new(config: &Config) -> Self {
+        let mut asset_manager = AssetManager::with_loader_threads(config.assets.loader_threads);
+
+        if !config.assets.mounts.is_empty() {
+            let vfs = asset_manager.vfs_mut();
+            *vfs = Vfs::default();
+
+            for mount in &config.assets.mounts {
+                let path = Path::new(&mount.path);
+
+                if path.is_dir() {
+                    vfs.mount_directory(&mount.point, path);
+                } else if let Err(e) = vfs.mount_pack(&mount.point, path) {
+                    warn!("Failed to mount {}: {}", mount.path, e);
+                }
+            }
+        }
+
+        asset_manager
+            .set_default_mesh_optimizations(mesh_optimizations(&config.assets.mesh_optimizations));
+
+        for (id, optimizations) in &config.assets.mesh_optimization_overrides {
+            asset_manager.set_mesh_optimizations(id, mesh_optimizations(optimizations));
+        }
+
+        asset_manager.set_lod_settings(LodSettings {
+            levels: config.assets.lods.levels,
+            reduction: config.assets.lods.reduction,
+            max_error: config.assets.lods.max_error,
+        });
+
+        match Duration::try_from_secs_f32(config.assets.sound_streaming_threshold) {
+            Ok(threshold) => asset_manager.set_sound_streaming_threshold(threshold),
+            Err(e) => warn!("Invalid sound streaming threshold: {}", e),
+        }
+
+        if let Some(cache_directory) = &config.assets.cache_directory {
+            asset_manager.enable_cache(cache_directory);
+        }
+
+        if config.assets.hot_reload {
+            if let Err(e) = asset_manager.enable_hot_reload() {
+                warn!("Failed to enable asset hot reloading: {}", e);
+            }
+        }
+
+        Self { asset_manager }
+    }
+}
+
+fn mesh_optimizations(config: &MeshOptimizationsConfig) -> MeshOptimizations {
+    MeshOptimizations {
+        deduplicate_vertices: config.deduplicate_vertices,
+        optimize_vertex_cache: config.optimize_vertex_cache,
+        optimize_vertex_fetch: config.optimize_vertex_fetch,
+        compute_bounds: config.compute_bounds,
+    }
 }
@@ -0,0 +1,34 @@
+use asset_manager::{AssetError, AssetGroup, AssetManager, Handle, LoadProgress};
+use bevy_ecs::system::{Res, Resource};
+
+/// The group of assets a loading screen waits on, its progress is updated every frame
+///
+/// Start loading a manifest with `LoadingState::load`, then only run gameplay systems
+/// once it is done with `.run_if(loading_finished)`
+#[derive(Resource, Default)]
+pub struct LoadingState {
+    pub group: Option<Handle<AssetGroup>>,
+    pub progress: LoadProgress,
+}
+
+impl LoadingState {
+    /// Load the `LoadManifest` at the path, replacing the group that was waited on
+    pub fn load(&mut self, asset_manager: &AssetManager, manifest: &str) -> Result<(), AssetError> {
+        let group = asset_manager.load_manifest(manifest)?;
+
+        self.group = Some(group);
+        self.progress = asset_manager.group_progress(group).unwrap_or_default();
+
+        Ok(())
+    }
+
+    /// Every asset of the group is loaded, uploaded or has failed
+    pub fn is_ready(&self) -> bool {
+        self.progress.is_ready()
+    }
+}
+
+/// Run condition for systems that need the assets of the `LoadingState` group
+pub fn loading_finished(loading: Res<LoadingState>) -> bool {
+    loading.is_ready()
+}
@@ -1,11 +1,15 @@
 pub mod asset_manager_resource;
 pub mod control_input;
 pub mod game_config;
+pub mod loading_state;
+pub mod pending_scenes;
 pub mod renderer_resource;
 pub mod time;
 
 pub use asset_manager_resource::AssetManagerResource;
 pub use control_input::ControlInput;
 pub use game_config::GameConfig;
+pub use loading_state::{loading_finished, LoadingState};
+pub use pending_scenes::PendingScenes;
 pub use renderer_resource::RendererResource;
 pub use time::Time;
@@ -0,0 +1,9 @@
+use bevy_ecs::system::Resource;
+
+use crate::commands::SceneSpawner;
+
+/// Scenes waiting for their asset to load before `scene_spawner_system` spawns them
+#[derive(Resource, Default)]
+pub struct PendingScenes {
+    pub scenes: Vec<SceneSpawner>,
+}
@@ -1,5 +1,6 @@
 use winit::window::Window;
 
+use asset_manager::Vfs;
 use config::Config;
 
 use renderer::Renderer;
@@ -9,8 +10,8 @@ pub struct RendererResource {
 }
 
 impl RendererResource {
-    pub fn new(config: Config, window: &Window) -> Self {
-        let renderer  = match Renderer::new(&config, window) {
+    pub fn new(config: Config, window: &Window, vfs: &Vfs) -> Self {
+        let renderer = match Renderer::new(&config, window, vfs) {
             Ok(renderer) => renderer,
             Err(e) => panic!("Failed to init renderer: {}", e),
         };
@@ -0,0 +1,8 @@
+use bevy_ecs::system::ResMut;
+
+use crate::resources::AssetManagerResource;
+
+/// Unload assets that no handle references anymore, like the ones held by the components of the world
+pub fn asset_eviction_system(mut asset_manager: ResMut<AssetManagerResource>) {
+    asset_manager.asset_manager.evict_unreferenced();
+}
@@ -0,0 +1,18 @@
+use bevy_ecs::{event::EventWriter, system::Res};
+
+use crate::{events::AssetManagerEvent, resources::AssetManagerResource};
+
+pub fn asset_manager_system(
+    asset_manager: Res<AssetManagerResource>,
+    mut events: EventWriter<AssetManagerEvent>,
+) {
+    asset_manager.asset_manager.update();
+
+    events.send_batch(
+        asset_manager
+            .asset_manager
+            .drain_events()
+            .into_iter()
+            .map(AssetManagerEvent),
+    );
+}
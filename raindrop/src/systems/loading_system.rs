@@ -0,0 +1,49 @@
+use bevy_ecs::system::{NonSendMut, Res, ResMut};
+use log::{info, warn};
+
+use crate::resources::{AssetManagerResource, LoadingState, RendererResource};
+
+/// Upload the meshes and textures of the group being loaded as soon as they are read, so they are
+/// ready before they are first drawn, and update the group's progress
+pub fn loading_system(
+    mut loading: ResMut<LoadingState>,
+    mut renderer: NonSendMut<RendererResource>,
+    asset_manager: Res<AssetManagerResource>,
+) {
+    let group = match loading.group {
+        Some(group) => group,
+        None => return,
+    };
+
+    if let Some(assets) = asset_manager.asset_manager.group(group) {
+        let (meshes, textures): (Vec<_>, Vec<_>) = {
+            let assets = assets.lock().unwrap();
+
+            (assets.meshes().collect(), assets.textures().collect())
+        };
+
+        renderer
+            .renderer
+            .upload_meshes(&meshes, &asset_manager.asset_manager);
+        renderer
+            .renderer
+            .upload_textures(&textures, &asset_manager.asset_manager);
+    }
+
+    let was_ready = loading.is_ready();
+    loading.progress = asset_manager
+        .asset_manager
+        .group_progress(group)
+        .unwrap_or_default();
+
+    if loading.is_ready() && !was_ready {
+        match loading.progress.failed {
+            0 => info!("Loaded {} assets", loading.progress.total),
+            failed => warn!(
+                "Loaded {} assets, {} failed",
+                loading.progress.total - failed,
+                failed
+            ),
+        }
+    }
+}
@@ -1,9 +1,19 @@
+pub mod asset_eviction_system;
+pub mod asset_manager_system;
+pub mod loading_system;
 pub mod player_control_system;
 pub mod renderer_shutdown_system;
 pub mod renderer_system;
+pub mod scene_material_system;
+pub mod scene_spawner_system;
 pub mod spin_system;
 
+pub use asset_eviction_system::asset_eviction_system;
+pub use asset_manager_system::asset_manager_system;
+pub use loading_system::loading_system;
 pub use player_control_system::player_control_system;
 pub use renderer_shutdown_system::renderer_shutdown_system;
 pub use renderer_system::renderer_system;
+pub use scene_material_system::scene_material_system;
+pub use scene_spawner_system::scene_spawner_system;
 pub use spin_system::spin_system;
\ No newline at end of file
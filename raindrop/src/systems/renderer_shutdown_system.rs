@@ -1,13 +1,13 @@
-use bevy_ecs::system::{NonSendMut, ResMut};
+use bevy_ecs::system::{NonSendMut, Res};
 
 use crate::resources::{AssetManagerResource, RendererResource};
 
 pub fn renderer_shutdown_system(
     mut renderer: NonSendMut<RendererResource>,
-    mut asset_manager: ResMut<AssetManagerResource>,
+    asset_manager: Res<AssetManagerResource>,
 ) {
     renderer
         .as_mut()
         .renderer
-        .cleanup(&mut asset_manager.as_mut().asset_manager);
+        .cleanup(&asset_manager.asset_manager);
 }
@@ -1,46 +1,64 @@
+use std::collections::HashMap;
+
 use crate::{
-    components::{Camera, Material, Mesh, Player, Transform},
+    components::{parent, Camera, Material, Mesh, Parent, Player, Transform},
     resources::{AssetManagerResource, RendererResource},
 };
 
 use bevy_ecs::{
+    entity::Entity,
     query::{With, Without},
-    system::{NonSendMut, Query, ResMut},
+    system::{NonSendMut, Query, Res},
 };
 
 use renderer::Renderable;
 
 pub fn renderer_system(
     mut player_camera: Query<(&mut Camera, &mut Transform), With<Player>>,
-    mut renderable_objects: Query<(&mut Transform, &Mesh, &Material), Without<Player>>,
+    mut transforms: Query<(Entity, &mut Transform, Option<&Parent>), Without<Player>>,
+    renderable_objects: Query<(Entity, &Mesh, &Material), Without<Player>>,
     mut renderer: NonSendMut<RendererResource>,
-    mut asset_manager: ResMut<AssetManagerResource>,
+    asset_manager: Res<AssetManagerResource>,
 ) {
     let (camera, mut transform) = player_camera.iter_mut().next().unwrap();
 
     let view_matrix = transform.view_matrix();
     let projection_matrix = camera.matrix();
 
+    let locals: HashMap<Entity, (glm::Mat4, Option<Entity>)> = transforms
+        .iter_mut()
+        .map(|(entity, mut transform, parent)| {
+            (
+                entity,
+                (transform.model_matrix(), parent.map(|parent| parent.entity)),
+            )
+        })
+        .collect();
+    let matrices = parent::world_matrices(&locals);
+
     let mut renderables: Vec<Renderable> = vec![];
-    for (mut transform, mesh, material) in renderable_objects.iter_mut() {
-        renderables.push(Renderable {
-            mesh: mesh.id.clone(),
-            material: material.id.clone(),
-            matrix: transform.model_matrix(),
-        });
+    for (entity, mesh, material) in renderable_objects.iter() {
+        // Entities without a transform are not placed anywhere
+        if let Some(matrix) = matrices.get(&entity) {
+            renderables.push(Renderable {
+                mesh: mesh.handle.weak(),
+                submesh: mesh.submesh,
+                material: material.handle,
+                matrix: *matrix,
+            });
+        }
     }
 
-    renderables
-        .sort_unstable_by_key(|renderable| (renderable.mesh.clone(), renderable.material.clone()));
+    renderables.sort_unstable_by_key(|renderable| (renderable.mesh, renderable.material));
 
     renderer.as_mut().renderer.render(
         projection_matrix,
         view_matrix,
         &renderables,
-        &mut asset_manager.as_mut().asset_manager,
+        &asset_manager.asset_manager,
     );
 
     asset_manager
         .asset_manager
-        .get_audio(&"assets/sounds/CantinaBand60.wav".to_string());
+        .get_audio("assets/sounds/CantinaBand60.wav");
 }
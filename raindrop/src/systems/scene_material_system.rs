@@ -0,0 +1,32 @@
+use bevy_ecs::{
+    entity::Entity,
+    query::Without,
+    system::{Commands, NonSend, Query, Res},
+};
+
+use crate::{
+    components::{Material, SceneMaterial},
+    resources::{AssetManagerResource, RendererResource},
+};
+
+/// Give entities spawned from a scene the renderer material of their imported material
+pub fn scene_material_system(
+    mut commands: Commands,
+    spawned: Query<(Entity, &SceneMaterial), Without<Material>>,
+    renderer: NonSend<RendererResource>,
+    asset_manager: Res<AssetManagerResource>,
+) {
+    let renderer = &renderer.renderer;
+
+    for (entity, scene_material) in spawned.iter() {
+        let handle = scene_material
+            .handle
+            .as_ref()
+            .and_then(|handle| renderer.get_pbr_material(handle, &asset_manager.asset_manager))
+            .or_else(|| renderer.get_material("defaultmesh"));
+
+        if let Some(handle) = handle {
+            commands.entity(entity).insert(Material { handle });
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use asset_manager::AssetStatus;
+use bevy_ecs::world::World;
+use log::warn;
+
+use crate::resources::{AssetManagerResource, PendingScenes};
+
+/// Spawn the scenes of `SceneSpawner` commands whose asset has finished loading
+pub fn scene_spawner_system(world: &mut World) {
+    let pending = match world.get_resource_mut::<PendingScenes>() {
+        Some(mut pending) => std::mem::take(&mut pending.scenes),
+        None => return,
+    };
+
+    let mut waiting = vec![];
+
+    for spawner in pending {
+        let scene = {
+            let asset_manager = &world.resource::<AssetManagerResource>().asset_manager;
+
+            // Requested again every frame so it is not evicted while it loads
+            asset_manager.scene(*asset_manager.get_scene(&spawner.scene))
+        };
+
+        let (status, nodes) = match scene {
+            Some(scene) => {
+                let scene = scene.lock().unwrap();
+
+                (scene.asset_info.status, scene.nodes.clone())
+            }
+            None => (AssetStatus::Unloaded, vec![]),
+        };
+
+        match status {
+            AssetStatus::Unloaded => waiting.push(spawner),
+            // Why it failed was already logged when it loaded
+            AssetStatus::Invalid => warn!("Not spawning scene {}", spawner.scene),
+            _ => {
+                spawner.spawn(world, &nodes);
+            }
+        }
+    }
+
+    world.resource_mut::<PendingScenes>().scenes.extend(waiting);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use bevy_ecs::{entity::Entity, system::Command};
+
+    use super::*;
+    use crate::{
+        commands::SceneSpawner,
+        components::{Mesh, Parent, SceneMaterial, Transform},
+    };
+
+    fn spawn(scene: &str) -> World {
+        let mut world = World::new();
+        world.init_resource::<AssetManagerResource>();
+
+        SceneSpawner::new(scene).apply(&mut world);
+
+        for _ in 0..500 {
+            scene_spawner_system(&mut world);
+
+            if world.resource::<PendingScenes>().scenes.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        world
+    }
+
+    #[test]
+    fn test_spawn_scene() {
+        let mut world = spawn("../asset_manager/fixtures/nested_nodes.gltf#scenes/0");
+
+        // Four nodes, and a child for each of the two submeshes of the last one
+        assert_eq!(world.query::<&Transform>().iter(&world).count(), 6);
+
+        let mut meshes = world.query::<(&Mesh, &Transform, Option<&Parent>)>();
+        let submeshes: Vec<Option<usize>> = meshes
+            .iter(&world)
+            .map(|(mesh, _, _)| mesh.submesh)
+            .collect();
+        assert_eq!(submeshes.len(), 3);
+        assert!(submeshes.contains(&Some(1)));
+
+        // The child node keeps its local translation and is linked to its parent node
+        let (_, transform, parent) = meshes
+            .iter(&world)
+            .find(|(mesh, _, _)| mesh.submesh.is_none())
+            .unwrap();
+        assert_eq!(transform.get_translation(), glm::vec3(0.0, 5.0, 0.0));
+
+        let parent: Entity = parent.unwrap().entity;
+        let parent_transform = world.get::<Transform>(parent).unwrap();
+        assert_eq!(
+            parent_transform.get_translation(),
+            glm::vec3(10.0, 0.0, 0.0)
+        );
+        assert!(world.get::<Parent>(parent).is_none());
+
+        // Every submesh gets its imported material, but the last one, which has none
+        let materials: Vec<Option<String>> = world
+            .query::<&SceneMaterial>()
+            .iter(&world)
+            .map(|material| {
+                material.handle.as_ref().map(|handle| {
+                    world
+                        .resource::<AssetManagerResource>()
+                        .asset_manager
+                        .material(handle.weak())
+                        .unwrap()
+                        .lock()
+                        .unwrap()
+                        .asset_info
+                        .id
+                        .clone()
+                })
+            })
+            .collect();
+        assert_eq!(materials.len(), 3);
+        assert!(materials.contains(&Some(
+            "../asset_manager/fixtures/nested_nodes.gltf#materials/1".to_owned()
+        )));
+        assert!(materials.contains(&None));
+    }
+
+    #[test]
+    fn test_spawn_missing_scene() {
+        let mut world = spawn("../asset_manager/fixtures/nested_nodes.gltf#scenes/1");
+
+        assert!(world.resource::<PendingScenes>().scenes.is_empty());
+        assert_eq!(world.query::<&Transform>().iter(&world).count(), 0);
+    }
+}
@@ -1,15 +1,20 @@
 use ash::{
-    vk::{self, BufferCreateInfo, BufferUsageFlags, ImageCreateInfo, PhysicalDevice},
+    vk::{
+        self, BufferCreateInfo, BufferUsageFlags, Extent3D, Format, ImageAspectFlags,
+        ImageCreateInfo, ImageLayout, ImageSubresourceRange, ImageTiling, ImageType,
+        ImageUsageFlags, ImageViewCreateInfo, ImageViewType, PhysicalDevice, SampleCountFlags,
+    },
     Device, Instance,
 };
 use vk_mem::{Alloc, AllocationCreateInfo};
 
-use gpu_info::Buffer;
+use gpu_info::{Buffer, Image};
 
-use crate::primitives::AllocatedImage;
-use asset_manager::Vertex;
+use crate::primitives::{AllocatedImage, CommandManager};
+use asset_manager::Indices;
 
 pub struct Allocator {
+    device: Device,
     allocator: vk_mem::Allocator,
 }
 
@@ -23,7 +28,10 @@ impl Allocator {
 
         unsafe {
             match vk_mem::Allocator::new(create_info) {
-                Ok(allocator) => Ok(Allocator { allocator }),
+                Ok(allocator) => Ok(Allocator {
+                    device: device.clone(),
+                    allocator,
+                }),
                 Err(e) => Err("Failed to create allocator: ".to_owned() + &e.to_string()),
             }
         }
@@ -50,13 +58,197 @@ impl Allocator {
         }
     }
 
-    pub fn create_vertex_buffer(&self, vertices: &[Vertex]) -> Buffer {
+    /// A sampled image holding the RGBA8 pixels of a texture and of each of its mip levels,
+    /// copied in through a staging buffer with the command manager
+    pub fn create_texture(
+        &self,
+        command_manager: &CommandManager,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        srgb: bool,
+        mips: &[Vec<u8>],
+    ) -> Result<Image, String> {
+        let format = match srgb {
+            true => Format::R8G8B8A8_SRGB,
+            false => Format::R8G8B8A8_UNORM,
+        };
+        let mip_levels = 1 + mips.len() as u32;
+
+        let levels: Vec<&[u8]> = std::iter::once(pixels)
+            .chain(mips.iter().map(Vec::as_slice))
+            .collect();
+        let mut staging_buffer =
+            self.create_buffer_with_data(&levels.concat(), BufferUsageFlags::TRANSFER_SRC);
+
+        let image_create_info = ImageCreateInfo::default()
+            .image_type(ImageType::TYPE_2D)
+            .format(format)
+            .extent(Extent3D {
+                width,
+                height,
+                depth: 1,
+            })
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .samples(SampleCountFlags::TYPE_1)
+            .tiling(ImageTiling::OPTIMAL)
+            .usage(ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED);
+
+        let image_allocation_create_info = AllocationCreateInfo {
+            usage: vk_mem::MemoryUsage::Auto,
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            ..Default::default()
+        };
+
+        let mut image = match self.create_image(&image_create_info, &image_allocation_create_info) {
+            Ok(image) => image,
+            Err(e) => {
+                self.destroy_buffer(&mut staging_buffer);
+                return Err(e);
+            }
+        };
+
+        let subresource_range = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(mip_levels)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        // Every level is half the size of the one before, and follows it in the staging buffer
+        let mut buffer_offset = 0;
+        let copy_regions: Vec<vk::BufferImageCopy> = levels
+            .iter()
+            .enumerate()
+            .map(|(level, pixels)| {
+                let region = vk::BufferImageCopy::default()
+                    .buffer_offset(buffer_offset)
+                    .image_subresource(
+                        vk::ImageSubresourceLayers::default()
+                            .aspect_mask(ImageAspectFlags::COLOR)
+                            .mip_level(level as u32)
+                            .base_array_layer(0)
+                            .layer_count(1),
+                    )
+                    .image_extent(Extent3D {
+                        width: (width >> level).max(1),
+                        height: (height >> level).max(1),
+                        depth: 1,
+                    });
+
+                buffer_offset += pixels.len() as u64;
+
+                region
+            })
+            .collect();
+
+        let uploaded = command_manager.immediate_submit(|device, command_buffer| {
+            let to_transfer = vk::ImageMemoryBarrier::default()
+                .old_layout(ImageLayout::UNDEFINED)
+                .new_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image.image)
+                .subresource_range(subresource_range);
+
+            let to_shader_read = vk::ImageMemoryBarrier::default()
+                .old_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image.image)
+                .subresource_range(subresource_range);
+
+            unsafe {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[to_transfer],
+                );
+                device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    staging_buffer.buffer,
+                    image.image,
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &copy_regions,
+                );
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[to_shader_read],
+                );
+            }
+        });
+
+        // The copy has finished or failed, either way the staging buffer is no longer needed
+        self.destroy_buffer(&mut staging_buffer);
+
+        if let Err(e) = uploaded {
+            self.destroy_image(&mut image);
+            return Err(e);
+        }
+
+        let image_view_create_info = ImageViewCreateInfo::default()
+            .view_type(ImageViewType::TYPE_2D)
+            .image(image.image)
+            .format(format)
+            .subresource_range(subresource_range);
+
+        match unsafe { self.device.create_image_view(&image_view_create_info, None) } {
+            Ok(view) => Ok(Image {
+                image: image.image,
+                allocation: image.allocation,
+                view,
+            }),
+            Err(e) => {
+                self.destroy_image(&mut image);
+                Err("Failed to create image view: ".to_owned() + &e.to_string())
+            }
+        }
+    }
+
+    pub fn destroy_texture(&self, texture: &mut Image) {
+        unsafe {
+            self.device.destroy_image_view(texture.view, None);
+            self.allocator
+                .destroy_image(texture.image, &mut texture.allocation);
+        }
+    }
+
+    pub fn create_vertex_buffer(&self, vertex_data: &[f32]) -> Buffer {
+        self.create_buffer_with_data(vertex_data, BufferUsageFlags::VERTEX_BUFFER)
+    }
+
+    pub fn create_index_buffer(&self, indices: &Indices) -> Buffer {
+        match indices {
+            Indices::U16(indices) => {
+                self.create_buffer_with_data(indices, BufferUsageFlags::INDEX_BUFFER)
+            }
+            Indices::U32(indices) => {
+                self.create_buffer_with_data(indices, BufferUsageFlags::INDEX_BUFFER)
+            }
+        }
+    }
+
+    fn create_buffer_with_data<T: Copy>(&self, data: &[T], usage: BufferUsageFlags) -> Buffer {
         let (buffer, mut allocation) = unsafe {
             self.allocator
                 .create_buffer(
                     &BufferCreateInfo::default()
-                        .size(std::mem::size_of_val(vertices) as u64)
-                        .usage(BufferUsageFlags::VERTEX_BUFFER),
+                        .size(std::mem::size_of_val(data) as u64)
+                        .usage(usage),
                     &AllocationCreateInfo {
                         required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
                         flags: vk_mem::AllocationCreateFlags::MAPPED
@@ -71,9 +263,9 @@ impl Allocator {
         let memory_handle = unsafe { self.allocator.map_memory(&mut allocation).unwrap() };
         unsafe {
             std::ptr::copy_nonoverlapping(
-                vertices.as_ptr() as *const u8,
+                data.as_ptr() as *const u8,
                 memory_handle,
-                std::mem::size_of_val(vertices),
+                std::mem::size_of_val(data),
             );
         }
         unsafe { self.allocator.unmap_memory(&mut allocation) };
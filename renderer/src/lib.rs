@@ -13,6 +13,6 @@ pub mod renderable;
 pub mod renderer;
 
 use boilerplate::Boilerplate;
-use material::Material;
+pub use material::Material;
 pub use renderable::Renderable;
 pub use renderer::Renderer;
\ No newline at end of file
@@ -1,19 +1,34 @@
-use std::{cell::RefCell, rc::Rc};
+use ash::vk::{DescriptorSet, ImageView};
+use asset_manager::{PbrMaterial, StrongHandle, Texture, VertexAttribute};
 
-use super::primitives::Pipeline;
+use crate::primitives::PipelineVariant;
 
 pub struct Material {
-    pub pipeline: Rc<RefCell<Pipeline>>,
-}
-
-impl Drop for Material {
-    fn drop(&mut self) {
-        // We don't need to do anything here because the pipeline will be dropped when the Material is dropped
-    }
+    /// Name of the shaders the material draws with, a pipeline is made from them for every
+    /// vertex layout and variant they are drawn with, see `Renderer::pipeline`
+    pub pipeline: String,
+    /// The vertex attributes the pipeline's shaders take, in order of their locations
+    pub vertex_attributes: Vec<VertexAttribute>,
+    pub variant: PipelineVariant,
+    /// Multiplies the color of every vertex drawn with the material
+    pub base_color: glm::Vec4,
+    /// Fragments with a lower alpha are discarded when the variant masks them
+    pub alpha_cutoff: f32,
+    /// Multiplies the base color, a white texture is sampled instead until it is uploaded
+    pub base_color_texture: Option<StrongHandle<Texture>>,
+    /// The set of texture coordinates the base color texture is sampled with
+    pub base_color_tex_coord: u32,
+    /// The imported material the parameters are kept in sync with, see `Renderer::get_pbr_material`
+    pub source: Option<StrongHandle<PbrMaterial>>,
+    // The id `base_color_texture` was requested for, so it is only requested again when a reload
+    // of the source points the material at another texture
+    pub(crate) base_color_texture_id: Option<String>,
+    // The set binding the sampled texture, along with the view it was written with
+    pub(crate) texture_descriptor: Option<(ImageView, DescriptorSet)>,
 }
 
 impl PartialEq for Material {
     fn eq(&self, other: &Self) -> bool {
-        Rc::ptr_eq(&self.pipeline, &other.pipeline)
+        self.pipeline == other.pipeline && self.variant == other.variant
     }
 }
@@ -1,11 +1,9 @@
-use std::mem::size_of;
-
 use ash::vk::{
     PipelineVertexInputStateCreateFlags, VertexInputAttributeDescription,
     VertexInputBindingDescription, VertexInputRate,
 };
 
-use memoffset::offset_of;
+use asset_manager::{Vertex, VertexAttribute, VertexLayout};
 use serde_derive::Serialize;
 
 pub struct VertexInputDescription {
@@ -14,62 +12,80 @@ pub struct VertexInputDescription {
     pub flags: PipelineVertexInputStateCreateFlags,
 }
 
-#[repr(C, align(16))]
-#[derive(Clone, Copy)]
-pub struct Vertex {
-    pub position: glm::Vec3,
-    pub normal: glm::Vec3,
-    pub color: glm::Vec3,
-}
-
-impl Vertex {
-    pub fn get_vertex_input_description() -> VertexInputDescription {
+impl VertexInputDescription {
+    /// Describe the vertex input of a pipeline taking the given attributes, each from its own
+    /// binding and at the location of its position in the list
+    ///
+    /// Attributes missing from the mesh's layout are read with a stride of 0, so every vertex
+    /// gets the single value bound for them, see `default_vertex_data`
+    pub fn from_layout(
+        attributes: &[VertexAttribute],
+        layout: VertexLayout,
+    ) -> VertexInputDescription {
         let mut vertex_input_description = VertexInputDescription {
             binding_descriptions: vec![],
             attribute_descriptions: vec![],
             flags: PipelineVertexInputStateCreateFlags::empty(),
         };
 
-        vertex_input_description.binding_descriptions.push(
-            VertexInputBindingDescription::default()
-                .binding(0)
-                .stride(size_of::<Vertex>() as u32)
-                .input_rate(VertexInputRate::VERTEX),
-        );
-
-        vertex_input_description.attribute_descriptions.push(
-            VertexInputAttributeDescription::default()
-                .binding(0)
-                .location(0)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Self, position) as u32),
-        );
+        for (index, attribute) in attributes.iter().enumerate() {
+            vertex_input_description.binding_descriptions.push(
+                VertexInputBindingDescription::default()
+                    .binding(index as u32)
+                    .stride(match layout.contains(*attribute) {
+                        true => attribute.size() as u32,
+                        false => 0,
+                    })
+                    .input_rate(VertexInputRate::VERTEX),
+            );
 
-        vertex_input_description.attribute_descriptions.push(
-            VertexInputAttributeDescription::default()
-                .binding(0)
-                .location(1)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Self, normal) as u32),
-        );
+            let format = match attribute.component_count() {
+                2 => ash::vk::Format::R32G32_SFLOAT,
+                3 => ash::vk::Format::R32G32B32_SFLOAT,
+                _ => ash::vk::Format::R32G32B32A32_SFLOAT,
+            };
 
-        vertex_input_description.attribute_descriptions.push(
-            VertexInputAttributeDescription::default()
-                .binding(0)
-                .location(2)
-                .format(ash::vk::Format::R32G32B32_SFLOAT)
-                .offset(offset_of!(Self, color) as u32),
-        );
+            vertex_input_description.attribute_descriptions.push(
+                VertexInputAttributeDescription::default()
+                    .binding(index as u32)
+                    .location(index as u32)
+                    .format(format)
+                    .offset(0),
+            );
+        }
 
         vertex_input_description
     }
 }
 
+/// The default value of every attribute, one after the other in the order of
+/// `VertexAttribute::ALL`, bound for the attributes a mesh does not have
+pub fn default_vertex_data() -> Vec<f32> {
+    let vertex = Vertex::default();
+
+    VertexAttribute::ALL
+        .iter()
+        .flat_map(|attribute| attribute.read(&vertex).iter().copied())
+        .collect()
+}
+
+/// Offset in bytes of the attribute's value in the data returned by `default_vertex_data`
+pub fn default_attribute_offset(attribute: VertexAttribute) -> u64 {
+    VertexAttribute::ALL
+        .iter()
+        .take_while(|existing| **existing != attribute)
+        .map(|existing| existing.size() as u64)
+        .sum()
+}
+
 #[repr(C, align(16))]
 #[derive(Clone, Copy, Serialize)]
 pub struct MeshPushConstants {
     pub data: glm::Vec4,
     pub render_matrix: glm::Mat4,
+    /// The alpha cutoff of masked materials, and the set of texture coordinates their base color
+    /// texture is sampled with
+    pub parameters: glm::Vec4,
 }
 
 impl MeshPushConstants {}
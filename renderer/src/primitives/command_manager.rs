@@ -1,8 +1,8 @@
 use ash::{
     vk::{
-        self, CommandBufferResetFlags, CommandBufferUsageFlags, Fence, PipelineBindPoint,
-        PipelineLayout, PipelineStageFlags, RenderPassBeginInfo, Semaphore, ShaderStageFlags,
-        SubmitInfo, SubpassContents,
+        self, CommandBufferResetFlags, CommandBufferUsageFlags, DescriptorSet, Fence,
+        PipelineBindPoint, PipelineLayout, PipelineStageFlags, RenderPassBeginInfo, Semaphore,
+        ShaderStageFlags, SubmitInfo, SubpassContents,
     },
     Device,
 };
@@ -144,6 +144,72 @@ impl CommandManager {
         }
     }
 
+    /// Record commands into a command buffer of their own and wait for the GPU to run them,
+    /// for uploads made outside of a frame's command buffer
+    pub fn immediate_submit(
+        &self,
+        record: impl FnOnce(&Device, vk::CommandBuffer),
+    ) -> Result<(), String> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::default()
+            .command_pool(self.main_command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+
+        let command_buffer = match unsafe {
+            self.device
+                .allocate_command_buffers(&command_buffer_allocate_info)
+        } {
+            Ok(buffer) => buffer[0],
+            Err(_) => return Err("Failed to allocate command buffer".to_string()),
+        };
+
+        let fence = match unsafe {
+            self.device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+        } {
+            Ok(fence) => fence,
+            Err(e) => {
+                unsafe {
+                    self.device
+                        .free_command_buffers(self.main_command_pool, &[command_buffer])
+                };
+                return Err("Failed to create fence: ".to_owned() + &e.to_string());
+            }
+        };
+
+        let begin_info =
+            vk::CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        let command_buffers = [command_buffer];
+        let submit_info = SubmitInfo::default().command_buffers(&command_buffers);
+
+        let result = unsafe {
+            self.device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .and_then(|_| {
+                    record(&self.device, command_buffer);
+
+                    self.device.end_command_buffer(command_buffer)
+                })
+                .and_then(|_| {
+                    self.device
+                        .queue_submit(self.queue.main_queue, &[submit_info], fence)
+                })
+                .and_then(|_| self.device.wait_for_fences(&[fence], true, u64::MAX))
+        };
+
+        unsafe {
+            self.device.destroy_fence(fence, None);
+            self.device
+                .free_command_buffers(self.main_command_pool, &command_buffers);
+        }
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err("Failed to submit command buffer: ".to_owned() + &e.to_string()),
+        }
+    }
+
     pub fn bind_pipeline(&self, pipeline: &Pipeline) {
         unsafe {
             self.device.cmd_bind_pipeline(
@@ -154,6 +220,19 @@ impl CommandManager {
         };
     }
 
+    pub fn bind_descriptor_set(&self, layout: PipelineLayout, set_index: u32, set: DescriptorSet) {
+        unsafe {
+            self.device.cmd_bind_descriptor_sets(
+                self.main_command_buffer,
+                PipelineBindPoint::GRAPHICS,
+                layout,
+                set_index,
+                &[set],
+                &[],
+            )
+        };
+    }
+
     pub fn bind_vertex_buffers(&self, first_binding: u32, buffers: &[vk::Buffer], offsets: &[u64]) {
         unsafe {
             self.device.cmd_bind_vertex_buffers(
@@ -165,6 +244,13 @@ impl CommandManager {
         };
     }
 
+    pub fn bind_index_buffer(&self, buffer: vk::Buffer, offset: u64, index_type: vk::IndexType) {
+        unsafe {
+            self.device
+                .cmd_bind_index_buffer(self.main_command_buffer, buffer, offset, index_type)
+        };
+    }
+
     pub fn push_constants<T: Serialize>(&self, layout: PipelineLayout, constants: T) {
         let bytes = bincode::serialize(&constants).unwrap();
 
@@ -179,19 +265,21 @@ impl CommandManager {
         }
     }
 
-    pub fn draw(
+    pub fn draw_indexed(
         &self,
-        vertex_count: u32,
+        index_count: u32,
         instance_count: u32,
-        first_vertex: u32,
+        first_index: u32,
+        vertex_offset: i32,
         first_instance: u32,
     ) {
         unsafe {
-            self.device.cmd_draw(
+            self.device.cmd_draw_indexed(
                 self.main_command_buffer,
-                vertex_count,
+                index_count,
                 instance_count,
-                first_vertex,
+                first_index,
+                vertex_offset,
                 first_instance,
             )
         };
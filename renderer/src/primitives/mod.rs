@@ -5,11 +5,13 @@ pub mod queue;
 pub mod shader;
 pub mod surface;
 pub mod swapchain;
+pub mod texture_descriptors;
 
 pub use allocated_image::AllocatedImage;
 pub use command_manager::CommandManager;
-pub use pipeline::Pipeline;
+pub use pipeline::{AlphaBlend, Pipeline, PipelineVariant};
 pub use queue::Queue;
 pub use shader::Shader;
 pub use surface::Surface;
 pub use swapchain::Swapchain;
+pub use texture_descriptors::TextureDescriptors;
@@ -2,14 +2,15 @@ use std::mem::size_of;
 
 use ash::{
     vk::{
-        self, ColorComponentFlags, CullModeFlags, Extent2D, FrontFace, GraphicsPipelineCreateInfo,
-        LogicOp, Offset2D, PipelineCache, PipelineColorBlendAttachmentState,
-        PipelineColorBlendStateCreateInfo, PipelineDepthStencilStateCreateInfo,
-        PipelineInputAssemblyStateCreateInfo, PipelineLayoutCreateFlags,
-        PipelineMultisampleStateCreateInfo, PipelineRasterizationStateCreateInfo,
-        PipelineShaderStageCreateInfo, PipelineVertexInputStateCreateInfo,
-        PipelineViewportStateCreateInfo, PolygonMode, PrimitiveTopology, Rect2D, RenderPass,
-        SampleCountFlags, Viewport,
+        self, BlendFactor, BlendOp, ColorComponentFlags, CullModeFlags, DescriptorSetLayout,
+        Extent2D, FrontFace, GraphicsPipelineCreateInfo, LogicOp, Offset2D, PipelineCache,
+        PipelineColorBlendAttachmentState, PipelineColorBlendStateCreateInfo,
+        PipelineDepthStencilStateCreateInfo, PipelineInputAssemblyStateCreateInfo,
+        PipelineLayoutCreateFlags, PipelineMultisampleStateCreateInfo,
+        PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo,
+        PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode,
+        PrimitiveTopology, Rect2D, RenderPass, SampleCountFlags, SpecializationInfo,
+        SpecializationMapEntry, Viewport,
     },
     Device,
 };
@@ -18,6 +19,28 @@ use crate::mesh::{MeshPushConstants, VertexInputDescription};
 
 use super::Shader;
 
+/// How the fragments of a pipeline are combined with what is already drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AlphaBlend {
+    /// Replaces what is behind, alpha is ignored
+    #[default]
+    Opaque,
+    /// Like opaque, but fragments with an alpha below the cutoff pushed by the material are
+    /// discarded. Shaders see it as the `ALPHA_MASK` specialization constant
+    Mask,
+    /// Blended over what is behind, without writing depth
+    Blend,
+}
+
+/// The fixed function state that differs between materials drawn with the same shaders,
+/// a pipeline is made for every variant they are drawn with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PipelineVariant {
+    pub alpha: AlphaBlend,
+    /// Back faces are drawn as well instead of being culled
+    pub double_sided: bool,
+}
+
 #[derive(Clone)]
 pub struct Pipeline {
     device: Device,
@@ -31,9 +54,10 @@ impl Pipeline {
         device: &Device,
         shaders: &[&Shader],
         render_pass: &RenderPass,
-        width: u32,
-        height: u32,
+        extent: Extent2D,
         vertex_input_description: &VertexInputDescription,
+        set_layouts: &[DescriptorSetLayout],
+        variant: PipelineVariant,
     ) -> Result<Pipeline, String> {
         let push_constant_ranges = [ash::vk::PushConstantRange::default()
             .stage_flags(ash::vk::ShaderStageFlags::VERTEX)
@@ -43,7 +67,7 @@ impl Pipeline {
         let pipeline_layout_create_info = ash::vk::PipelineLayoutCreateInfo::default()
             .flags(PipelineLayoutCreateFlags::empty())
             .push_constant_ranges(&push_constant_ranges)
-            .set_layouts(&[]);
+            .set_layouts(set_layouts);
 
         let pipeline_layout =
             match unsafe { device.create_pipeline_layout(&pipeline_layout_create_info, None) } {
@@ -53,8 +77,16 @@ impl Pipeline {
                 }
             };
 
+        let blend = variant.alpha == AlphaBlend::Blend;
+
         let color_blend_attachment_states = [PipelineColorBlendAttachmentState::default()
-            .blend_enable(false)
+            .blend_enable(blend)
+            .src_color_blend_factor(BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(BlendOp::ADD)
+            .src_alpha_blend_factor(BlendFactor::ONE)
+            .dst_alpha_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(BlendOp::ADD)
             .color_write_mask(ColorComponentFlags::RGBA)];
 
         let color_blend_state = PipelineColorBlendStateCreateInfo::default()
@@ -74,7 +106,10 @@ impl Pipeline {
             .min_sample_shading(1.0);
 
         let rasterization_state_create_info = PipelineRasterizationStateCreateInfo::default()
-            .cull_mode(CullModeFlags::NONE)
+            .cull_mode(match variant.double_sided {
+                true => CullModeFlags::NONE,
+                false => CullModeFlags::BACK,
+            })
             .depth_clamp_enable(false)
             .depth_bias_enable(false)
             .depth_bias_constant_factor(0.0)
@@ -92,29 +127,43 @@ impl Pipeline {
         let viewports = [Viewport {
             x: 0.0,
             y: 0.0,
-            width: width as f32,
-            height: height as f32,
+            width: extent.width as f32,
+            height: extent.height as f32,
             min_depth: 0.0,
             max_depth: 1.0,
         }];
 
         let scissors = [Rect2D {
             offset: Offset2D { x: 0, y: 0 },
-            extent: Extent2D { width, height },
+            extent,
         }];
 
         let viewport_state_create_info = PipelineViewportStateCreateInfo::default()
             .viewports(&viewports)
             .scissors(&scissors);
 
+        // Constant 0 is `ALPHA_MASK`, stages that do not declare it ignore it
+        let specialization_map_entries = [SpecializationMapEntry::default()
+            .constant_id(0)
+            .offset(0)
+            .size(size_of::<vk::Bool32>())];
+        let specialization_data = ((variant.alpha == AlphaBlend::Mask) as vk::Bool32).to_ne_bytes();
+        let specialization_info = SpecializationInfo::default()
+            .map_entries(&specialization_map_entries)
+            .data(&specialization_data);
+
         let shader_stage_create_infos = shaders
             .iter()
-            .map(|shader| shader.stage_create_info())
+            .map(|shader| {
+                shader
+                    .stage_create_info()
+                    .specialization_info(&specialization_info)
+            })
             .collect::<Vec<PipelineShaderStageCreateInfo>>();
 
         let depth_stencil_state = PipelineDepthStencilStateCreateInfo::default()
             .depth_test_enable(true)
-            .depth_write_enable(true)
+            .depth_write_enable(!blend)
             .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
             .depth_bounds_test_enable(false)
             .min_depth_bounds(0.0)
@@ -1,9 +1,10 @@
-use std::{ffi::CString, fs, path::Path};
+use std::{ffi::CString, path::Path};
 
 use ash::{
     vk::{PipelineShaderStageCreateInfo, ShaderModule, ShaderModuleCreateInfo, ShaderStageFlags},
     Device,
 };
+use asset_manager::Vfs;
 
 pub struct Shader {
     device: Device,
@@ -16,7 +17,7 @@ lazy_static! {
 }
 
 impl Shader {
-    pub fn from_path(device: &Device, path: &str) -> Result<Shader, String> {
+    pub fn from_path(device: &Device, vfs: &Vfs, path: &str) -> Result<Shader, String> {
         let file_ending = match Path::new(path).extension() {
             Some(file_ending) => file_ending.to_str().unwrap(),
             None => "vert",
@@ -30,7 +31,10 @@ impl Shader {
             }
         };
 
-        let shader_text = fs::read_to_string(path).expect("Failed to load vertex shader");
+        let shader_text = match vfs.read_to_string(path) {
+            Ok(shader_text) => shader_text,
+            Err(e) => return Err(format!("Failed to read shader {}: {}", path, e)),
+        };
 
         let compiler = shaderc::Compiler::new().unwrap();
         let mut compiler_options = shaderc::CompileOptions::new().unwrap();
@@ -53,8 +57,8 @@ impl Shader {
             )
             .unwrap();
 
-        let shader_module_create_info = ShaderModuleCreateInfo::default()
-            .code(spirv_binary_data.as_binary());
+        let shader_module_create_info =
+            ShaderModuleCreateInfo::default().code(spirv_binary_data.as_binary());
 
         let module = match unsafe { device.create_shader_module(&shader_module_create_info, None) }
         {
@@ -0,0 +1,132 @@
+use ash::{
+    vk::{
+        self, DescriptorPool, DescriptorSet, DescriptorSetLayout, DescriptorType, Filter,
+        ImageLayout, ImageView, Sampler, SamplerAddressMode, SamplerMipmapMode, ShaderStageFlags,
+    },
+    Device,
+};
+use log::warn;
+
+/// How many textures can be bound by materials at once
+const MAX_TEXTURE_SETS: u32 = 1024;
+
+/// Descriptor sets binding a single texture for fragment shaders to sample, as
+/// `layout(set = 0, binding = 0) uniform sampler2D`
+pub struct TextureDescriptors {
+    device: Device,
+    pub set_layout: DescriptorSetLayout,
+    pool: DescriptorPool,
+    sampler: Sampler,
+}
+
+impl TextureDescriptors {
+    pub fn new(device: &Device) -> Result<TextureDescriptors, String> {
+        let bindings = [vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(ShaderStageFlags::FRAGMENT)];
+
+        let set_layout = match unsafe {
+            device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+                None,
+            )
+        } {
+            Ok(set_layout) => set_layout,
+            Err(e) => return Err(format!("Failed to create descriptor set layout: {}", e)),
+        };
+
+        let pool_sizes = [vk::DescriptorPoolSize::default()
+            .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(MAX_TEXTURE_SETS)];
+
+        // Sets are freed one by one as the textures they bind are replaced
+        let pool_create_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(MAX_TEXTURE_SETS)
+            .pool_sizes(&pool_sizes);
+
+        let pool = match unsafe { device.create_descriptor_pool(&pool_create_info, None) } {
+            Ok(pool) => pool,
+            Err(e) => {
+                unsafe { device.destroy_descriptor_set_layout(set_layout, None) };
+                return Err(format!("Failed to create descriptor pool: {}", e));
+            }
+        };
+
+        let sampler_create_info = vk::SamplerCreateInfo::default()
+            .mag_filter(Filter::LINEAR)
+            .min_filter(Filter::LINEAR)
+            .mipmap_mode(SamplerMipmapMode::LINEAR)
+            .address_mode_u(SamplerAddressMode::REPEAT)
+            .address_mode_v(SamplerAddressMode::REPEAT)
+            .address_mode_w(SamplerAddressMode::REPEAT)
+            .max_lod(vk::LOD_CLAMP_NONE);
+
+        let sampler = match unsafe { device.create_sampler(&sampler_create_info, None) } {
+            Ok(sampler) => sampler,
+            Err(e) => {
+                unsafe {
+                    device.destroy_descriptor_pool(pool, None);
+                    device.destroy_descriptor_set_layout(set_layout, None);
+                }
+                return Err(format!("Failed to create sampler: {}", e));
+            }
+        };
+
+        Ok(TextureDescriptors {
+            device: device.clone(),
+            set_layout,
+            pool,
+            sampler,
+        })
+    }
+
+    /// A set binding the image view, to be freed once no frame in flight uses it anymore
+    pub fn allocate(&self, view: ImageView) -> Result<DescriptorSet, String> {
+        let set_layouts = [self.set_layout];
+
+        let set = match unsafe {
+            self.device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(self.pool)
+                    .set_layouts(&set_layouts),
+            )
+        } {
+            Ok(sets) => sets[0],
+            Err(e) => return Err(format!("Failed to allocate descriptor set: {}", e)),
+        };
+
+        let image_infos = [vk::DescriptorImageInfo::default()
+            .sampler(self.sampler)
+            .image_view(view)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(set)
+            .dst_binding(0)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_infos);
+
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+
+        Ok(set)
+    }
+
+    pub fn free(&self, set: DescriptorSet) {
+        if let Err(e) = unsafe { self.device.free_descriptor_sets(self.pool, &[set]) } {
+            warn!("Failed to free descriptor set: {}", e);
+        }
+    }
+
+    /// Destroy the pool, and with it every set still allocated from it
+    pub fn destroy(&self) {
+        unsafe {
+            self.device.destroy_sampler(self.sampler, None);
+            self.device.destroy_descriptor_pool(self.pool, None);
+            self.device
+                .destroy_descriptor_set_layout(self.set_layout, None);
+        }
+    }
+}
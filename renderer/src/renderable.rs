@@ -1,5 +1,11 @@
+use asset_manager::{Handle, Mesh};
+
+use crate::Material;
+
 pub struct Renderable {
-    pub mesh: String,
-    pub material: String,
+    pub mesh: Handle<Mesh>,
+    /// Only this submesh of the mesh is drawn, every submesh when None
+    pub submesh: Option<usize>,
+    pub material: Handle<Material>,
     pub matrix: glm::Mat4,
 }
@@ -1,4 +1,9 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 use ash::{
     vk::{
@@ -9,34 +14,69 @@ use ash::{
     },
     Device,
 };
-use asset_manager::AssetManager;
-use log::trace;
+use asset_manager::{
+    AlphaMode, AssetManager, AssetStorage, Handle, Indices, Mesh, PbrMaterial, StrongHandle,
+    Submesh, Texture, VertexAttribute, VertexLayout, Vfs,
+};
+use log::{trace, warn};
 
 use config::Config;
+use gpu_info::{Buffer, Image};
 
 use crate::Boilerplate;
 use crate::Material;
 use crate::Renderable;
 use crate::{boilerplate::frame_data::FrameData, mesh::MeshPushConstants};
 use crate::{
-    mesh::Vertex,
-    primitives::{Pipeline, Shader, Swapchain},
+    mesh::{self, VertexInputDescription},
+    primitives::{AlphaBlend, Pipeline, PipelineVariant, Shader, Swapchain, TextureDescriptors},
 };
 
+/// The vertex attributes the shaders of materials made by `Renderer::get_pbr_material` take
+const PBR_VERTEX_ATTRIBUTES: [VertexAttribute; 5] = [
+    VertexAttribute::Position,
+    VertexAttribute::Normal,
+    VertexAttribute::Color,
+    VertexAttribute::TexCoord0,
+    VertexAttribute::TexCoord1,
+];
+
+/// The pipelines made from a set of shaders, by the vertex layout and variant they draw
+type PipelineVariants = HashMap<(VertexLayout, PipelineVariant), Rc<RefCell<Pipeline>>>;
+
 pub struct Renderer {
     config: Config,
     boilerplate: Boilerplate,
     render_pass: RenderPass,
     framebuffers: Vec<Framebuffer>,
-    pipelines: HashMap<String, Rc<RefCell<Pipeline>>>,
-    materials: HashMap<String, Rc<RefCell<Material>>>,
+    // The shaders of every named pipeline, kept to make it for the vertex layouts and variants
+    // it draws
+    pipeline_shaders: HashMap<String, Vec<Shader>>,
+    pipelines: HashMap<String, PipelineVariants>,
+    materials: AssetStorage<Material>,
+    // Holds the default value of every vertex attribute, bound for those a mesh does not have
+    default_vertex: Buffer,
+    texture_descriptors: TextureDescriptors,
+    // Sampled by materials without a texture, or whose texture is not uploaded yet
+    white_texture: Image,
     framenumber: u64,
     mesh_binds: u64,
     material_binds: u64,
+    // Buffers replaced by a re-upload, along with the frame they were replaced on
+    retired_buffers: Vec<(u64, Buffer)>,
+    // Images of unloaded or re-uploaded textures, along with the frame they were released on
+    retired_images: Vec<(u64, Image)>,
+    // Descriptor sets of materials whose texture changed, along with the frame they were replaced on
+    retired_descriptors: Vec<(u64, vk::DescriptorSet)>,
 }
 
 impl Renderer {
-    pub fn new(config: &Config, window: &winit::window::Window) -> Result<Renderer, String> {
+    /// Shaders are read through the virtual filesystem, like every other asset
+    pub fn new(
+        config: &Config,
+        window: &winit::window::Window,
+        vfs: &Vfs,
+    ) -> Result<Renderer, String> {
         trace!("Initializing: Renderer");
 
         let boilerplate = match Boilerplate::new(config, window) {
@@ -60,58 +100,130 @@ impl Renderer {
         };
 
         let vertex_shader =
-            match Shader::from_path(&boilerplate.device, "assets/shaders/tri_mesh.vert") {
+            match Shader::from_path(&boilerplate.device, vfs, "assets/shaders/tri_mesh.vert") {
                 Ok(shader) => shader,
                 Err(e) => {
                     return Err("Failed to create vertex shader: ".to_owned() + &e.to_string())
                 }
             };
 
-        let color_fragment_shader =
-            match Shader::from_path(&boilerplate.device, "assets/shaders/colored_triangle.frag") {
+        let color_fragment_shader = match Shader::from_path(
+            &boilerplate.device,
+            vfs,
+            "assets/shaders/colored_triangle.frag",
+        ) {
+            Ok(shader) => shader,
+            Err(e) => return Err("Failed to create fragment shader: ".to_owned() + &e.to_string()),
+        };
+
+        let pbr_vertex_shader =
+            match Shader::from_path(&boilerplate.device, vfs, "assets/shaders/pbr_mesh.vert") {
+                Ok(shader) => shader,
+                Err(e) => {
+                    return Err("Failed to create vertex shader: ".to_owned() + &e.to_string())
+                }
+            };
+
+        let pbr_fragment_shader =
+            match Shader::from_path(&boilerplate.device, vfs, "assets/shaders/pbr_mesh.frag") {
                 Ok(shader) => shader,
                 Err(e) => {
                     return Err("Failed to create fragment shader: ".to_owned() + &e.to_string())
                 }
             };
 
-        let mesh_pipeline = match Pipeline::new(
-            &boilerplate.device,
-            &[&vertex_shader, &color_fragment_shader],
-            &render_pass,
-            boilerplate.swapchain.extent.width,
-            boilerplate.swapchain.extent.height,
-            &Vertex::get_vertex_input_description(),
-        ) {
-            Ok(pipeline) => pipeline,
-            Err(e) => return Err("Failed to create pipeline: ".to_owned() + &e.to_string()),
-        };
+        let mesh_vertex_attributes = vec![
+            VertexAttribute::Position,
+            VertexAttribute::Normal,
+            VertexAttribute::Color,
+        ];
 
-        let mut pipelines = HashMap::new();
-        pipelines.insert(
+        let mut pipeline_shaders = HashMap::new();
+        pipeline_shaders.insert(
             "meshpipeline".to_string(),
-            Rc::new(RefCell::new(mesh_pipeline)),
+            vec![vertex_shader, color_fragment_shader],
+        );
+        pipeline_shaders.insert(
+            "pbrpipeline".to_string(),
+            vec![pbr_vertex_shader, pbr_fragment_shader],
         );
 
-        let mut materials = HashMap::new();
+        let materials = AssetStorage::new();
         materials.insert(
-            "defaultmesh".to_string(),
-            Rc::new(RefCell::new(Material {
-                pipeline: Rc::clone(pipelines.get("meshpipeline").unwrap()),
-            })),
+            "defaultmesh",
+            Material {
+                pipeline: "meshpipeline".to_string(),
+                vertex_attributes: mesh_vertex_attributes.clone(),
+                variant: PipelineVariant {
+                    alpha: AlphaBlend::Opaque,
+                    double_sided: true,
+                },
+                base_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+                alpha_cutoff: 0.0,
+                base_color_texture: None,
+                base_color_tex_coord: 0,
+                source: None,
+                base_color_texture_id: None,
+                texture_descriptor: None,
+            },
         );
 
-        Ok(Renderer {
+        let default_vertex = boilerplate
+            .allocator
+            .create_vertex_buffer(&mesh::default_vertex_data());
+
+        let texture_descriptors = match TextureDescriptors::new(&boilerplate.device) {
+            Ok(texture_descriptors) => texture_descriptors,
+            Err(e) => return Err("Failed to init renderer: descriptors: ".to_owned() + &e),
+        };
+
+        let white_texture = match boilerplate.allocator.create_texture(
+            &boilerplate.frame_data[0].command_manager,
+            &[255; 4],
+            1,
+            1,
+            false,
+            &[],
+        ) {
+            Ok(white_texture) => white_texture,
+            Err(e) => return Err("Failed to init renderer: white texture: ".to_owned() + &e),
+        };
+
+        let mut renderer = Renderer {
             config: config.clone(),
             boilerplate,
             render_pass,
             framebuffers,
-            pipelines,
+            pipeline_shaders,
+            pipelines: HashMap::new(),
             materials,
+            default_vertex,
+            texture_descriptors,
+            white_texture,
             framenumber: 0,
             mesh_binds: 0,
             material_binds: 0,
-        })
+            retired_buffers: vec![],
+            retired_images: vec![],
+            retired_descriptors: vec![],
+        };
+
+        // Made right away so broken shaders are reported at startup rather than on the first draw
+        for (name, attributes) in [
+            ("meshpipeline", mesh_vertex_attributes),
+            ("pbrpipeline", PBR_VERTEX_ATTRIBUTES.to_vec()),
+        ] {
+            if let Err(e) = renderer.pipeline(
+                name,
+                &attributes,
+                VertexLayout::ALL,
+                PipelineVariant::default(),
+            ) {
+                return Err("Failed to create pipeline: ".to_owned() + &e);
+            }
+        }
+
+        Ok(renderer)
     }
 
     fn init_render_pass(device: &Device, swapchain: &Swapchain) -> Result<RenderPass, String> {
@@ -216,67 +328,408 @@ impl Renderer {
         Ok(framebuffers)
     }
 
+    pub fn get_material(&self, name: &str) -> Option<Handle<Material>> {
+        self.materials.handle(name)
+    }
+
+    /// The material drawing an imported material, made the first time it is asked for and named
+    /// after the asset's id. Its parameters follow the asset as it loads and reloads, which it
+    /// keeps loaded
+    ///
+    /// The base color factor and texture are applied, along with the alpha mode and whether the
+    /// material is double sided, which pick the pipeline variant it is drawn with
+    pub fn get_pbr_material(
+        &self,
+        handle: &StrongHandle<PbrMaterial>,
+        asset_manager: &AssetManager,
+    ) -> Option<Handle<Material>> {
+        let id = asset_manager
+            .material(handle.weak())?
+            .lock()
+            .unwrap()
+            .asset_info
+            .id
+            .clone();
+
+        // Drawn with the defaults until the asset is loaded, see `sync_material`
+        let (material, _) = self.materials.get_or_insert_with(&id, || Material {
+            pipeline: "pbrpipeline".to_string(),
+            vertex_attributes: PBR_VERTEX_ATTRIBUTES.to_vec(),
+            variant: PipelineVariant::default(),
+            base_color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            alpha_cutoff: 0.0,
+            base_color_texture: None,
+            base_color_tex_coord: 0,
+            source: Some(handle.clone()),
+            base_color_texture_id: None,
+            texture_descriptor: None,
+        });
+
+        Some(material)
+    }
+
+    /// The imported material a renderer material follows, if it was made by `get_pbr_material`
+    pub fn pbr_material_source(&self, material: Handle<Material>) -> Option<Handle<PbrMaterial>> {
+        self.materials
+            .get(material)?
+            .lock()
+            .unwrap()
+            .source
+            .as_ref()
+            .map(|source| source.weak())
+    }
+
     fn current_frame_data(&self) -> &FrameData {
         &self.boilerplate.frame_data
             [(self.framenumber % self.config.renderer.frame_overlap as u64) as usize]
     }
 
+    /// The pipeline drawing the attributes with the named shaders from meshes with the layout,
+    /// made the first time such a mesh is drawn with them in the variant
+    fn pipeline(
+        &mut self,
+        name: &str,
+        attributes: &[VertexAttribute],
+        layout: VertexLayout,
+        variant: PipelineVariant,
+    ) -> Result<Rc<RefCell<Pipeline>>, String> {
+        // Only whether the attributes the shaders read are there matters
+        let present: Vec<VertexAttribute> = attributes
+            .iter()
+            .copied()
+            .filter(|attribute| layout.contains(*attribute))
+            .collect();
+        let layout = VertexLayout::new(&present);
+
+        if let Some(pipeline) = self
+            .pipelines
+            .get(name)
+            .and_then(|layouts| layouts.get(&(layout, variant)))
+        {
+            return Ok(Rc::clone(pipeline));
+        }
+
+        let shaders: Vec<&Shader> = match self.pipeline_shaders.get(name) {
+            Some(shaders) => shaders.iter().collect(),
+            None => return Err(format!("Unknown pipeline {}", name)),
+        };
+
+        let pipeline = Rc::new(RefCell::new(Pipeline::new(
+            &self.boilerplate.device,
+            &shaders,
+            &self.render_pass,
+            self.boilerplate.swapchain.extent,
+            &VertexInputDescription::from_layout(attributes, layout),
+            &[self.texture_descriptors.set_layout],
+            variant,
+        )?));
+
+        self.pipelines
+            .entry(name.to_owned())
+            .or_default()
+            .insert((layout, variant), Rc::clone(&pipeline));
+
+        Ok(pipeline)
+    }
+
     fn bind_renderable_mesh(
         &mut self,
         renderable: &Renderable,
-        asset_manager: &mut AssetManager,
-    ) -> (bool, String, u32) {
-        let mesh_handle = asset_manager.get_mesh(&renderable.mesh);
+        material: &Material,
+        bound_pipeline: &mut Option<Rc<RefCell<Pipeline>>>,
+        asset_manager: &AssetManager,
+    ) -> (bool, Vec<Submesh>) {
+        let mesh_handle = match asset_manager.mesh(renderable.mesh) {
+            Some(mesh_handle) => mesh_handle,
+            None => return (false, vec![]),
+        };
         let lock = mesh_handle.lock();
         let mut mesh = lock.unwrap();
 
-        if mesh.needs_uploaded() {
-            let vertices = mesh.vertices.clone();
-
-            mesh.add_gpu_info(self.boilerplate.allocator.create_vertex_buffer(&vertices));
-        }
+        self.upload_mesh(&mut mesh);
 
         let mut can_be_drawn = false;
 
-        if mesh.gpu_info.is_some() {
+        if let (Some(vertex_buffer), Some(index_buffer)) = (&mesh.gpu_info, &mesh.index_gpu_info) {
             let offset = 0;
-            let buffer = mesh.gpu_info.as_mut().unwrap().buffer;
+
+            let index_type = match mesh.indices {
+                Indices::U16(_) => vk::IndexType::UINT16,
+                Indices::U32(_) => vk::IndexType::UINT32,
+            };
+
+            let pipeline = match self.pipeline(
+                &material.pipeline,
+                &material.vertex_attributes,
+                mesh.vertex_layout,
+                material.variant,
+            ) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    warn!("Not drawing mesh {}: {}", mesh.asset_info.id, e);
+                    return (false, vec![]);
+                }
+            };
+
+            if !bound_pipeline
+                .as_ref()
+                .is_some_and(|bound| Rc::ptr_eq(bound, &pipeline))
+            {
+                self.current_frame_data()
+                    .command_manager
+                    .bind_pipeline(&pipeline.borrow());
+
+                *bound_pipeline = Some(pipeline);
+            }
+
+            // Every attribute is a separate stream of the same buffer, only the ones the
+            // material uses are bound. Those the mesh does not have come from the default vertex
+            let (buffers, offsets): (Vec<vk::Buffer>, Vec<u64>) = material
+                .vertex_attributes
+                .iter()
+                .map(|attribute| match mesh.attribute_offset(*attribute) {
+                    Some(offset) => (vertex_buffer.buffer, offset),
+                    None => (
+                        self.default_vertex.buffer,
+                        mesh::default_attribute_offset(*attribute),
+                    ),
+                })
+                .unzip();
 
             self.current_frame_data()
                 .command_manager
-                .bind_vertex_buffers(0, &[buffer], &[offset]);
+                .bind_vertex_buffers(0, &buffers, &offsets);
+
+            self.current_frame_data().command_manager.bind_index_buffer(
+                index_buffer.buffer,
+                offset,
+                index_type,
+            );
 
             can_be_drawn = true;
             self.mesh_binds += 1;
         }
 
-        (can_be_drawn, renderable.mesh.clone(), mesh.vertex_count)
+        (can_be_drawn, mesh.submeshes.clone())
     }
 
-    fn bind_renderable_material(
+    fn upload_mesh(&mut self, mesh: &mut Mesh) {
+        if !mesh.needs_uploaded() {
+            return;
+        }
+
+        // A reloaded mesh may still have its previous buffers in use by frames in flight
+        for old_buffer in mesh.take_gpu_info() {
+            self.retired_buffers.push((self.framenumber, old_buffer));
+        }
+
+        let vertex_buffer = self
+            .boilerplate
+            .allocator
+            .create_vertex_buffer(&mesh.vertex_data());
+        let index_buffer = self
+            .boilerplate
+            .allocator
+            .create_index_buffer(&mesh.indices);
+
+        mesh.add_gpu_info(vertex_buffer, index_buffer);
+    }
+
+    /// Upload every loaded mesh of the list that is not uploaded yet, instead of waiting for
+    /// them to be drawn, like the meshes of an `AssetGroup` behind a loading screen
+    pub fn upload_meshes(&mut self, meshes: &[Handle<Mesh>], asset_manager: &AssetManager) {
+        for handle in meshes {
+            if let Some(mesh) = asset_manager.mesh(*handle) {
+                self.upload_mesh(&mut mesh.lock().unwrap());
+            }
+        }
+    }
+
+    fn upload_texture(&mut self, texture: &mut Texture) {
+        if !texture.needs_uploaded() {
+            return;
+        }
+
+        // A reloaded texture may still have its previous image in use by frames in flight
+        if let Some(old_image) = texture.take_gpu_info() {
+            self.retired_images.push((self.framenumber, old_image));
+        }
+
+        match self.boilerplate.allocator.create_texture(
+            &self.current_frame_data().command_manager,
+            &texture.pixels,
+            texture.width,
+            texture.height,
+            texture.srgb,
+            &texture.mips,
+        ) {
+            Ok(image) => texture.add_gpu_info(image),
+            Err(e) => warn!("Failed to upload texture {}: {}", texture.asset_info.id, e),
+        }
+    }
+
+    /// Upload every loaded texture of the list that is not uploaded yet, like the textures of an
+    /// `AssetGroup` behind a loading screen. Must not be called while a frame is being recorded
+    pub fn upload_textures(&mut self, textures: &[Handle<Texture>], asset_manager: &AssetManager) {
+        for handle in textures {
+            if let Some(texture) = asset_manager.texture(*handle) {
+                self.upload_texture(&mut texture.lock().unwrap());
+            }
+        }
+    }
+
+    // Destroy retired buffers and images once every frame that could have used them has finished
+    fn destroy_retired_resources(&mut self) {
+        let frame_overlap = self.config.renderer.frame_overlap as u64;
+        let framenumber = self.framenumber;
+
+        let (expired, retired): (Vec<_>, Vec<_>) = std::mem::take(&mut self.retired_buffers)
+            .into_iter()
+            .partition(|(retired_frame, _)| retired_frame + frame_overlap <= framenumber);
+
+        for (_, mut buffer) in expired {
+            self.boilerplate.allocator.destroy_buffer(&mut buffer);
+        }
+
+        self.retired_buffers = retired;
+
+        let (expired, retired): (Vec<_>, Vec<_>) = std::mem::take(&mut self.retired_images)
+            .into_iter()
+            .partition(|(retired_frame, _)| retired_frame + frame_overlap <= framenumber);
+
+        for (_, mut image) in expired {
+            self.boilerplate.allocator.destroy_texture(&mut image);
+        }
+
+        self.retired_images = retired;
+
+        let (expired, retired): (Vec<_>, Vec<_>) = std::mem::take(&mut self.retired_descriptors)
+            .into_iter()
+            .partition(|(retired_frame, _)| retired_frame + frame_overlap <= framenumber);
+
+        for (_, descriptor) in expired {
+            self.texture_descriptors.free(descriptor);
+        }
+
+        self.retired_descriptors = retired;
+    }
+
+    /// Bring every material about to be drawn up to date with its source and upload the textures
+    /// it samples, returning the ones that blend. Runs before the frame is recorded, as uploads
+    /// wait for the GPU
+    fn prepare_materials(
         &mut self,
-        renderable: &Renderable,
-    ) -> (Option<Rc<RefCell<Material>>>, String) {
-        let material = self.materials.get(&renderable.material).unwrap();
+        renderables: &[Renderable],
+        asset_manager: &AssetManager,
+    ) -> HashSet<Handle<Material>> {
+        let mut prepared: HashSet<Handle<Material>> = HashSet::new();
+        let mut blended: HashSet<Handle<Material>> = HashSet::new();
 
-        self.current_frame_data()
-            .command_manager
-            .bind_pipeline(&material.borrow().pipeline.borrow());
+        for renderable in renderables {
+            if !prepared.insert(renderable.material) {
+                continue;
+            }
+
+            let material = match self.materials.get(renderable.material) {
+                Some(material) => material,
+                None => continue,
+            };
+            let mut material = material.lock().unwrap();
+
+            self.sync_material(&mut material, asset_manager);
+
+            if material.variant.alpha == AlphaBlend::Blend {
+                blended.insert(renderable.material);
+            }
+        }
 
-        let last_material = Some(Rc::clone(self.materials.get(&renderable.material).unwrap()));
-        let last_material_id = renderable.material.clone();
+        blended
+    }
+
+    /// Pick up the parameters of the material's source once it is loaded, and again whenever it
+    /// is reloaded, then bind the texture it samples
+    fn sync_material(&mut self, material: &mut Material, asset_manager: &AssetManager) {
+        if let Some(source) = material.source.as_ref().map(|source| source.weak()) {
+            if let Some(pbr_material) = asset_manager.material(source) {
+                let pbr_material = pbr_material.lock().unwrap();
+                let properties = &pbr_material.properties;
+
+                material.base_color = properties.base_color;
+                (material.variant.alpha, material.alpha_cutoff) = match properties.alpha_mode {
+                    AlphaMode::Opaque => (AlphaBlend::Opaque, 0.0),
+                    AlphaMode::Mask(cutoff) => (AlphaBlend::Mask, cutoff),
+                    AlphaMode::Blend => (AlphaBlend::Blend, 0.0),
+                };
+                material.variant.double_sided = properties.double_sided;
+
+                let texture_id = properties
+                    .base_color_texture
+                    .as_ref()
+                    .map(|texture| &texture.id);
+
+                if texture_id != material.base_color_texture_id.as_ref() {
+                    material.base_color_texture =
+                        texture_id.map(|id| asset_manager.get_texture(id));
+                    material.base_color_texture_id = texture_id.cloned();
+                }
+
+                material.base_color_tex_coord = properties
+                    .base_color_texture
+                    .as_ref()
+                    .map_or(0, |texture| texture.tex_coord);
+            }
+        }
+
+        let view = material
+            .base_color_texture
+            .as_ref()
+            .and_then(|handle| asset_manager.texture(handle.weak()))
+            .and_then(|texture| {
+                let mut texture = texture.lock().unwrap();
+                self.upload_texture(&mut texture);
+
+                texture.gpu_info.as_ref().map(|image| image.view)
+            })
+            .unwrap_or(self.white_texture.view);
+
+        if material
+            .texture_descriptor
+            .map(|(bound_view, _)| bound_view)
+            == Some(view)
+        {
+            return;
+        }
+
+        // Frames in flight may still be using the previous set
+        match self.texture_descriptors.allocate(view) {
+            Ok(descriptor) => {
+                if let Some((_, old_descriptor)) =
+                    material.texture_descriptor.replace((view, descriptor))
+                {
+                    self.retired_descriptors
+                        .push((self.framenumber, old_descriptor));
+                }
+            }
+            Err(e) => warn!("Failed to bind the texture of a material: {}", e),
+        }
+    }
+
+    /// The material of the renderable, None for handles that are stale, like those of a material
+    /// removed since the renderable was made
+    fn renderable_material(&mut self, renderable: &Renderable) -> Option<Arc<Mutex<Material>>> {
+        let material = self.materials.get(renderable.material)?;
 
         self.material_binds += 1;
 
-        (last_material, last_material_id)
+        Some(material)
     }
 
     fn render_objects(
         &mut self,
         mut projection_matrix: glm::Mat4,
         view_matrix: glm::Mat4,
-        renderables: &[Renderable],
-        asset_manager: &mut AssetManager,
+        renderables: &[&Renderable],
+        asset_manager: &AssetManager,
     ) {
         self.mesh_binds = 0;
         self.material_binds = 0;
@@ -285,50 +738,105 @@ impl Renderer {
         projection_matrix[(1, 1)] *= -1.0;
         let view_proj_mat = projection_matrix * view_matrix;
 
-        let mut last_mesh_id: String = "".to_string();
-        let mut last_mesh_vertex_count = 0;
+        let mut last_mesh_id: Option<Handle<Mesh>> = None;
+        let mut last_mesh_submeshes: Vec<Submesh> = vec![];
+
+        let mut last_material: Option<Arc<Mutex<Material>>> = None;
+        let mut last_material_id: Option<Handle<Material>> = None;
 
-        let mut last_material: Option<Rc<RefCell<Material>>> = None;
-        let mut last_material_id: String = "".to_string();
+        let mut bound_pipeline: Option<Rc<RefCell<Pipeline>>> = None;
 
         for renderable in renderables {
-            if renderable.mesh != last_mesh_id {
-                let (can_be_drawn, last_bound_mesh_id, last_bound_mesh_vertex_count) =
-                    self.bind_renderable_mesh(renderable, asset_manager);
+            let material_changed = Some(renderable.material) != last_material_id;
+
+            if material_changed {
+                last_material = self.renderable_material(renderable);
+
+                if last_material.is_none() {
+                    last_material_id = None;
+                    continue;
+                }
+
+                last_material_id = Some(renderable.material);
+            }
+
+            // The pipeline and vertex streams to bind depend on both the attributes the material
+            // uses and the ones the mesh has
+            if Some(renderable.mesh) != last_mesh_id || material_changed {
+                let material = Arc::clone(last_material.as_ref().unwrap());
+
+                let (can_be_drawn, last_bound_mesh_submeshes) = self.bind_renderable_mesh(
+                    renderable,
+                    &material.lock().unwrap(),
+                    &mut bound_pipeline,
+                    asset_manager,
+                );
 
                 if !can_be_drawn {
+                    last_mesh_id = None;
                     continue;
                 } else {
-                    last_mesh_id = last_bound_mesh_id;
-                    last_mesh_vertex_count = last_bound_mesh_vertex_count;
+                    last_mesh_id = Some(renderable.mesh);
+                    last_mesh_submeshes = last_bound_mesh_submeshes;
                 }
-            }
 
-            if renderable.material != last_material_id {
-                (last_material, last_material_id) = self.bind_renderable_material(renderable);
+                let texture_descriptor = material.lock().unwrap().texture_descriptor;
+
+                // Every pipeline samples the set, nothing is drawn without one
+                let (_, descriptor) = match texture_descriptor {
+                    Some(texture_descriptor) => texture_descriptor,
+                    None => {
+                        last_mesh_id = None;
+                        continue;
+                    }
+                };
+
+                self.current_frame_data()
+                    .command_manager
+                    .bind_descriptor_set(
+                        bound_pipeline.as_ref().unwrap().borrow().pipeline_layout,
+                        0,
+                        descriptor,
+                    );
             }
 
             let mvp = view_proj_mat * renderable.matrix;
 
-            let push_constants = MeshPushConstants {
-                data: glm::vec4(0.0, 0.0, 0.0, 0.0),
-                render_matrix: mvp,
+            let push_constants = {
+                let material = last_material.as_ref().unwrap().lock().unwrap();
+
+                MeshPushConstants {
+                    data: material.base_color,
+                    render_matrix: mvp,
+                    parameters: glm::vec4(
+                        material.alpha_cutoff,
+                        material.base_color_tex_coord as f32,
+                        0.0,
+                        0.0,
+                    ),
+                }
             };
 
             self.current_frame_data().command_manager.push_constants(
-                last_material
-                    .as_ref()
-                    .unwrap()
-                    .borrow()
-                    .pipeline
-                    .borrow()
-                    .pipeline_layout,
+                bound_pipeline.as_ref().unwrap().borrow().pipeline_layout,
                 push_constants,
             );
 
-            self.current_frame_data()
-                .command_manager
-                .draw(last_mesh_vertex_count, 1, 0, 0);
+            let submeshes = last_mesh_submeshes
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| renderable.submesh.is_none_or(|submesh| submesh == *index))
+                .map(|(_, submesh)| submesh);
+
+            for submesh in submeshes {
+                self.current_frame_data().command_manager.draw_indexed(
+                    submesh.index_count,
+                    1,
+                    submesh.first_index,
+                    submesh.first_vertex as i32,
+                    0,
+                );
+            }
         }
 
         trace!(
@@ -344,7 +852,7 @@ impl Renderer {
         projection_matrix: glm::Mat4,
         view_matrix: glm::Mat4,
         renderables: &[Renderable],
-        asset_manager: &mut AssetManager,
+        asset_manager: &AssetManager,
     ) {
         trace!("Renderer Rendering");
 
@@ -364,6 +872,28 @@ impl Renderer {
         }
         .expect("Failed to reset fence");
 
+        self.destroy_retired_resources();
+
+        for buffer in asset_manager.take_released_buffers() {
+            self.retired_buffers.push((self.framenumber, buffer));
+        }
+
+        for image in asset_manager.take_released_images() {
+            self.retired_images.push((self.framenumber, image));
+        }
+
+        let blended_materials = self.prepare_materials(renderables, asset_manager);
+
+        // Blended materials are drawn over everything opaque, from back to front
+        let (mut blended, opaque): (Vec<&Renderable>, Vec<&Renderable>) = renderables
+            .iter()
+            .partition(|renderable| blended_materials.contains(&renderable.material));
+
+        let view_depth = |renderable: &Renderable| (view_matrix * renderable.matrix)[(2, 3)];
+        blended.sort_by(|a, b| view_depth(a).total_cmp(&view_depth(b)));
+
+        let ordered: Vec<&Renderable> = opaque.into_iter().chain(blended).collect();
+
         let (image_index, _) = self
             .boilerplate
             .swapchain
@@ -403,7 +933,7 @@ impl Renderer {
             .command_manager
             .begin_render_pass(&render_pass_begin_info);
 
-        self.render_objects(projection_matrix, view_matrix, renderables, asset_manager);
+        self.render_objects(projection_matrix, view_matrix, &ordered, asset_manager);
 
         self.current_frame_data().command_manager.end_render_pass();
 
@@ -429,22 +959,58 @@ impl Renderer {
         self.framenumber += 1;
     }
 
-    pub fn cleanup(&mut self, asset_manager: &mut AssetManager) {
+    pub fn cleanup(&mut self, asset_manager: &AssetManager) {
         trace!("Cleaning: Renderer");
 
         unsafe {
             self.boilerplate.wait_for_fences();
 
-            self.materials = HashMap::new();
+            self.materials = AssetStorage::new();
             self.pipelines = HashMap::new();
+            self.pipeline_shaders = HashMap::new();
+
+            self.boilerplate
+                .allocator
+                .destroy_buffer(&mut self.default_vertex);
+            self.boilerplate
+                .allocator
+                .destroy_texture(&mut self.white_texture);
+
+            // Every set is freed along with the pool
+            self.retired_descriptors = vec![];
+            self.texture_descriptors.destroy();
 
-            for (_, mesh_clone) in asset_manager.iter_meshes_mut().lock().unwrap().iter_mut() {
+            for (_, buffer) in self.retired_buffers.iter_mut() {
+                self.boilerplate.allocator.destroy_buffer(buffer);
+            }
+            self.retired_buffers = vec![];
+
+            for mut buffer in asset_manager.take_released_buffers() {
+                self.boilerplate.allocator.destroy_buffer(&mut buffer);
+            }
+
+            for (_, image) in self.retired_images.iter_mut() {
+                self.boilerplate.allocator.destroy_texture(image);
+            }
+            self.retired_images = vec![];
+
+            for mut image in asset_manager.take_released_images() {
+                self.boilerplate.allocator.destroy_texture(&mut image);
+            }
+
+            for mesh_clone in asset_manager.iter_meshes() {
                 let mesh_handle = mesh_clone.lock();
 
                 let mut mesh = mesh_handle.unwrap();
 
-                if let Some(gpu_info) = &mut mesh.gpu_info {
-                    self.boilerplate.allocator.destroy_buffer(gpu_info)
+                for mut buffer in mesh.take_gpu_info() {
+                    self.boilerplate.allocator.destroy_buffer(&mut buffer)
+                }
+            }
+
+            for texture in asset_manager.iter_textures() {
+                if let Some(gpu_info) = &mut texture.lock().unwrap().gpu_info {
+                    self.boilerplate.allocator.destroy_texture(gpu_info)
                 };
             }
 